@@ -53,6 +53,12 @@ fn generate_edge_cases() -> Vec<(String, Vec<String>)> {
             "abacadaeafagahaiajakal".to_string(),
             "bbbbbbbbb".to_string(),
         ]),
+
+        // Long non-matching inputs that are exponential for a naive backtracker
+        ("a*a*a*a*a*a*a*a*a*a*b".to_string(), vec![
+            "a".repeat(1000),
+            format!("{}/{}", "a".repeat(500), "a".repeat(500)),
+        ]),
     ]
 }
 
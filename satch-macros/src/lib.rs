@@ -0,0 +1,243 @@
+//! Compile-time checked glob patterns for [satch](https://docs.rs/satch).
+//!
+//! [`glob!`] validates a hard-coded pattern while the crate is compiled,
+//! so a typo such as an unclosed `[` is a compile error instead of a
+//! pattern that silently matches the wrong inputs. The parsed pattern is
+//! embedded in the binary as constant data, so nothing is parsed at run
+//! time.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use satch::__private::{Compiled, Token as CompiledToken};
+use satch::ExtglobKind;
+use syn::{Ident, LitBool, LitStr, Token};
+
+/// The boolean options `glob!` accepts, named after their `MatchOptions`
+/// builder methods.
+const OPTIONS: &[&str] = &[
+    "strict_slashes",
+    "windows",
+    "posix_slashes",
+    "dot",
+    "match_base",
+    "nocase",
+    "class_bang",
+    "strict_globstar",
+    "noglobstar",
+    "nobrace",
+    "noext",
+    "nonegate",
+    "extended_classes",
+    "graphemes",
+    "normalize_unicode",
+    "named_captures",
+    "star_matches_empty",
+    "strip_dot_slash",
+];
+
+/// Compiles a glob pattern once and returns it as a `&'static satch::Pattern`.
+///
+/// The pattern is checked like `Pattern::try_with_options` and parsed
+/// while the calling crate is compiled, and an invalid pattern fails the
+/// build. The parsed segments are embedded as a constant; the first call
+/// builds the `Pattern` from them without parsing, and later calls share
+/// it.
+///
+/// Options follow the pattern as `MatchOptions` method names, alone to
+/// turn them on or as `name = false`.
+///
+/// ```
+/// use satch_macros::glob;
+///
+/// fn is_source(path: &str) -> bool {
+///     glob!("src/**/*.{rs,toml}").is_match(path)
+/// }
+///
+/// assert!(is_source("src/lib/mod.rs"));
+/// assert!(glob!("*.MD", nocase, dot).is_match(".README.md"));
+/// assert!(glob!("@(src|lib)/!(*.test).{js,ts}").is_match("lib/app.ts"));
+/// assert_eq!(glob!("src/**/*.rs"), &satch::Pattern::new("src/**/*.rs"));
+/// ```
+///
+/// ```compile_fail
+/// // unterminated character class at byte 4
+/// satch_macros::glob!("src/[a-z.rs");
+/// ```
+#[proc_macro]
+pub fn glob(input: TokenStream) -> TokenStream {
+    match syn::parse(input) {
+        Ok(input) => expand(input).unwrap_or_else(syn::Error::into_compile_error).into(),
+        Err(error) => error.into_compile_error().into(),
+    }
+}
+
+struct GlobInput {
+    pattern: LitStr,
+    options: Vec<(Ident, bool)>,
+}
+
+impl Parse for GlobInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let pattern = input.parse()?;
+        let mut options = Vec::new();
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            for option in Punctuated::<GlobOption, Token![,]>::parse_terminated(input)? {
+                options.push((option.name, option.value));
+            }
+        }
+        Ok(Self { pattern, options })
+    }
+}
+
+struct GlobOption {
+    name: Ident,
+    value: bool,
+}
+
+impl Parse for GlobOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        if !OPTIONS.contains(&name.to_string().as_str()) {
+            let message = format!("unknown option `{name}`, expected one of: {}", OPTIONS.join(", "));
+            return Err(syn::Error::new(name.span(), message));
+        }
+        let value = if input.parse::<Option<Token![=]>>()?.is_some() {
+            input.parse::<LitBool>()?.value
+        } else {
+            true
+        };
+        Ok(Self { name, value })
+    }
+}
+
+fn expand(input: GlobInput) -> syn::Result<TokenStream2> {
+    let pattern = input.pattern.value();
+    let mut options = satch::MatchOptions::new();
+    for (name, value) in &input.options {
+        // OPTIONS にない名前はパースの時点で弾いている
+        options = match name.to_string().as_str() {
+            "strict_slashes" => options.strict_slashes(*value),
+            "windows" => options.windows(*value),
+            "posix_slashes" => options.posix_slashes(*value),
+            "dot" => options.dot(*value),
+            "match_base" => options.match_base(*value),
+            "nocase" => options.nocase(*value),
+            "class_bang" => options.class_bang(*value),
+            "strict_globstar" => options.strict_globstar(*value),
+            "noglobstar" => options.noglobstar(*value),
+            "nobrace" => options.nobrace(*value),
+            "noext" => options.noext(*value),
+            "nonegate" => options.nonegate(*value),
+            "extended_classes" => options.extended_classes(*value),
+            "graphemes" => options.graphemes(*value),
+            "normalize_unicode" => options.normalize_unicode(*value),
+            "named_captures" => options.named_captures(*value),
+            "star_matches_empty" => options.star_matches_empty(*value),
+            "strip_dot_slash" => options.strip_dot_slash(*value),
+            _ => unreachable!("unknown options are rejected while parsing"),
+        };
+    }
+    let checked = match satch::Pattern::try_with_options(&pattern, &options) {
+        Ok(checked) => checked,
+        Err(error) => return Err(syn::Error::new(input.pattern.span(), error)),
+    };
+
+    let literal = &input.pattern;
+    let setters = input.options.iter().map(|(name, value)| quote!(.#name(#value)));
+    let compiled = checked.__with_compiled(compiled_tokens);
+    Ok(quote! {{
+        const COMPILED: ::satch::__private::Compiled<'static> = #compiled;
+        static PATTERN: ::std::sync::OnceLock<::satch::Pattern> = ::std::sync::OnceLock::new();
+        PATTERN.get_or_init(|| {
+            ::satch::Pattern::__from_compiled(#literal, &::satch::MatchOptions::new() #(#setters)*, &COMPILED)
+        })
+    }})
+}
+
+/// Writes `compiled` as a constant expression.
+fn compiled_tokens(compiled: &Compiled<'_>) -> TokenStream2 {
+    let negated = compiled.negated;
+    let key = compiled.key;
+    let alternatives = compiled.alternatives.iter().map(|alternative| {
+        let chars = alternative.chars;
+        let tokens = alternative.tokens.iter().map(token_tokens);
+        let names = alternative.names.iter().map(|(index, name)| quote!((#index, #name)));
+        quote! {
+            ::satch::__private::CompiledAlternative {
+                chars: &[#(#chars),*],
+                tokens: &[#(#tokens),*],
+                names: &[#(#names),*],
+            }
+        }
+    });
+    quote! {
+        ::satch::__private::Compiled {
+            negated: #negated,
+            key: #key,
+            alternatives: &[#(#alternatives),*],
+        }
+    }
+}
+
+fn token_tokens(token: &CompiledToken<'_>) -> TokenStream2 {
+    let body = match *token {
+        CompiledToken::Literal(literal) => quote!(Literal(&[#(#literal),*])),
+        CompiledToken::AnyChar => quote!(AnyChar),
+        CompiledToken::Wildcard => quote!(Wildcard),
+        CompiledToken::Globstar { slash, requires_directory } => {
+            quote!(Globstar { slash: #slash, requires_directory: #requires_directory })
+        }
+        CompiledToken::CharClass(content) => quote!(CharClass(&[#(#content),*])),
+        CompiledToken::ExtglobStart { kind, crosses_separator } => {
+            let kind = match kind {
+                ExtglobKind::ExactlyOne => quote!(ExactlyOne),
+                ExtglobKind::ZeroOrOne => quote!(ZeroOrOne),
+                ExtglobKind::Negated => quote!(Negated),
+                ExtglobKind::OneOrMore => quote!(OneOrMore),
+                ExtglobKind::ZeroOrMore => quote!(ZeroOrMore),
+            };
+            quote!(ExtglobStart { kind: ::satch::ExtglobKind::#kind, crosses_separator: #crosses_separator })
+        }
+        CompiledToken::ExtglobBar => quote!(ExtglobBar),
+        CompiledToken::ExtglobEnd => quote!(ExtglobEnd),
+    };
+    quote!(::satch::__private::Token::#body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(tokens: TokenStream2) -> Result<String, String> {
+        let input: GlobInput = syn::parse2(tokens).map_err(|error| error.to_string())?;
+        expand(input).map(|tokens| tokens.to_string()).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_expands_to_static_pattern() {
+        let expanded = expand_str(quote!("src/**/*.rs")).unwrap();
+        assert!(expanded.contains("OnceLock"));
+        assert!(expanded.contains("__from_compiled (\"src/**/*.rs\""));
+        // パース済みのセグメントを埋め込み、実行時にはパースしない
+        assert!(expanded.contains("Token :: Globstar { slash : true , requires_directory : true }"));
+        assert!(!expanded.contains("with_options"));
+
+        let expanded = expand_str(quote!("*.md", nocase, dot = false,)).unwrap();
+        assert!(expanded.contains(". nocase (true) . dot (false)"));
+
+        // 無効にした構文は検証でも誤りにならない
+        assert!(expand_str(quote!("a{b", nobrace)).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        assert_eq!(expand_str(quote!("src/[a-z.rs")).unwrap_err(), "unterminated character class at byte 4");
+        assert_eq!(expand_str(quote!("a{b")).unwrap_err(), "unterminated brace group at byte 1");
+        assert!(expand_str(quote!("*.rs", fast)).unwrap_err().starts_with("unknown option `fast`"));
+        assert!(expand_str(quote!("*.rs", dot = 1)).is_err());
+        assert!(expand_str(quote!(src)).is_err());
+    }
+}
@@ -0,0 +1,6 @@
+//! The `cdylib` that `wasm-pack build satch-wasm` turns into a JavaScript
+//! package. The bindings live in `satch::wasm`; this crate only links them
+//! into a WebAssembly module, so the `satch` library itself stays an
+//! ordinary Rust library.
+
+pub use satch::wasm::*;
@@ -0,0 +1,470 @@
+//! A syntax tree for glob patterns.
+//!
+//! [`parse`] exposes the structure the matcher sees, so tools such as
+//! linters, converters and visualizers do not need their own parser.
+
+use std::fmt::{self, Write};
+
+use crate::brace::brace_group_at;
+use crate::class::{class_items, push_class, split_class_negation, ClassItem};
+use crate::delimiters::Delimiters;
+use crate::extglob::{split_alternatives, ExtglobKind};
+use crate::{escape, split_negation};
+
+/// A parsed glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ast {
+    /// Whether the pattern starts with a `!` negation.
+    pub negated: bool,
+    /// The pattern body, in order.
+    pub segments: Vec<Segment>,
+}
+
+/// A single element of a glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Segment {
+    /// Text matched as is. Never contains `/`.
+    Literal(String),
+    /// A `/` path separator.
+    Separator,
+    /// `?`: any single character except `/`.
+    AnyChar,
+    /// `*`: any run of characters within a path segment.
+    Star,
+    /// `**`: any run of characters, including separators.
+    Globstar,
+    /// `[...]`: one character from (or, when negated, outside) the set.
+    Class {
+        /// Whether the class starts with `^` or `!`.
+        negated: bool,
+        /// The characters, ranges and named classes in the class.
+        items: Vec<ClassItem>,
+    },
+    /// `{a,b}` or `{1..3}`: any one of the alternatives. Sequences are
+    /// expanded into literal alternatives.
+    Brace(Vec<Vec<Segment>>),
+    /// `@(a|b)`, `?(a|b)`, `!(a|b)`, `+(a|b)` or `*(a|b)`.
+    Extglob {
+        /// The operator preceding the pattern list.
+        kind: ExtglobKind,
+        /// The `|`-separated alternatives.
+        alternatives: Vec<Vec<Segment>>,
+    },
+}
+
+/// Parses `pattern` into its syntax tree.
+///
+/// Parsing never fails: syntax the matcher treats as literal text, such as
+/// an unclosed `[` or a brace group without a comma, becomes a literal.
+///
+/// ```
+/// use satch::{parse, Segment};
+///
+/// let ast = parse("src/*.{js,ts}");
+/// assert!(!ast.negated);
+/// assert_eq!(ast.segments[0], Segment::Literal("src".into()));
+/// assert_eq!(ast.segments[1], Segment::Separator);
+/// assert_eq!(ast.segments[2], Segment::Star);
+/// ```
+pub fn parse(pattern: &str) -> Ast {
+    let (negated, body) = split_negation(pattern);
+    Ast {
+        negated,
+        segments: parse_segments(&body.chars().collect::<Vec<_>>()),
+    }
+}
+
+impl Ast {
+    /// Walks the pattern body with `visitor`, in pattern order.
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        walk_segments(visitor, &self.segments);
+    }
+}
+
+/// Renders the pattern in canonical form, which parses back into the same
+/// tree. Literal text is escaped with [`escape`], and `*` directly after
+/// `*` is dropped, as `**` would read as a globstar.
+///
+/// ```
+/// use satch::parse;
+///
+/// assert_eq!(parse("!src/{a,b}/[!x]*").to_string(), "!src/{a,b}/[^x]*");
+/// assert_eq!(parse("{1..3}.\\*").to_string(), "{1,2,3}.\\*");
+/// ```
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            f.write_char('!')?;
+        }
+        write_segments(f, &self.segments)
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Literal(text) => f.write_str(&escape(text)),
+            Segment::Separator => f.write_char('/'),
+            Segment::AnyChar => f.write_char('?'),
+            Segment::Star => f.write_char('*'),
+            Segment::Globstar => f.write_str("**"),
+            Segment::Class { negated, items } => {
+                let mut class = String::new();
+                push_class(&mut class, *negated, items);
+                f.write_str(&class)
+            }
+            // 代替が1つのブレースは {a} のままだとリテラルになるので中身だけを書く
+            Segment::Brace(alternatives) if alternatives.len() == 1 => write_segments(f, &alternatives[0]),
+            Segment::Brace(alternatives) => write_alternatives(f, '{', alternatives, ',', '}'),
+            Segment::Extglob { kind, alternatives } => {
+                f.write_char(kind.operator())?;
+                write_alternatives(f, '(', alternatives, '|', ')')
+            }
+        }
+    }
+}
+
+fn write_segments(f: &mut fmt::Formatter<'_>, segments: &[Segment]) -> fmt::Result {
+    let mut previous = None;
+    for segment in segments {
+        if !(segment == &Segment::Star && previous == Some(&Segment::Star)) {
+            write!(f, "{segment}")?;
+        }
+        previous = Some(segment);
+    }
+    Ok(())
+}
+
+fn write_alternatives(
+    f: &mut fmt::Formatter<'_>,
+    open: char,
+    alternatives: &[Vec<Segment>],
+    separator: char,
+    close: char,
+) -> fmt::Result {
+    f.write_char(open)?;
+    for (idx, alternative) in alternatives.iter().enumerate() {
+        if idx > 0 {
+            f.write_char(separator)?;
+        }
+        write_segments(f, alternative)?;
+    }
+    f.write_char(close)
+}
+
+/// Callbacks for traversing an [`Ast`] with [`Ast::walk`].
+///
+/// Every method has a default: leaves do nothing, and braces, extglobs and
+/// their alternatives visit their children. Override the methods for the
+/// segments of interest, calling the matching `walk_*` function to keep
+/// descending.
+///
+/// ```
+/// use satch::{parse, Visitor};
+///
+/// #[derive(Default)]
+/// struct Literals(Vec<String>);
+///
+/// impl Visitor for Literals {
+///     fn visit_literal(&mut self, text: &str) {
+///         self.0.push(text.to_string());
+///     }
+/// }
+///
+/// let mut literals = Literals::default();
+/// parse("src/*.{js,ts}").walk(&mut literals);
+/// assert_eq!(literals.0, ["src", ".", "js", "ts"]);
+/// ```
+pub trait Visitor {
+    /// Called for every segment. The default calls the method for its kind.
+    fn visit_segment(&mut self, segment: &Segment) {
+        walk_segment(self, segment);
+    }
+
+    /// Called for each alternative of a brace group or extglob.
+    fn visit_alternative(&mut self, segments: &[Segment]) {
+        walk_segments(self, segments);
+    }
+
+    /// Called for [`Segment::Literal`].
+    fn visit_literal(&mut self, _text: &str) {}
+
+    /// Called for [`Segment::Separator`].
+    fn visit_separator(&mut self) {}
+
+    /// Called for [`Segment::AnyChar`].
+    fn visit_any_char(&mut self) {}
+
+    /// Called for [`Segment::Star`].
+    fn visit_star(&mut self) {}
+
+    /// Called for [`Segment::Globstar`].
+    fn visit_globstar(&mut self) {}
+
+    /// Called for [`Segment::Class`].
+    fn visit_class(&mut self, _negated: bool, _items: &[ClassItem]) {}
+
+    /// Called for [`Segment::Brace`].
+    fn visit_brace(&mut self, alternatives: &[Vec<Segment>]) {
+        walk_alternatives(self, alternatives);
+    }
+
+    /// Called for [`Segment::Extglob`].
+    fn visit_extglob(&mut self, _kind: ExtglobKind, alternatives: &[Vec<Segment>]) {
+        walk_alternatives(self, alternatives);
+    }
+}
+
+/// Calls the [`Visitor`] method for the kind of `segment`.
+pub fn walk_segment<V: Visitor + ?Sized>(visitor: &mut V, segment: &Segment) {
+    match segment {
+        Segment::Literal(text) => visitor.visit_literal(text),
+        Segment::Separator => visitor.visit_separator(),
+        Segment::AnyChar => visitor.visit_any_char(),
+        Segment::Star => visitor.visit_star(),
+        Segment::Globstar => visitor.visit_globstar(),
+        Segment::Class { negated, items } => visitor.visit_class(*negated, items),
+        Segment::Brace(alternatives) => visitor.visit_brace(alternatives),
+        Segment::Extglob { kind, alternatives } => visitor.visit_extglob(*kind, alternatives),
+    }
+}
+
+/// Visits each of `segments` with [`Visitor::visit_segment`].
+pub fn walk_segments<V: Visitor + ?Sized>(visitor: &mut V, segments: &[Segment]) {
+    for segment in segments {
+        visitor.visit_segment(segment);
+    }
+}
+
+/// Visits each of `alternatives` with [`Visitor::visit_alternative`].
+pub fn walk_alternatives<V: Visitor + ?Sized>(visitor: &mut V, alternatives: &[Vec<Segment>]) {
+    for alternative in alternatives {
+        visitor.visit_alternative(alternative);
+    }
+}
+
+fn parse_segments(pattern: &[char]) -> Vec<Segment> {
+    let delimiters = Delimiters::new(pattern);
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut i = 0;
+    while i < pattern.len() {
+        let (segment, next) = if let Some((kind, close_idx)) = delimiters.extglob(i) {
+            let alternatives = split_alternatives(&pattern[i + 2..close_idx])
+                .iter()
+                .map(|alt| parse_segments(alt))
+                .collect();
+            (Segment::Extglob { kind, alternatives }, close_idx + 1)
+        } else if let Some((close_idx, alternatives)) = brace_group_at(pattern, &delimiters, i) {
+            let alternatives = alternatives
+                .iter()
+                .map(|alt| parse_segments(&alt.chars().collect::<Vec<_>>()))
+                .collect();
+            (Segment::Brace(alternatives), close_idx + 1)
+        } else if let Some(class_end) = delimiters.class_end(i) {
+            (parse_class(&pattern[i + 1..class_end]), class_end + 1)
+        } else {
+            match pattern[i] {
+                '/' => (Segment::Separator, i + 1),
+                '?' => (Segment::AnyChar, i + 1),
+                '*' if pattern.get(i + 1) == Some(&'*') => (Segment::Globstar, i + 2),
+                '*' => (Segment::Star, i + 1),
+                // エスケープしても / はパスの区切りとして照合される
+                '\\' if pattern.get(i + 1) == Some(&'/') => (Segment::Separator, i + 2),
+                '\\' if i + 1 < pattern.len() => {
+                    literal.push(pattern[i + 1]);
+                    i += 2;
+                    continue;
+                }
+                c => {
+                    literal.push(c);
+                    i += 1;
+                    continue;
+                }
+            }
+        };
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(segment);
+        i = next;
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    segments
+}
+
+fn parse_class(content: &[char]) -> Segment {
+    let (negated, content) = split_class_negation(content, true);
+    Segment::Class {
+        negated,
+        items: class_items(content).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class::PosixClass;
+
+    fn literal(text: &str) -> Segment {
+        Segment::Literal(text.into())
+    }
+
+    #[test]
+    fn test_parse_wildcards_and_separators() {
+        assert_eq!(
+            parse("src/**/?.rs").segments,
+            vec![
+                literal("src"),
+                Segment::Separator,
+                Segment::Globstar,
+                Segment::Separator,
+                Segment::AnyChar,
+                literal(".rs"),
+            ]
+        );
+        // エスケープした / も区切り
+        assert_eq!(parse("a\\/b").segments, vec![literal("a"), Segment::Separator, literal("b")]);
+        assert!(crate::is_match("a/b", "a\\/b"));
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        assert_eq!(
+            parse("[^a-z_]").segments,
+            vec![Segment::Class {
+                negated: true,
+                items: vec![ClassItem::Range('a', 'z'), ClassItem::Char('_')],
+            }]
+        );
+        assert_eq!(parse("[abc").segments, vec![literal("[abc")]);
+        assert_eq!(parse("\\[a\\*]").segments, vec![literal("[a*]")]);
+        assert_eq!(
+            parse("[[:alpha:]]").segments,
+            vec![Segment::Class {
+                negated: false,
+                items: vec![ClassItem::Posix(PosixClass::Alpha)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_braces() {
+        assert_eq!(
+            parse("*.{js,t*}").segments,
+            vec![
+                Segment::Star,
+                literal("."),
+                Segment::Brace(vec![vec![literal("js")], vec![literal("t"), Segment::Star]]),
+            ]
+        );
+        assert_eq!(
+            parse("{1..3}").segments,
+            vec![Segment::Brace(vec![vec![literal("1")], vec![literal("2")], vec![literal("3")]])]
+        );
+        assert_eq!(
+            parse("{x{a,b}}").segments,
+            vec![literal("{x"), Segment::Brace(vec![vec![literal("a")], vec![literal("b")]]), literal("}")]
+        );
+    }
+
+    #[test]
+    fn test_parse_extglobs_and_negation() {
+        let ast = parse("!+(a|b*)");
+        assert!(ast.negated);
+        assert_eq!(
+            ast.segments,
+            vec![Segment::Extglob {
+                kind: ExtglobKind::OneOrMore,
+                alternatives: vec![vec![literal("a")], vec![literal("b"), Segment::Star]],
+            }]
+        );
+        assert!(!parse("!(a)").negated);
+    }
+
+    #[test]
+    fn test_visitor_walks_nested_segments() {
+        #[derive(Default)]
+        struct Counter {
+            stars: usize,
+            classes: usize,
+            depth: usize,
+            max_depth: usize,
+        }
+
+        impl Visitor for Counter {
+            fn visit_alternative(&mut self, segments: &[Segment]) {
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+                walk_segments(self, segments);
+                self.depth -= 1;
+            }
+
+            fn visit_star(&mut self) {
+                self.stars += 1;
+            }
+
+            fn visit_globstar(&mut self) {
+                self.stars += 1;
+            }
+
+            fn visit_class(&mut self, _negated: bool, _items: &[ClassItem]) {
+                self.classes += 1;
+            }
+        }
+
+        let mut counter = Counter::default();
+        parse("**/*.{js,@(a|[bc]*)}").walk(&mut counter);
+        assert_eq!(counter.stars, 3);
+        assert_eq!(counter.classes, 1);
+        assert_eq!(counter.max_depth, 2);
+        assert_eq!(counter.depth, 0);
+
+        // visit_segment を上書きすると子要素に降りるかどうかを選べる
+        struct TopLevel(usize);
+        impl Visitor for TopLevel {
+            fn visit_segment(&mut self, _segment: &Segment) {
+                self.0 += 1;
+            }
+        }
+        let mut top_level = TopLevel(0);
+        parse("a/{b,c}/*").walk(&mut top_level);
+        assert_eq!(top_level.0, 5);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for pattern in [
+            "src/**/*.{js,ts}",
+            "!**/+([0-9]).[jt]s",
+            "a\\*b/\\!c,d|e",
+            "[^a-z_\\]][[:alpha:]]",
+            "@(a|b/*)/?(x)!(y)*(z)",
+            "{a,{b,c}}/{1..3}",
+            "weird(file)",
+            "x{y}",
+            "[abc",
+            "a\\/b",
+            "",
+        ] {
+            let ast = parse(pattern);
+            let rendered = ast.to_string();
+            assert_eq!(parse(&rendered), ast, "{pattern} -> {rendered}");
+            for input in ["src/a.js", "1.ts", "a*b/!c,d|e", "weird(file)", "x{y}", "[abc", "b/x/z", "2", "a/b", ""] {
+                assert_eq!(crate::is_match(input, pattern), crate::is_match(input, &rendered), "{pattern} {input}");
+            }
+        }
+
+        let ast = Ast {
+            negated: false,
+            segments: vec![Segment::Star, Segment::Star, literal("a"), Segment::Brace(vec![vec![literal("b")]])],
+        };
+        assert_eq!(ast.to_string(), "*ab");
+        assert_eq!(Segment::Literal("[x]".into()).to_string(), "\\[x\\]");
+    }
+}
@@ -0,0 +1,307 @@
+//! Patterns as finite automata, for comparing the sets of inputs they match.
+//!
+//! The automaton follows the matcher in `lib.rs` segment by segment,
+//! including its quirks (a `**/` spans at least one `/` once anything has
+//! been consumed, and `prefix/**/*.ext` needs an intermediate directory).
+//! Two patterns are compared by running both automata over every input at
+//! once, with one representative for each group of characters no pattern
+//! tells apart.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use crate::class::class_items;
+use crate::{class_matches, ClassItem, GlobSegment, MatchOptions};
+
+/// States explored before a comparison gives up.
+const MAX_STATES: usize = 10_000;
+
+/// One step of a brace alternative, with literals split into characters.
+#[derive(Debug)]
+enum Item {
+    Char(char),
+    AnyChar,
+    Class(Vec<char>),
+    Star,
+    Globstar { slash: bool, requires_directory: bool },
+}
+
+impl Item {
+    /// Items that match once the input is used up, like [`GlobSegment::is_star`].
+    fn is_star(&self) -> bool {
+        matches!(self, Item::Star | Item::Globstar { slash: false, .. })
+    }
+}
+
+/// Where the matcher is within one alternative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Position {
+    /// Before the item at this index.
+    At(usize),
+    /// Inside the `**/` at this index, before the first `/` it spans.
+    BeforeSlash(usize),
+    /// Inside the `**/` at this index, past a `/`, free to end it here.
+    AfterSlash(usize),
+    /// Inside a final `**/`, which matches the rest of the input.
+    Rest,
+}
+
+type State = (usize, Position);
+
+/// The set of active states, or `None` once the input has been rejected
+/// before matching (a `//` under [`MatchOptions::strict_slashes`]).
+type States = Option<BTreeSet<State>>;
+
+/// A compiled pattern as an automaton over its brace alternatives.
+pub(crate) struct Automaton<'a> {
+    alternatives: Vec<Vec<Item>>,
+    /// Alternatives subject to the dotfile check.
+    dot_checked: Vec<bool>,
+    negated: bool,
+    options: &'a MatchOptions,
+}
+
+impl<'a> Automaton<'a> {
+    /// Builds the automaton, or returns `None` for what it cannot model:
+    /// extglobs, POSIX classes, the `nocase`, `match_base`, `graphemes`,
+    /// `normalize_unicode` and `max_steps` options, and `star_matches_empty`
+    /// turned off.
+    pub(crate) fn new<'s>(
+        alternatives: impl IntoIterator<Item = &'s [GlobSegment]>,
+        negated: bool,
+        options: &'a MatchOptions,
+    ) -> Option<Self> {
+        if options.nocase
+            || options.match_base
+            || options.graphemes
+            || options.normalize_unicode
+            || options.max_steps.is_some()
+            || !options.star_matches_empty
+        {
+            return None;
+        }
+        let mut items = Vec::new();
+        let mut dot_checked = Vec::new();
+        for segments in alternatives {
+            let mut alternative = Vec::new();
+            for segment in segments {
+                match segment {
+                    GlobSegment::Literal(literal) => alternative.extend(literal.iter().map(|&c| Item::Char(c))),
+                    GlobSegment::AnyChar => alternative.push(Item::AnyChar),
+                    GlobSegment::CharClass(content) => {
+                        if class_items(content).any(|item| matches!(item, ClassItem::Posix(_))) {
+                            return None;
+                        }
+                        alternative.push(Item::Class(content.clone()));
+                    }
+                    GlobSegment::Wildcard => alternative.push(Item::Star),
+                    &GlobSegment::Globstar { slash, requires_directory } => {
+                        alternative.push(Item::Globstar { slash, requires_directory })
+                    }
+                    GlobSegment::Extglob { .. } => return None,
+                }
+            }
+            dot_checked.push(
+                !options.dot && matches!(segments.first(), Some(GlobSegment::Wildcard | GlobSegment::Globstar { .. })),
+            );
+            items.push(alternative);
+        }
+        Some(Self {
+            alternatives: items,
+            dot_checked,
+            negated,
+            options,
+        })
+    }
+
+    fn start(&self) -> States {
+        Some((0..self.alternatives.len()).map(|alt| (alt, Position::At(0))).collect())
+    }
+
+    /// Returns whether the input read so far matches.
+    fn matches(&self, states: &States) -> bool {
+        // 前処理で弾かれた入力は否定パターンにもマッチしない
+        let Some(states) = states else {
+            return false;
+        };
+        let matched = states.iter().any(|&(alt, position)| self.accepts(alt, position));
+        matched != self.negated
+    }
+
+    fn accepts(&self, alt: usize, position: Position) -> bool {
+        let items = &self.alternatives[alt];
+        match position {
+            // 入力の終わりでは、残りが全て * のときだけ一致する
+            Position::At(idx) => items[idx..].iter().all(Item::is_star),
+            Position::BeforeSlash(_) => false,
+            Position::AfterSlash(idx) => self.accepts(alt, Position::At(idx + 1)),
+            Position::Rest => true,
+        }
+    }
+
+    /// Reads `c`, the character at `input_idx` (counted up to 2), after
+    /// an input whose last character was a `/` when `after_slash` is set.
+    fn step(&self, states: &States, c: char, input_idx: usize, after_slash: bool) -> States {
+        let states = states.as_ref()?;
+        // windows では先頭の // を UNC パスの始まりとして許す
+        let unc_root = self.options.windows && input_idx == 1;
+        if self.options.strict_slashes && c == '/' && after_slash && !unc_root {
+            return None;
+        }
+        let mut next = BTreeSet::new();
+        for &(alt, position) in states {
+            if input_idx == 0 && c == '.' && self.dot_checked[alt] {
+                continue;
+            }
+            let mut consumers = Vec::new();
+            self.consumers(alt, position, &mut consumers);
+            next.extend(consumers.into_iter().filter_map(|position| self.consume(alt, position, c).map(|p| (alt, p))));
+        }
+        Some(next)
+    }
+
+    /// Collects the positions reachable from `position` without reading
+    /// input that can read the next character.
+    fn consumers(&self, alt: usize, position: Position, consumers: &mut Vec<Position>) {
+        let items = &self.alternatives[alt];
+        let Position::At(idx) = position else {
+            consumers.push(position);
+            if let Position::AfterSlash(idx) = position {
+                self.consumers(alt, Position::At(idx + 1), consumers);
+            }
+            return;
+        };
+        let Some(item) = items.get(idx) else {
+            return;
+        };
+        consumers.push(position);
+        match item {
+            Item::Star | Item::Globstar { slash: false, .. } => self.consumers(alt, Position::At(idx + 1), consumers),
+            // 空文字列にマッチするのは続きがあり、中間ディレクトリが要らないときだけ
+            Item::Globstar { slash: true, requires_directory } if idx + 1 < items.len() && !requires_directory => {
+                self.consumers(alt, Position::At(idx + 1), consumers)
+            }
+            _ => {}
+        }
+    }
+
+    fn consume(&self, alt: usize, position: Position, c: char) -> Option<Position> {
+        let items = &self.alternatives[alt];
+        let idx = match position {
+            Position::At(idx) => idx,
+            Position::BeforeSlash(idx) if c == '/' => return Some(Position::AfterSlash(idx)),
+            Position::BeforeSlash(_) | Position::AfterSlash(_) | Position::Rest => return Some(position),
+        };
+        let advanced = Position::At(idx + 1);
+        match &items[idx] {
+            Item::Char(literal) => (c == *literal).then_some(advanced),
+            Item::AnyChar => (c != '/').then_some(advanced),
+            Item::Class(content) => class_matches(c, content, self.options).then_some(advanced),
+            Item::Star => (c != '/').then_some(position),
+            Item::Globstar { slash: false, .. } => Some(position),
+            Item::Globstar { slash: true, .. } if idx + 1 == items.len() => Some(Position::Rest),
+            Item::Globstar { slash: true, .. } if c == '/' => Some(Position::AfterSlash(idx)),
+            Item::Globstar { slash: true, .. } => Some(Position::BeforeSlash(idx)),
+        }
+    }
+
+    /// Adds the characters this automaton tells apart to `boundaries`.
+    fn collect_boundaries(&self, boundaries: &mut BTreeSet<char>) {
+        for item in self.alternatives.iter().flatten() {
+            match item {
+                Item::Char(c) => {
+                    boundaries.insert(*c);
+                }
+                Item::Class(content) => {
+                    for item in class_items(content) {
+                        match item {
+                            ClassItem::Char(c) => {
+                                boundaries.insert(c);
+                            }
+                            ClassItem::Range(start, end) => boundaries.extend([start, end]),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Searches for an input on which `found` holds for whether `left` and
+/// `right` match it. Returns `None` if the search is too large to finish.
+pub(crate) fn find_input(left: &Automaton, right: &Automaton, found: impl Fn(bool, bool) -> bool) -> Option<bool> {
+    // 先頭の ./ の扱いや区切り文字が違うと、同じ入力を別の文字列として照合する
+    let strip_dot_slash = left.options.strips_dot_slash();
+    if strip_dot_slash != right.options.strips_dot_slash() || left.options.separator != right.options.separator {
+        return None;
+    }
+    let alphabet = alphabet(left, right);
+    // 入力の位置は先頭の2文字だけが意味を持つ（ドットファイルと UNC パスの判定）
+    // only_dot は読んだ入力が "." だけのとき
+    let start = (0, false, false, left.start(), right.start());
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([start]);
+    while let Some((input_idx, after_slash, only_dot, left_states, right_states)) = queue.pop_front() {
+        if found(left.matches(&left_states), right.matches(&right_states)) {
+            return Some(true);
+        }
+        for &c in &alphabet {
+            // ./ で始まる入力は取り除いた残りとして照合されるので、調べなくてよい
+            if strip_dot_slash && only_dot && c == '/' {
+                continue;
+            }
+            let next = (
+                (input_idx + 1).min(2),
+                c == '/',
+                input_idx == 0 && c == '.',
+                left.step(&left_states, c, input_idx, after_slash),
+                right.step(&right_states, c, input_idx, after_slash),
+            );
+            if !seen.contains(&next) {
+                if seen.len() >= MAX_STATES {
+                    return None;
+                }
+                seen.insert(next.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    Some(false)
+}
+
+/// Picks one character from each group that both automata treat alike:
+/// every character they mention, and its neighbours for the characters
+/// in between.
+fn alphabet(left: &Automaton, right: &Automaton) -> Vec<char> {
+    let mut boundaries = BTreeSet::from(['/', '.']);
+    left.collect_boundaries(&mut boundaries);
+    right.collect_boundaries(&mut boundaries);
+    // windows や posix_slashes では \ が / に置き換わるので、入力に \ は現れない
+    let converted = left.options.converts_backslashes() || right.options.converts_backslashes();
+    let usable = |c: &char| !(converted && *c == '\\');
+    let mut alphabet = BTreeSet::new();
+    for &c in &boundaries {
+        alphabet.insert(c);
+        alphabet.extend(neighbour(c, -1, usable));
+        alphabet.extend(neighbour(c, 1, usable));
+    }
+    alphabet.into_iter().filter(usable).collect()
+}
+
+/// Returns the nearest usable character before (`-1`) or after (`1`) `c`.
+fn neighbour(c: char, direction: i64, usable: impl Fn(&char) -> bool) -> Option<char> {
+    let mut code = i64::from(u32::from(c));
+    loop {
+        code += direction;
+        if !(0..=i64::from(u32::from(char::MAX))).contains(&code) {
+            return None;
+        }
+        // サロゲートの範囲は文字にならないので飛ばす
+        if let Some(next) = u32::try_from(code).ok().and_then(char::from_u32) {
+            if usable(&next) {
+                return Some(next);
+            }
+        }
+    }
+}
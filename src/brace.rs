@@ -0,0 +1,418 @@
+//! Brace expansion (`{a,b,c}`) performed before pattern matching.
+//!
+//! The matcher only sees brace-free patterns: a pattern such as
+//! `src/*.{js,ts}` is expanded into `src/*.js` and `src/*.ts`, each is
+//! parsed into segments, and the input matches if any of the alternatives
+//! matches. Bash-style sequences
+//! (`{1..3}`, `{a..e}`, `{01..10}`, `{0..10..5}`) are expanded as well.
+
+use crate::delimiters::Delimiters;
+use crate::PatternErrorKind;
+
+/// Brace groups nested deeper than this are kept as literal text, and
+/// rejected by [`check_expansion`].
+const MAX_BRACE_DEPTH: usize = 16;
+
+/// Upper bound on the number of alternatives a single pattern may expand to.
+/// Patterns that would exceed it are matched unexpanded, and rejected by
+/// [`check_expansion`].
+const MAX_EXPANSIONS: usize = 10_000;
+
+/// Expands every brace group in `pattern` into a list of alternatives.
+///
+/// Groups may be nested (`{a,b{c,d}}` expands to `a`, `bc`, `bd`). A group
+/// that is neither a comma list nor a valid sequence (`{a}`), or that has no
+/// closing brace, is kept as literal text, as in bash. Braces inside a
+/// character class are not expanded.
+pub(crate) fn expand_braces(pattern: &str) -> Vec<String> {
+    expand_at_depth(pattern, 0).unwrap_or_else(|| vec![pattern.to_string()])
+}
+
+/// Expands the brace groups in `pattern` into concrete strings, without
+/// matching anything, like the `braces` npm package's `expand`.
+///
+/// This is the expansion every pattern goes through before matching:
+/// comma lists and sequences expand, nested groups included, while `{a}`,
+/// unclosed groups, escaped braces and braces inside `[...]` stay as
+/// written. A pattern that would expand to more than 10,000 strings is
+/// returned unexpanded.
+///
+/// ```
+/// use satch::expand;
+///
+/// assert_eq!(expand("file.{js,ts,tsx}"), ["file.js", "file.ts", "file.tsx"]);
+/// assert_eq!(expand("v{1..3}/{a,b{c,d}}"), ["v1/a", "v1/bc", "v1/bd", "v2/a", "v2/bc", "v2/bd", "v3/a", "v3/bc", "v3/bd"]);
+/// assert_eq!(expand("notes.md"), ["notes.md"]);
+/// ```
+pub fn expand(pattern: &str) -> Vec<String> {
+    expand_braces(pattern)
+}
+
+/// Checks that `pattern` stays within the limits past which
+/// [`expand_braces`] keeps braces as literal text, for the strict
+/// constructors.
+pub(crate) fn check_expansion(pattern: &str) -> Result<(), PatternErrorKind> {
+    count_expansions(pattern, 0).map(|_| ())
+}
+
+/// Returns how many strings `pattern` expands to, following
+/// [`expand_at_depth`] without building them.
+fn count_expansions(pattern: &str, depth: usize) -> Result<usize, PatternErrorKind> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let groups = brace_groups(&chars);
+    if depth > MAX_BRACE_DEPTH && !groups.is_empty() {
+        return Err(PatternErrorKind::TooDeeplyNested);
+    }
+    let mut count = 1usize;
+    for (open, close) in groups {
+        let body = &chars[open + 1..close];
+        let alternatives = match expand_sequence(&body.iter().collect::<String>()) {
+            Some(sequence) => sequence.len(),
+            None => {
+                let mut alternatives = 0usize;
+                for alternative in split_alternatives(body) {
+                    alternatives = alternatives.saturating_add(count_expansions(&alternative, depth + 1)?);
+                }
+                alternatives
+            }
+        };
+        count = count.saturating_mul(alternatives);
+        if count > MAX_EXPANSIONS {
+            return Err(PatternErrorKind::TooManyExpansions);
+        }
+    }
+    Ok(count)
+}
+
+/// Returns `None` when the expansion would exceed [`MAX_EXPANSIONS`].
+fn expand_at_depth(pattern: &str, depth: usize) -> Option<Vec<String>> {
+    let chars: Vec<char> = pattern.chars().collect();
+
+    // 深すぎるネストは展開せずリテラルとして扱う
+    if depth > MAX_BRACE_DEPTH {
+        return Some(vec![pattern.to_string()]);
+    }
+
+    // 左のグループの選択肢ごとに、右のグループの選択肢を順に並べる
+    let mut expanded = vec![String::new()];
+    let mut start = 0;
+    for (open, close) in brace_groups(&chars) {
+        let literal: String = chars[start..open].iter().collect();
+        let body = &chars[open + 1..close];
+        let alternatives = match expand_sequence(&body.iter().collect::<String>()) {
+            Some(sequence) => sequence,
+            None => {
+                let mut alternatives = Vec::new();
+                for alternative in split_alternatives(body) {
+                    alternatives.extend(expand_at_depth(&alternative, depth + 1)?);
+                }
+                alternatives
+            }
+        };
+        if expanded.len().saturating_mul(alternatives.len()) > MAX_EXPANSIONS {
+            return None;
+        }
+        let mut next = Vec::with_capacity(expanded.len() * alternatives.len());
+        for head in &expanded {
+            for alternative in &alternatives {
+                next.push(format!("{}{}{}", head, literal, alternative));
+            }
+        }
+        expanded = next;
+        start = close + 1;
+    }
+    let suffix: String = chars[start..].iter().collect();
+    for head in &mut expanded {
+        head.push_str(&suffix);
+    }
+    Some(expanded)
+}
+
+/// Finds the first expandable `{...}` group, returning the indices of its braces.
+pub(crate) fn find_brace_group(chars: &[char]) -> Option<(usize, usize)> {
+    brace_groups(chars).first().copied()
+}
+
+/// Returns the first expandable `{...}` group, then the first one after it,
+/// and so on: the groups expanded side by side at the top level.
+fn brace_groups(chars: &[char]) -> Vec<(usize, usize)> {
+    let delimiters = Delimiters::new(chars);
+    // 開いているグループごとに、{ の位置とトップレベルの , や入れ子の { を含むか
+    let mut open_groups: Vec<(usize, bool, bool)> = Vec::new();
+    let mut expandable = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                // エスケープされた文字はブレースの構文として扱わない
+                i += 2;
+                continue;
+            }
+            '[' => {
+                // 文字クラス内のブレースは展開しない
+                if let Some(end) = delimiters.class_end(i) {
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '{' => {
+                if let Some((_, _, nested)) = open_groups.last_mut() {
+                    *nested = true;
+                }
+                open_groups.push((i, false, false));
+            }
+            ',' => {
+                if let Some((_, comma, _)) = open_groups.last_mut() {
+                    *comma = true;
+                }
+            }
+            '}' => {
+                // 入れ子の { を含む本体は連番にならない
+                if let Some((open, comma, nested)) = open_groups.pop() {
+                    if comma || (!nested && expand_sequence(&chars[open + 1..i].iter().collect::<String>()).is_some()) {
+                        expandable.push((open, i));
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    // 閉じた順に見つかるので、開く位置の順に並べ直してから、前のグループの後ろで開くものだけを残す
+    expandable.sort_unstable();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (open, close) in expandable {
+        if groups.last().is_none_or(|&(_, last_close)| open > last_close) {
+            groups.push((open, close));
+        }
+    }
+    groups
+}
+
+/// If an expandable group opens at `open`, returns the index of its closing
+/// brace and its alternatives. Sequences are expanded; nested groups inside
+/// the alternatives are left as written.
+pub(crate) fn brace_group_at(chars: &[char], delimiters: &Delimiters, open: usize) -> Option<(usize, Vec<String>)> {
+    let close = delimiters.matching_brace(open)?;
+    let body = &chars[open + 1..close];
+    if let Some(sequence) = expand_sequence(&body.iter().collect::<String>()) {
+        return Some((close, sequence));
+    }
+    has_top_level_comma(body).then(|| (close, split_alternatives(body)))
+}
+
+/// Calls `f` with the index of every comma not nested in braces or a class.
+fn for_each_top_level_comma(body: &[char], mut f: impl FnMut(usize)) {
+    let delimiters = Delimiters::new(body);
+    let mut depth = 0;
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            '\\' => {
+                // エスケープされた文字はブレースの構文として扱わない
+                i += 2;
+                continue;
+            }
+            '[' => {
+                if let Some(end) = delimiters.class_end(i) {
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => f(i),
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+fn has_top_level_comma(body: &[char]) -> bool {
+    let mut found = false;
+    for_each_top_level_comma(body, |_| found = true);
+    found
+}
+
+fn split_alternatives(body: &[char]) -> Vec<String> {
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+    for_each_top_level_comma(body, |comma| {
+        alternatives.push(body[start..comma].iter().collect());
+        start = comma + 1;
+    });
+    alternatives.push(body[start..].iter().collect());
+    alternatives
+}
+
+/// Expands a sequence body such as `1..10`, `a..e` or `01..10..2`.
+///
+/// Returns `None` if `body` is not a valid sequence expression.
+fn expand_sequence(body: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = body.split("..").collect();
+    let (start, end, step) = match parts.as_slice() {
+        [start, end] => (*start, *end, None),
+        [start, end, step] => (*start, *end, Some(step.parse::<i64>().ok()?)),
+        _ => return None,
+    };
+    // 0ステップはbashと同様に1として扱う
+    let step = step.map(|s| s.unsigned_abs().max(1)).unwrap_or(1);
+
+    if let (Ok(from), Ok(to)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        if from.abs_diff(to) / step >= MAX_EXPANSIONS as u64 {
+            return None;
+        }
+        // 先頭が0の場合はゼロ埋めの桁数を保持する（例: 01..10）
+        let width = if is_zero_padded(start) || is_zero_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        let values = stepped_range(from, to, step);
+        return Some(
+            values
+                .map(|n| {
+                    if n < 0 {
+                        format!("-{:0width$}", n.unsigned_abs(), width = width.saturating_sub(1))
+                    } else {
+                        format!("{:0width$}", n, width = width)
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    let mut start_chars = start.chars();
+    let mut end_chars = end.chars();
+    match (start_chars.next(), start_chars.next(), end_chars.next(), end_chars.next()) {
+        (Some(from), None, Some(to), None) if from.is_ascii_alphabetic() && to.is_ascii_alphabetic() => Some(
+            stepped_range(from as i64, to as i64, step)
+                .filter_map(|c| char::from_u32(c as u32))
+                .map(String::from)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn is_zero_padded(number: &str) -> bool {
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+/// Iterates from `from` to `to` inclusive, counting down when `from > to`.
+fn stepped_range(from: i64, to: i64, step: u64) -> impl Iterator<Item = i64> {
+    let count = from.abs_diff(to) / step + 1;
+    // ステップは i64 に収まらないことがあるので i128 で数える。各項は from と to の間にある
+    let step = if from <= to { i128::from(step) } else { -i128::from(step) };
+    (0..count).map(move |i| (i128::from(from) + i128::from(i) * step) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_without_braces() {
+        assert_eq!(expand_braces("src/*.js"), vec!["src/*.js"]);
+    }
+
+    #[test]
+    fn test_expand_simple_alternation() {
+        assert_eq!(expand_braces("*.{js,ts,jsx}"), vec!["*.js", "*.ts", "*.jsx"]);
+        assert_eq!(expand_braces("{a,}b"), vec!["ab", "b"]);
+    }
+
+    #[test]
+    fn test_expand_multiple_groups() {
+        assert_eq!(
+            expand_braces("{src,lib}/*.{js,ts}"),
+            vec!["src/*.js", "src/*.ts", "lib/*.js", "lib/*.ts"]
+        );
+    }
+
+    #[test]
+    fn test_expand_numeric_sequence() {
+        assert_eq!(expand_braces("file{1..3}.txt"), vec!["file1.txt", "file2.txt", "file3.txt"]);
+        assert_eq!(expand_braces("{3..1}"), vec!["3", "2", "1"]);
+        assert_eq!(expand_braces("{-1..1}"), vec!["-1", "0", "1"]);
+        assert_eq!(expand_braces("{0..10..5}"), vec!["0", "5", "10"]);
+        // i64 に収まらないステップでもオーバーフローしない
+        assert_eq!(
+            expand_braces("{-9223372036854775808..9223372036854775807..-9223372036854775808}"),
+            vec!["-9223372036854775808", "0"]
+        );
+        assert_eq!(expand_braces("{9223372036854775807..-9223372036854775808..9223372036854775807}").len(), 3);
+    }
+
+    #[test]
+    fn test_expand_zero_padded_sequence() {
+        let expanded = expand_braces("{01..10}");
+        assert_eq!(expanded.len(), 10);
+        assert_eq!(expanded[0], "01");
+        assert_eq!(expanded[8], "09");
+        assert_eq!(expanded[9], "10");
+    }
+
+    #[test]
+    fn test_expand_alphabetic_sequence() {
+        assert_eq!(expand_braces("log-{a..c}.txt"), vec!["log-a.txt", "log-b.txt", "log-c.txt"]);
+        assert_eq!(expand_braces("{C..A}"), vec!["C", "B", "A"]);
+    }
+
+    #[test]
+    fn test_expand_invalid_sequence_is_literal() {
+        assert_eq!(expand_braces("{1..}"), vec!["{1..}"]);
+        assert_eq!(expand_braces("{a..10}"), vec!["{a..10}"]);
+        assert_eq!(expand_braces("{ab..cd}"), vec!["{ab..cd}"]);
+    }
+
+    #[test]
+    fn test_expand_nested_braces() {
+        assert_eq!(expand_braces("{a,b{c,d}}"), vec!["a", "bc", "bd"]);
+        assert_eq!(
+            expand_braces("src/{lib,test/{unit,e2e}}/*.js"),
+            vec!["src/lib/*.js", "src/test/unit/*.js", "src/test/e2e/*.js"]
+        );
+        assert_eq!(expand_braces("{a,{1..2}}"), vec!["a", "1", "2"]);
+        assert_eq!(expand_braces("{x{a,b}}"), vec!["{xa}", "{xb}"]);
+    }
+
+    #[test]
+    fn test_expand_pathological_nesting_is_bounded() {
+        let deep = format!("{}x{}", "{a,".repeat(64), "}".repeat(64));
+        let expanded = expand_braces(&deep);
+        assert!(expanded.len() <= MAX_EXPANSIONS);
+
+        let wide = "{a,b,c,d,e,f,g,h,i,j}".repeat(8);
+        assert_eq!(expand_braces(&wide), vec![wide.clone()]);
+        assert_eq!(expand_braces("{1..100000000}"), vec!["{1..100000000}"]);
+    }
+
+    #[test]
+    fn test_check_expansion() {
+        assert_eq!(check_expansion("{a,b{c,d}}/{1..3}"), Ok(()));
+        assert_eq!(check_expansion(&"{a,b,c,d}".repeat(6)), Ok(()));
+        assert_eq!(check_expansion(&"{a,b,c,d,e,f,g,h,i,j}".repeat(5)), Err(PatternErrorKind::TooManyExpansions));
+        let deep = |depth| format!("{}x{}", "{a,".repeat(depth), "}".repeat(depth));
+        assert_eq!(check_expansion(&deep(17)), Ok(()));
+        assert_eq!(check_expansion(&deep(18)), Err(PatternErrorKind::TooDeeplyNested));
+        assert_eq!(check_expansion("{1..100000000}"), Ok(()));
+    }
+
+    #[test]
+    fn test_public_expand() {
+        assert_eq!(expand("file.{js,ts,tsx}"), vec!["file.js", "file.ts", "file.tsx"]);
+        assert_eq!(expand("{a..c}{1..2}").len(), 6);
+        // エスケープはパターンとして書かれたまま残る
+        assert_eq!(expand("\\{a,b}.{x,y}"), vec!["\\{a,b}.x", "\\{a,b}.y"]);
+        assert_eq!(expand(""), vec![""]);
+    }
+
+    #[test]
+    fn test_expand_keeps_literal_braces() {
+        assert_eq!(expand_braces("{a}"), vec!["{a}"]);
+        assert_eq!(expand_braces("{a,b"), vec!["{a,b"]);
+        assert_eq!(expand_braces("[{,}]"), vec!["[{,}]"]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Building patterns from typed parts.
+
+use crate::class::push_class;
+use crate::{escape, ClassItem, MatchOptions, Pattern};
+
+/// Builds a [`Pattern`] piece by piece, escaping literal text so that
+/// user-supplied names never turn into glob syntax.
+///
+/// The parts are written out as a pattern source, so the result matches
+/// exactly like the same pattern written by hand, and
+/// [`Pattern::as_str`] shows what was built.
+///
+/// ```
+/// use satch::PatternBuilder;
+///
+/// let pattern = PatternBuilder::new().literal("src").sep().globstar().sep().wildcard_ext("rs").build();
+/// assert_eq!(pattern.as_str(), "src/**/*.rs");
+/// assert!(pattern.is_match("src/lib/main.rs"));
+///
+/// let pattern = PatternBuilder::new().literal("[draft] notes").sep().wildcard().build();
+/// assert!(pattern.is_match("[draft] notes/todo.md"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PatternBuilder {
+    source: String,
+    negated: bool,
+    options: MatchOptions,
+}
+
+impl PatternBuilder {
+    /// Creates an empty builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `text` matched as is. A `/` in `text` still separates
+    /// directories.
+    pub fn literal(mut self, text: &str) -> Self {
+        self.source.push_str(&escape(text));
+        self
+    }
+
+    /// Appends a `/` separator.
+    pub fn sep(mut self) -> Self {
+        self.source.push('/');
+        self
+    }
+
+    /// Appends `?`, matching any single character except `/`.
+    pub fn any_char(mut self) -> Self {
+        self.source.push('?');
+        self
+    }
+
+    /// Appends `*`, matching any run of characters within a path segment.
+    /// Nothing is appended directly after `*` or `**`, as `**` would read
+    /// as a globstar.
+    pub fn wildcard(mut self) -> Self {
+        if !self.ends_with_star() {
+            self.source.push('*');
+        }
+        self
+    }
+
+    /// Appends `**`, matching any number of directories when it is a whole
+    /// path segment.
+    pub fn globstar(mut self) -> Self {
+        self.source.push_str("**");
+        self
+    }
+
+    /// Appends `*.ext`, matching file names with the extension `ext`
+    /// (given without the dot).
+    pub fn wildcard_ext(self, ext: &str) -> Self {
+        self.wildcard().literal(".").literal(ext)
+    }
+
+    /// Appends a character class matching one character from `items`.
+    /// An empty `items` adds nothing.
+    pub fn class(self, items: &[ClassItem]) -> Self {
+        self.push_class(false, items)
+    }
+
+    /// Appends a character class matching one character not in `items`.
+    /// An empty `items` adds nothing.
+    pub fn negated_class(self, items: &[ClassItem]) -> Self {
+        self.push_class(true, items)
+    }
+
+    /// Appends a brace group matching any one of the literal
+    /// `alternatives`. A single alternative is appended as a literal, and
+    /// an empty list adds nothing.
+    pub fn any_of<S: AsRef<str>>(mut self, alternatives: &[S]) -> Self {
+        match alternatives {
+            [] => self,
+            [only] => self.literal(only.as_ref()),
+            _ => {
+                self.source.push('{');
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        self.source.push(',');
+                    }
+                    self.source.push_str(&escape(alternative.as_ref()));
+                }
+                self.source.push('}');
+                self
+            }
+        }
+    }
+
+    /// Negates the pattern, so it matches every input the parts do not.
+    pub fn negate(mut self, yes: bool) -> Self {
+        self.negated = yes;
+        self
+    }
+
+    /// Sets the options the pattern is compiled with.
+    pub fn options(mut self, options: MatchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the pattern source built so far, without the negation.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Compiles the pattern.
+    pub fn build(&self) -> Pattern {
+        let source = if self.negated { format!("!{}", self.source) } else { self.source.clone() };
+        Pattern::with_options(&source, &self.options)
+    }
+
+    /// Returns `true` if the source ends with an unescaped `*`.
+    fn ends_with_star(&self) -> bool {
+        let Some(rest) = self.source.strip_suffix('*') else {
+            return false;
+        };
+        // 直前の \ が奇数個なら * はエスケープされている
+        rest.chars().rev().take_while(|&c| c == '\\').count() % 2 == 0
+    }
+
+    fn push_class(mut self, negated: bool, items: &[ClassItem]) -> Self {
+        if items.is_empty() {
+            return self;
+        }
+        push_class(&mut self.source, negated, items);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PosixClass;
+
+    #[test]
+    fn test_builds_pattern_source() {
+        let pattern = PatternBuilder::new().literal("src").sep().globstar().sep().wildcard_ext("rs").build();
+        assert_eq!(pattern.as_str(), "src/**/*.rs");
+
+        let builder = PatternBuilder::new()
+            .literal("a*b")
+            .any_char()
+            .any_of(&["js", "t,s"])
+            .class(&[ClassItem::Range('0', '9'), ClassItem::Char(']')])
+            .negated_class(&[ClassItem::Posix(PosixClass::Alpha)]);
+        assert_eq!(builder.as_str(), "a\\*b?{js,t\\,s}[0-9\\]][^[:alpha:]]");
+        assert_eq!(builder.clone().negate(true).build().as_str(), "!a\\*b?{js,t\\,s}[0-9\\]][^[:alpha:]]");
+    }
+
+    #[test]
+    fn test_repeated_wildcards_stay_single() {
+        assert_eq!(PatternBuilder::new().wildcard().wildcard().as_str(), "*");
+        assert_eq!(PatternBuilder::new().globstar().wildcard().as_str(), "**");
+        assert_eq!(PatternBuilder::new().literal("a*").wildcard().as_str(), "a\\**");
+        assert_eq!(PatternBuilder::new().literal("a\\").wildcard().wildcard().as_str(), "a\\\\*");
+
+        let pattern = PatternBuilder::new().literal("src").sep().wildcard().wildcard().build();
+        assert!(pattern.is_match("src/main.rs"));
+        assert!(!pattern.is_match("src/bin/main.rs"));
+    }
+
+    #[test]
+    fn test_built_pattern_matches() {
+        let pattern = PatternBuilder::new().literal("!notes (1)").sep().wildcard_ext("[md]").build();
+        assert!(!pattern.is_negated());
+        assert!(pattern.is_match("!notes (1)/a.[md]"));
+        assert!(!pattern.is_match("!notes (1)/a.m"));
+
+        let pattern = PatternBuilder::new().class(&[ClassItem::Char('^'), ClassItem::Char('-')]).build();
+        assert!(pattern.is_match("^"));
+        assert!(pattern.is_match("-"));
+        assert!(!pattern.is_match("a"));
+
+        let pattern = PatternBuilder::new().any_of(&["a{b}"]).literal("x").build();
+        assert!(pattern.is_match("a{b}x"));
+
+        let pattern = PatternBuilder::new()
+            .wildcard_ext("JS")
+            .negate(true)
+            .options(MatchOptions::new().nocase(true))
+            .build();
+        assert!(!pattern.is_match("a.js"));
+        assert!(pattern.is_match("a.ts"));
+    }
+}
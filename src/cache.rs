@@ -0,0 +1,158 @@
+//! A process-wide cache of compiled patterns.
+//!
+//! The one-shot functions such as [`is_match`](crate::is_match) compile
+//! their pattern on every call. With the `cache` feature (enabled by
+//! default) they look it up here first, so callers that cannot hold on to
+//! a [`Pattern`] still compile each pattern once.
+
+use crate::{MatchOptions, Pattern};
+
+/// Runs `f` with `pattern` compiled under `options`, reusing a cached
+/// compilation when there is one.
+#[cfg(feature = "cache")]
+pub(crate) fn with_pattern<R>(pattern: &str, options: &MatchOptions, f: impl FnOnce(&Pattern) -> R) -> R {
+    f(&lru::get_or_compile(pattern, options))
+}
+
+#[cfg(not(feature = "cache"))]
+pub(crate) fn with_pattern<R>(pattern: &str, options: &MatchOptions, f: impl FnOnce(&Pattern) -> R) -> R {
+    f(&Pattern::with_options(pattern, options))
+}
+
+#[cfg(feature = "cache")]
+pub use lru::{clear_cache, set_cache_capacity};
+
+#[cfg(feature = "cache")]
+mod lru {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+    use crate::{MatchOptions, Pattern};
+
+    /// The number of patterns kept when [`set_cache_capacity`] is never called.
+    const DEFAULT_CAPACITY: usize = 256;
+
+    type Key = (String, MatchOptions);
+
+    struct Lru {
+        capacity: usize,
+        /// 参照のたびに増える時刻。最も古いエントリから追い出す
+        clock: u64,
+        entries: HashMap<Key, (Arc<Pattern>, u64)>,
+    }
+
+    impl Lru {
+        fn new(capacity: usize) -> Self {
+            Self {
+                capacity,
+                clock: 0,
+                entries: HashMap::new(),
+            }
+        }
+
+        fn get(&mut self, key: &Key) -> Option<Arc<Pattern>> {
+            self.clock += 1;
+            let (compiled, used) = self.entries.get_mut(key)?;
+            *used = self.clock;
+            Some(Arc::clone(compiled))
+        }
+
+        fn insert(&mut self, key: Key, compiled: Arc<Pattern>) {
+            if self.capacity == 0 {
+                return;
+            }
+            self.evict_to(self.capacity - 1);
+            self.clock += 1;
+            self.entries.insert(key, (compiled, self.clock));
+        }
+
+        fn set_capacity(&mut self, capacity: usize) {
+            self.capacity = capacity;
+            self.evict_to(capacity);
+        }
+
+        fn evict_to(&mut self, len: usize) {
+            while self.entries.len() > len {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, used))| *used)
+                    .map(|(key, _)| key.clone());
+                match oldest {
+                    Some(key) => self.entries.remove(&key),
+                    None => break,
+                };
+            }
+        }
+    }
+
+    fn cache() -> MutexGuard<'static, Lru> {
+        static CACHE: OnceLock<Mutex<Lru>> = OnceLock::new();
+        CACHE
+            .get_or_init(|| Mutex::new(Lru::new(DEFAULT_CAPACITY)))
+            .lock()
+            // パニックしたスレッドがあってもキャッシュの内容は壊れていない
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(super) fn get_or_compile(pattern: &str, options: &MatchOptions) -> Arc<Pattern> {
+        let key = (pattern.to_string(), options.clone());
+        if let Some(compiled) = cache().get(&key) {
+            return compiled;
+        }
+        // コンパイル中はロックを保持しない
+        let compiled = Arc::new(Pattern::with_options(pattern, options));
+        cache().insert(key, Arc::clone(&compiled));
+        compiled
+    }
+
+    /// Sets how many compiled patterns the one-shot functions such as
+    /// [`is_match`](crate::is_match) keep, evicting the least recently used
+    /// ones beyond it. The default is 256; `0` disables caching.
+    ///
+    /// Requires the `cache` feature, which is enabled by default.
+    pub fn set_cache_capacity(capacity: usize) {
+        cache().set_capacity(capacity);
+    }
+
+    /// Drops every cached pattern.
+    ///
+    /// Requires the `cache` feature, which is enabled by default.
+    pub fn clear_cache() {
+        cache().entries.clear();
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn key(pattern: &str) -> Key {
+            (pattern.to_string(), MatchOptions::default())
+        }
+
+        fn insert(lru: &mut Lru, pattern: &str) -> Arc<Pattern> {
+            let compiled = Arc::new(Pattern::new(pattern));
+            lru.insert(key(pattern), Arc::clone(&compiled));
+            compiled
+        }
+
+        #[test]
+        fn test_lru_evicts_least_recently_used() {
+            let mut lru = Lru::new(2);
+            let a = insert(&mut lru, "a");
+            insert(&mut lru, "b");
+            assert!(Arc::ptr_eq(&lru.get(&key("a")).unwrap(), &a));
+            insert(&mut lru, "c");
+            // b が最も長く参照されていない
+            assert!(lru.get(&key("b")).is_none());
+            assert!(lru.get(&key("a")).is_some() && lru.get(&key("c")).is_some());
+
+            lru.set_capacity(1);
+            assert_eq!(lru.entries.len(), 1);
+            assert!(lru.get(&key("c")).is_some());
+            lru.set_capacity(0);
+            insert(&mut lru, "d");
+            assert!(lru.entries.is_empty());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Unicode default case folding for `nocase` matching.
+//!
+//! Folding can change the length of the text (`ß` folds to `ss`), so both
+//! the input and the literal parts of a pattern are folded before matching,
+//! and match positions are mapped back to the original input.
+
+use caseless::Caseless;
+
+use crate::delimiters::Delimiters;
+
+/// Input text after case folding.
+pub(crate) struct FoldedInput {
+    pub(crate) text: String,
+    /// origin[i]: 折りたたみ後のi文字目に対応する元の入力の文字インデックス（末尾に番兵）
+    origin: Vec<usize>,
+}
+
+impl FoldedInput {
+    pub(crate) fn new(input: &str) -> Self {
+        let mut text = String::with_capacity(input.len());
+        let mut origin = Vec::with_capacity(input.len() + 1);
+        let mut count = 0;
+        for (index, c) in input.chars().enumerate() {
+            for folded in std::iter::once(c).default_case_fold() {
+                text.push(folded);
+                origin.push(index);
+            }
+            count = index + 1;
+        }
+        origin.push(count);
+        Self { text, origin }
+    }
+
+    /// Maps a character range of the folded text to the original input.
+    pub(crate) fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.origin[start], self.origin[end])
+    }
+}
+
+/// Folds the literal text of a brace-free pattern, leaving the contents of
+/// character classes as written.
+pub(crate) fn fold_pattern(pattern: &[char]) -> Vec<char> {
+    let delimiters = Delimiters::new(pattern);
+    let mut folded = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        if pattern[i] == '[' {
+            if let Some(class_end) = delimiters.class_end(i) {
+                // 文字クラスは1文字単位で比較するため、そのまま残す
+                folded.extend_from_slice(&pattern[i..=class_end]);
+                i = class_end + 1;
+                continue;
+            }
+        }
+        if pattern[i] == '\\' && i + 1 < pattern.len() {
+            // エスケープ記号は残し、続く文字だけを畳み込む
+            folded.push('\\');
+            i += 1;
+        }
+        folded.extend(std::iter::once(pattern[i]).default_case_fold());
+        i += 1;
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_input_maps_spans() {
+        let folded = FoldedInput::new("Straße");
+        assert_eq!(folded.text, "strasse");
+        assert_eq!(folded.original_span(0, 7), (0, 6));
+        assert_eq!(folded.original_span(4, 7), (4, 6));
+    }
+
+    #[test]
+    fn test_fold_pattern_keeps_classes() {
+        let pattern: Vec<char> = "STRASSE.[A-Z]*".chars().collect();
+        assert_eq!(fold_pattern(&pattern).iter().collect::<String>(), "strasse.[A-Z]*");
+    }
+}
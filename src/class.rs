@@ -0,0 +1,250 @@
+//! Bracket expression (`[...]`) syntax.
+//!
+//! The matchers, the parser and the regex converter all read classes
+//! through this module, and locate them through
+//! [`Delimiters`](crate::delimiters::Delimiters), so they agree on where a
+//! class ends.
+
+use std::ops::Range;
+
+/// A member of a character class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClassItem {
+    /// A single character.
+    Char(char),
+    /// An inclusive range such as `a-z`. A reversed range matches nothing;
+    /// [`Pattern::try_new`](crate::Pattern::try_new) rejects it.
+    Range(char, char),
+    /// A named class such as `[:alpha:]`.
+    Posix(PosixClass),
+}
+
+impl ClassItem {
+    /// Returns whether `c` is a member of this item.
+    pub fn contains(self, c: char) -> bool {
+        match self {
+            ClassItem::Char(member) => c == member,
+            ClassItem::Range(start, end) => start <= c && c <= end,
+            ClassItem::Posix(class) => class.contains(c),
+        }
+    }
+}
+
+/// A POSIX named class, written `[:name:]` inside a bracket expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PosixClass {
+    /// `[:alpha:]`: alphabetic characters.
+    Alpha,
+    /// `[:digit:]`: `0` to `9`.
+    Digit,
+    /// `[:alnum:]`: alphabetic or numeric characters.
+    Alnum,
+    /// `[:space:]`: whitespace.
+    Space,
+    /// `[:upper:]`: uppercase characters.
+    Upper,
+    /// `[:lower:]`: lowercase characters.
+    Lower,
+    /// `[:punct:]`: ASCII punctuation.
+    Punct,
+    /// `[:xdigit:]`: hexadecimal digits.
+    Xdigit,
+}
+
+impl PosixClass {
+    /// Returns the class written as `[:name:]`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "alpha" => PosixClass::Alpha,
+            "digit" => PosixClass::Digit,
+            "alnum" => PosixClass::Alnum,
+            "space" => PosixClass::Space,
+            "upper" => PosixClass::Upper,
+            "lower" => PosixClass::Lower,
+            "punct" => PosixClass::Punct,
+            "xdigit" => PosixClass::Xdigit,
+            _ => return None,
+        })
+    }
+
+    /// The name written between `[:` and `:]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PosixClass::Alpha => "alpha",
+            PosixClass::Digit => "digit",
+            PosixClass::Alnum => "alnum",
+            PosixClass::Space => "space",
+            PosixClass::Upper => "upper",
+            PosixClass::Lower => "lower",
+            PosixClass::Punct => "punct",
+            PosixClass::Xdigit => "xdigit",
+        }
+    }
+
+    /// Returns whether `c` belongs to the class. Letters and whitespace
+    /// follow Unicode; digits and punctuation are ASCII only.
+    pub fn contains(self, c: char) -> bool {
+        match self {
+            PosixClass::Alpha => c.is_alphabetic(),
+            PosixClass::Digit => c.is_ascii_digit(),
+            PosixClass::Alnum => c.is_alphanumeric(),
+            PosixClass::Space => c.is_whitespace(),
+            PosixClass::Upper => c.is_uppercase(),
+            PosixClass::Lower => c.is_lowercase(),
+            PosixClass::Punct => c.is_ascii_punctuation(),
+            PosixClass::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+}
+
+/// Splits the text between the brackets into its negation flag and members.
+/// A leading `!` negates only when `bang` is set.
+pub(crate) fn split_class_negation(content: &[char], bang: bool) -> (bool, &[char]) {
+    match content.first() {
+        Some('^') => (true, &content[1..]),
+        Some('!') if bang => (true, &content[1..]),
+        _ => (false, content),
+    }
+}
+
+/// Reads the members of a class body (the text after any negation).
+/// Escaped characters are members even when they are `-` or `]`.
+pub(crate) fn class_items(content: &[char]) -> impl Iterator<Item = ClassItem> + '_ {
+    class_item_spans(content).map(|(_, item)| item)
+}
+
+/// Like [`class_items`], also yielding the range of `content` each member spans.
+pub(crate) fn class_item_spans(content: &[char]) -> impl Iterator<Item = (Range<usize>, ClassItem)> + '_ {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        if i >= content.len() {
+            return None;
+        }
+        let start_idx = i;
+        if let Some((class, len)) = posix_class_at(&content[i..]) {
+            i += len;
+            return Some((start_idx..i, ClassItem::Posix(class)));
+        }
+        let (start, len) = class_char_at(&content[i..]);
+        i += len;
+        // 末尾の - は範囲ではなく通常の要素
+        if content.get(i) == Some(&'-') && i + 1 < content.len() {
+            let (end, len) = class_char_at(&content[i + 1..]);
+            i += 1 + len;
+            return Some((start_idx..i, ClassItem::Range(start, end)));
+        }
+        Some((start_idx..i, ClassItem::Char(start)))
+    })
+}
+
+/// Writes a class matching one character from (or, when `negated`, outside)
+/// `items`, escaping the characters with a meaning inside brackets.
+pub(crate) fn push_class(source: &mut String, negated: bool, items: &[ClassItem]) {
+    source.push('[');
+    if negated {
+        source.push('^');
+    }
+    for item in items {
+        match *item {
+            ClassItem::Char(c) => push_class_char(source, c),
+            ClassItem::Range(start, end) => {
+                push_class_char(source, start);
+                source.push('-');
+                push_class_char(source, end);
+            }
+            ClassItem::Posix(class) => {
+                source.push_str("[:");
+                source.push_str(class.name());
+                source.push_str(":]");
+            }
+        }
+    }
+    source.push(']');
+}
+
+fn push_class_char(source: &mut String, c: char) {
+    if matches!(c, '\\' | ']' | '[' | '-' | '^' | '!') {
+        source.push('\\');
+    }
+    source.push(c);
+}
+
+/// Reads one possibly escaped character, returning it and its length.
+fn class_char_at(chars: &[char]) -> (char, usize) {
+    match chars {
+        ['\\', escaped, ..] => (*escaped, 2),
+        [c, ..] => (*c, 1),
+        [] => unreachable!("class_char_at called past the end of the class"),
+    }
+}
+
+/// If `chars` starts with a known `[:name:]`, returns the class and its length.
+pub(crate) fn posix_class_at(chars: &[char]) -> Option<(PosixClass, usize)> {
+    if chars.get(..2)? != ['[', ':'] {
+        return None;
+    }
+    let name_len = chars[2..].iter().position(|&c| c == ':')?;
+    if chars.get(2 + name_len + 1) != Some(&']') {
+        return None;
+    }
+    let name: String = chars[2..2 + name_len].iter().collect();
+    PosixClass::from_name(&name).map(|class| (class, name_len + 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delimiters::Delimiters;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn test_find_class_end_skips_posix_classes() {
+        let find_class_end = |pattern: &[char], open| Delimiters::new(pattern).class_end(open);
+        assert_eq!(find_class_end(&chars("[[:alpha:]]x"), 0), Some(10));
+        assert_eq!(find_class_end(&chars("[a[:digit:]_]"), 0), Some(12));
+        // 未知の名前はPOSIXクラスとして扱わない
+        assert_eq!(find_class_end(&chars("[[:foo:]]"), 0), Some(7));
+        assert_eq!(find_class_end(&chars("[abc"), 0), None);
+        // 先頭の ] とエスケープされた ] はクラスの要素
+        assert_eq!(find_class_end(&chars("[]]"), 0), Some(2));
+        assert_eq!(find_class_end(&chars("[a\\]]"), 0), Some(4));
+        assert_eq!(find_class_end(&chars("[^]a]"), 0), Some(4));
+        assert_eq!(find_class_end(&chars("[!]a]"), 0), Some(4));
+        assert_eq!(find_class_end(&chars("[]"), 0), None);
+        assert_eq!(find_class_end(&chars("abc]"), 0), None);
+    }
+
+    #[test]
+    fn test_class_items() {
+        assert_eq!(
+            class_items(&chars("a-z[:digit:]_")).collect::<Vec<_>>(),
+            vec![ClassItem::Range('a', 'z'), ClassItem::Posix(PosixClass::Digit), ClassItem::Char('_')]
+        );
+        assert_eq!(
+            class_items(&chars("a\\-z\\]")).collect::<Vec<_>>(),
+            vec![ClassItem::Char('a'), ClassItem::Char('-'), ClassItem::Char('z'), ClassItem::Char(']')]
+        );
+        assert_eq!(
+            class_items(&chars("\\!-\\]a-")).collect::<Vec<_>>(),
+            vec![ClassItem::Range('!', ']'), ClassItem::Char('a'), ClassItem::Char('-')]
+        );
+    }
+
+    #[test]
+    fn test_posix_class_membership() {
+        assert!(PosixClass::Alpha.contains('é'));
+        assert!(!PosixClass::Alpha.contains('1'));
+        assert!(PosixClass::Alnum.contains('7'));
+        assert!(PosixClass::Space.contains('\t'));
+        assert!(PosixClass::Upper.contains('Q') && !PosixClass::Upper.contains('q'));
+        assert!(PosixClass::Punct.contains('-') && !PosixClass::Punct.contains('a'));
+        assert!(PosixClass::Xdigit.contains('F') && !PosixClass::Xdigit.contains('g'));
+        assert_eq!(PosixClass::from_name("digit"), Some(PosixClass::Digit));
+        assert_eq!(PosixClass::Digit.name(), "digit");
+    }
+}
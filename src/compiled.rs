@@ -0,0 +1,162 @@
+//! The compiled form of a pattern as plain data, for `satch-macros`.
+//!
+//! `glob!` compiles its pattern while the calling crate is built and embeds
+//! the result as a constant [`Compiled`], which
+//! [`Pattern::__from_compiled`](crate::Pattern::__from_compiled) turns back
+//! into a pattern without parsing anything. None of this is public API: the
+//! layout follows the matcher's internals and changes with them.
+
+use crate::extglob::ExtglobKind;
+use crate::GlobSegment;
+
+/// A compiled pattern, holding everything its source would be parsed into.
+#[derive(Debug, Clone, Copy)]
+pub struct Compiled<'a> {
+    pub negated: bool,
+    /// The key the pattern compares and hashes by.
+    pub key: &'a str,
+    pub alternatives: &'a [CompiledAlternative<'a>],
+}
+
+/// One brace alternative of a [`Compiled`] pattern.
+#[derive(Debug, Clone, Copy)]
+pub struct CompiledAlternative<'a> {
+    /// The alternative as matched, after the rewrites compiling applies.
+    pub chars: &'a [char],
+    pub tokens: &'a [Token<'a>],
+    /// The `{name}` placeholders, with the index of the wildcard each became.
+    pub names: &'a [(usize, &'a str)],
+}
+
+/// One segment of an alternative. Extglobs are flattened into a start
+/// token, their alternatives separated by [`Token::ExtglobBar`], and an end
+/// token, so the tokens of an alternative form a single slice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    Literal(&'a [char]),
+    AnyChar,
+    Wildcard,
+    Globstar { slash: bool, requires_directory: bool },
+    CharClass(&'a [char]),
+    ExtglobStart { kind: ExtglobKind, crosses_separator: bool },
+    ExtglobBar,
+    ExtglobEnd,
+}
+
+/// Flattens `segments` into tokens borrowing their text.
+pub(crate) fn to_tokens(segments: &[GlobSegment]) -> Vec<Token<'_>> {
+    let mut tokens = Vec::with_capacity(segments.len());
+    push_tokens(segments, &mut tokens);
+    tokens
+}
+
+fn push_tokens<'a>(segments: &'a [GlobSegment], tokens: &mut Vec<Token<'a>>) {
+    for segment in segments {
+        let token = match segment {
+            GlobSegment::Literal(literal) => Token::Literal(literal),
+            GlobSegment::AnyChar => Token::AnyChar,
+            GlobSegment::Wildcard => Token::Wildcard,
+            GlobSegment::Globstar { slash, requires_directory } => Token::Globstar {
+                slash: *slash,
+                requires_directory: *requires_directory,
+            },
+            GlobSegment::CharClass(content) => Token::CharClass(content),
+            GlobSegment::Extglob { kind, alternatives, crosses_separator } => {
+                tokens.push(Token::ExtglobStart {
+                    kind: *kind,
+                    crosses_separator: *crosses_separator,
+                });
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        tokens.push(Token::ExtglobBar);
+                    }
+                    push_tokens(alternative, tokens);
+                }
+                Token::ExtglobEnd
+            }
+        };
+        tokens.push(token);
+    }
+}
+
+/// Rebuilds the segments [`to_tokens`] flattened.
+pub(crate) fn from_tokens(tokens: &[Token<'_>]) -> Vec<GlobSegment> {
+    let mut rest = tokens;
+    let segments = take_segments(&mut rest);
+    debug_assert!(rest.is_empty(), "unbalanced extglob tokens");
+    segments
+}
+
+/// Reads segments from the front of `tokens` up to the end of the
+/// alternative, leaving the `|` or `)` that ends it.
+fn take_segments(tokens: &mut &[Token<'_>]) -> Vec<GlobSegment> {
+    let mut segments = Vec::new();
+    while let Some((token, rest)) = tokens.split_first() {
+        let segment = match *token {
+            Token::Literal(literal) => GlobSegment::Literal(literal.to_vec()),
+            Token::AnyChar => GlobSegment::AnyChar,
+            Token::Wildcard => GlobSegment::Wildcard,
+            Token::Globstar { slash, requires_directory } => GlobSegment::Globstar { slash, requires_directory },
+            Token::CharClass(content) => GlobSegment::CharClass(content.to_vec()),
+            Token::ExtglobStart { kind, crosses_separator } => {
+                *tokens = rest;
+                let mut alternatives = vec![take_segments(tokens)];
+                // 代替パターンの区切りを読み飛ばし、閉じ括弧で抜ける
+                while let Some((separator, rest)) = tokens.split_first() {
+                    *tokens = rest;
+                    if *separator == Token::ExtglobEnd {
+                        break;
+                    }
+                    alternatives.push(take_segments(tokens));
+                }
+                segments.push(GlobSegment::Extglob {
+                    kind,
+                    alternatives,
+                    crosses_separator,
+                });
+                continue;
+            }
+            Token::ExtglobBar | Token::ExtglobEnd => break,
+        };
+        segments.push(segment);
+        *tokens = rest;
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_glob_segments, MatchOptions, Pattern};
+
+    #[test]
+    fn test_tokens_round_trip() {
+        for pattern in ["src/**/*.rs", "a?[b-d]", "@(a|+(b|c*)|)/x", "!(*.js)", "**/", ""] {
+            let chars: Vec<char> = pattern.chars().collect();
+            let segments = parse_glob_segments(&chars);
+            assert_eq!(from_tokens(&to_tokens(&segments)), segments, "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_pattern_round_trip() {
+        let cases = [
+            ("src/**/*.{rs,toml}", MatchOptions::new()),
+            ("!@(a|b*)/[!x]?.js", MatchOptions::new()),
+            ("*.MD", MatchOptions::new().nocase(true)),
+            ("{dir}/{name}.rs", MatchOptions::new().named_captures(true)),
+        ];
+        let inputs = ["src/lib/mod.rs", "src/Cargo.toml", "a/yz.js", "bc/xz.js", "README.md", "src/main.rs"];
+        for (source, options) in cases {
+            let pattern = Pattern::with_options(source, &options);
+            let rebuilt = pattern.__with_compiled(|compiled| Pattern::__from_compiled(source, &options, compiled));
+            assert_eq!(rebuilt, pattern);
+            assert_eq!(rebuilt.is_negated(), pattern.is_negated());
+            for input in inputs {
+                assert_eq!(rebuilt.is_match(input), pattern.is_match(input), "{source} {input}");
+                assert_eq!(rebuilt.captures(input), pattern.captures(input), "{source} {input}");
+                assert_eq!(rebuilt.named_captures(input), pattern.named_captures(input), "{source} {input}");
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Estimating how expensive a pattern can be to match, for
+//! [`Pattern::complexity`](crate::Pattern::complexity).
+
+use crate::GlobSegment;
+
+/// A size and cost estimate for a compiled pattern.
+///
+/// Services matching user-supplied patterns can compare these numbers
+/// against their own limits before matching anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Complexity {
+    /// The number of patterns the braces expand to, each matched in turn.
+    pub alternatives: usize,
+    /// The most segments in one alternative, counting those inside
+    /// extglobs. A run of literal text is one segment.
+    pub segments: usize,
+    /// The number of `**` over all alternatives.
+    pub globstars: usize,
+    /// How deeply extglobs nest, `0` without extglobs.
+    pub extglob_depth: usize,
+    /// How matching time grows with the input, in the worst case.
+    pub worst_case: WorstCase,
+}
+
+/// How matching time grows with the input length, from cheapest to most
+/// expensive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum WorstCase {
+    /// Each input character is compared once per alternative.
+    #[default]
+    Linear,
+    /// `*` and `**` try several splits of the input, at most the input
+    /// length times the segment count per alternative.
+    Backtracking,
+    /// Extglobs match every span of the input against their own patterns,
+    /// which grows faster than the input length, more so when nested.
+    Extglob,
+}
+
+/// Estimates the complexity of a pattern from its brace alternatives.
+pub(crate) fn estimate<'s>(alternatives: impl IntoIterator<Item = &'s [GlobSegment]>) -> Complexity {
+    let mut complexity = Complexity::default();
+    for segments in alternatives {
+        complexity.alternatives += 1;
+        let mut count = 0;
+        add_segments(segments, 0, &mut count, &mut complexity);
+        complexity.segments = complexity.segments.max(count);
+    }
+    complexity
+}
+
+fn add_segments(segments: &[GlobSegment], depth: usize, count: &mut usize, complexity: &mut Complexity) {
+    *count += segments.len();
+    for segment in segments {
+        let worst_case = match segment {
+            GlobSegment::Wildcard => WorstCase::Backtracking,
+            GlobSegment::Globstar { .. } => {
+                complexity.globstars += 1;
+                WorstCase::Backtracking
+            }
+            GlobSegment::Extglob { alternatives, .. } => {
+                complexity.extglob_depth = complexity.extglob_depth.max(depth + 1);
+                for alternative in alternatives {
+                    add_segments(alternative, depth + 1, count, complexity);
+                }
+                WorstCase::Extglob
+            }
+            GlobSegment::Literal(_) | GlobSegment::AnyChar | GlobSegment::CharClass(_) => WorstCase::Linear,
+        };
+        complexity.worst_case = complexity.worst_case.max(worst_case);
+    }
+}
@@ -0,0 +1,144 @@
+//! The ends of every class, extglob and brace group in a pattern.
+//!
+//! Scanners that walk a whole pattern look for these constructs at many
+//! positions. Scanning forward from each opener for its end would take time
+//! quadratic in the pattern length, or cubic when openers of one kind sit
+//! inside another that never closes, so [`Delimiters`] finds the ends for
+//! every position in one pass from the end of the pattern.
+
+use crate::class::posix_class_at;
+use crate::extglob::ExtglobKind;
+
+/// The ends of the classes, parenthesized groups and brace groups opening
+/// at each position of a pattern.
+pub(crate) struct Delimiters<'a> {
+    pattern: &'a [char],
+    /// members_end[i]: i からクラスの要素を読み進めたときに最初にクラスを閉じる ] の位置
+    members_end: Vec<Option<usize>>,
+    /// paren_ends[i]: i の ( と対応する ) の位置
+    paren_ends: Vec<Option<usize>>,
+    /// brace_ends[i]: i の { と対応する } の位置
+    brace_ends: Vec<Option<usize>>,
+}
+
+impl<'a> Delimiters<'a> {
+    pub(crate) fn new(pattern: &'a [char]) -> Self {
+        let len = pattern.len();
+        let mut members_end = vec![None; len + 3];
+        for i in (0..len).rev() {
+            members_end[i] = if pattern[i] == '\\' {
+                // エスケープされた文字はクラスを閉じない
+                members_end[i + 2]
+            } else if let Some((_, class_len)) = posix_class_at(&pattern[i..]) {
+                // [:name:] の中の ] はクラスを閉じない
+                members_end[i + class_len]
+            } else if pattern[i] == ']' {
+                Some(i)
+            } else {
+                members_end[i + 1]
+            };
+        }
+        let mut delimiters =
+            Self { pattern, members_end, paren_ends: vec![None; len], brace_ends: vec![None; len] };
+        delimiters.paren_ends = delimiters.group_ends('(', ')');
+        delimiters.brace_ends = delimiters.group_ends('{', '}');
+        delimiters
+    }
+
+    /// Returns the index of the `]` closing a class opened at `open`, or
+    /// `None` when there is no class there. A `]` right after `[`, `[^` or
+    /// `[!` is a member, not the end of the class, and so is an escaped `\]`.
+    pub(crate) fn class_end(&self, open: usize) -> Option<usize> {
+        if self.pattern.get(open) != Some(&'[') {
+            return None;
+        }
+        let mut i = open + 1;
+        if matches!(self.pattern.get(i), Some('^' | '!')) {
+            i += 1;
+        }
+        if self.pattern.get(i) == Some(&']') {
+            i += 1;
+        }
+        self.members_end[i]
+    }
+
+    /// If an extglob starts at `start`, returns its kind and the index of the
+    /// closing `)`. An operator without a balanced `)` is not an extglob.
+    pub(crate) fn extglob(&self, start: usize) -> Option<(ExtglobKind, usize)> {
+        let kind = ExtglobKind::from_char(*self.pattern.get(start)?)?;
+        Some((kind, self.paren_ends.get(start + 1).copied().flatten()?))
+    }
+
+    /// Returns the index of the `}` balancing the `{` at `open`.
+    pub(crate) fn matching_brace(&self, open: usize) -> Option<usize> {
+        self.brace_ends.get(open).copied().flatten()
+    }
+
+    /// Returns the position after the escape, class or character at `i`.
+    fn next(&self, i: usize) -> usize {
+        match self.pattern[i] {
+            '\\' => i + 2,
+            '[' => self.class_end(i).map_or(i + 1, |end| end + 1),
+            _ => i + 1,
+        }
+    }
+
+    /// Pairs each `open` with the `close` that balances it, skipping escaped
+    /// characters and classes.
+    fn group_ends(&self, open: char, close: char) -> Vec<Option<usize>> {
+        let len = self.pattern.len();
+        let mut ends = vec![None; len];
+        // first_close[i]: i から入れ子のグループを飛ばしながら進んだときに最初に出会う、対応のない close の位置
+        let mut first_close = vec![None; len + 2];
+        for i in (0..len).rev() {
+            let c = self.pattern[i];
+            let next = self.next(i).min(len + 1);
+            first_close[i] = if c == close {
+                Some(i)
+            } else if c == open {
+                ends[i] = first_close[next];
+                ends[i].and_then(|end| first_close[self.next(end).min(len + 1)])
+            } else {
+                first_close[next]
+            };
+        }
+        ends
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn test_matching_brace() {
+        let pattern = chars("{a,{b,c}[}]d}{");
+        let delimiters = Delimiters::new(&pattern);
+        assert_eq!(delimiters.matching_brace(0), Some(12));
+        assert_eq!(delimiters.matching_brace(3), Some(7));
+        // 閉じていない { と { 以外の位置
+        assert_eq!(delimiters.matching_brace(13), None);
+        assert_eq!(delimiters.matching_brace(1), None);
+        // エスケープされた } とクラスの中の } はグループを閉じない
+        let pattern = chars("{a\\}b[}]}");
+        assert_eq!(Delimiters::new(&pattern).matching_brace(0), Some(8));
+    }
+
+    #[test]
+    fn test_groups_skip_classes_and_escapes() {
+        let pattern = chars("@(a|[)]|b\\)c)");
+        assert_eq!(Delimiters::new(&pattern).extglob(0), Some((ExtglobKind::ExactlyOne, 12)));
+        // [:name:] の中の ] はクラスを閉じないので、クラスの外の ) は閉じない
+        let pattern = chars("+([[:alpha:])])");
+        assert_eq!(Delimiters::new(&pattern).extglob(0), Some((ExtglobKind::OneOrMore, 14)));
+        // 閉じていない外側のグループの中でも、内側のグループは閉じる
+        let pattern = chars("!(a@(b)c");
+        let delimiters = Delimiters::new(&pattern);
+        assert_eq!(delimiters.extglob(0), None);
+        assert_eq!(delimiters.extglob(3), Some((ExtglobKind::ExactlyOne, 6)));
+    }
+}
@@ -0,0 +1,260 @@
+//! Validation of user-supplied patterns.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::brace::check_expansion;
+use crate::class::{class_item_spans, split_class_negation, ClassItem};
+use crate::delimiters::Delimiters;
+use crate::MatchOptions;
+
+/// Why a pattern was rejected by [`Pattern::try_new`](crate::Pattern::try_new)
+/// or [`try_match`](crate::try_match), and where.
+///
+/// ```
+/// use satch::{try_match, PatternErrorKind};
+///
+/// let error = try_match("a", "src/[a-z.rs").unwrap_err();
+/// assert_eq!(error.kind(), &PatternErrorKind::UnclosedClass);
+/// assert_eq!(error.span(), 4..5);
+/// assert_eq!(error.to_string(), "unterminated character class at byte 4");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    kind: PatternErrorKind,
+    span: Range<usize>,
+}
+
+/// The kind of a [`PatternError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatternErrorKind {
+    /// A `[` without a closing `]`.
+    UnclosedClass,
+    /// A `{` without a matching `}`.
+    UnclosedBrace,
+    /// An extglob operator such as `@(` without a matching `)`.
+    UnclosedExtglob,
+    /// A `\` at the end of the pattern.
+    DanglingEscape,
+    /// A class range whose start sorts after its end, such as `z-a`.
+    ReversedRange,
+    /// A pattern longer than [`MatchOptions::max_pattern_len`](crate::MatchOptions::max_pattern_len).
+    /// The span covers the bytes past the limit.
+    TooLong,
+    /// A pattern with more segments than
+    /// [`MatchOptions::max_segments`](crate::MatchOptions::max_segments).
+    /// The span covers the whole pattern.
+    TooManySegments,
+    /// Extglobs nested more than 32 levels deep, or brace groups nested
+    /// more than 16. The span covers the whole pattern.
+    TooDeeplyNested,
+    /// Braces that would expand to more than 10,000 patterns. The span
+    /// covers the whole pattern.
+    TooManyExpansions,
+}
+
+impl PatternError {
+    fn new(kind: PatternErrorKind, span: Range<usize>) -> Self {
+        Self { kind, span }
+    }
+
+    /// Returns what is wrong with the pattern.
+    pub fn kind(&self) -> &PatternErrorKind {
+        &self.kind
+    }
+
+    /// Returns the byte range of the offending construct in the pattern,
+    /// such as the opening `[` of an unterminated class.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Returns the byte offset at which the offending construct starts.
+    pub fn offset(&self) -> usize {
+        self.span.start
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self.kind {
+            PatternErrorKind::UnclosedClass => "unterminated character class",
+            PatternErrorKind::UnclosedBrace => "unterminated brace group",
+            PatternErrorKind::UnclosedExtglob => "unterminated extglob",
+            PatternErrorKind::DanglingEscape => "dangling escape",
+            PatternErrorKind::ReversedRange => "reversed character range",
+            PatternErrorKind::TooLong => "pattern exceeds the length limit",
+            PatternErrorKind::TooManySegments => "pattern exceeds the segment limit",
+            PatternErrorKind::TooDeeplyNested => "extglobs or braces nested too deeply",
+            PatternErrorKind::TooManyExpansions => "braces expand to too many patterns",
+        };
+        write!(f, "{} at byte {}", message, self.span.start)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Returned by [`Pattern::try_is_match`](crate::Pattern::try_is_match)
+/// when matching an input takes more steps than
+/// [`MatchOptions::max_steps`](crate::MatchOptions::max_steps) allows.
+///
+/// ```
+/// use satch::{MatchOptions, Pattern};
+///
+/// let pattern = Pattern::with_options("+(a|aa)", &MatchOptions::new().max_steps(100));
+/// let error = pattern.try_is_match(&"a".repeat(1000)).unwrap_err();
+/// assert_eq!(error.limit(), 100);
+/// assert_eq!(error.to_string(), "matching exceeded the limit of 100 steps");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepLimitExceeded {
+    limit: usize,
+}
+
+impl StepLimitExceeded {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+
+    /// Returns the step limit that was exceeded.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl fmt::Display for StepLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "matching exceeded the limit of {} steps", self.limit)
+    }
+}
+
+impl std::error::Error for StepLimitExceeded {}
+
+/// Rejects patterns longer than `max_len` bytes.
+pub(crate) fn check_length(pattern: &str, max_len: usize) -> Result<(), PatternError> {
+    if pattern.len() > max_len {
+        return Err(PatternError::new(PatternErrorKind::TooLong, max_len..pattern.len()));
+    }
+    Ok(())
+}
+
+/// An error about `pattern` as a whole, such as exceeding a limit.
+pub(crate) fn whole_pattern_error(kind: PatternErrorKind, pattern: &str) -> PatternError {
+    PatternError::new(kind, 0..pattern.len())
+}
+
+/// Rejects syntax that the lenient matcher would silently treat as literal text.
+/// Braces and extglobs turned off in `options` are literal text anyway.
+pub(crate) fn validate(pattern: &str, options: &MatchOptions) -> Result<(), PatternError> {
+    let (offsets, chars): (Vec<usize>, Vec<char>) = pattern.char_indices().unzip();
+    // chars[start..end] が占めるバイト範囲
+    let span = |start: usize, end: usize| offsets[start]..offsets.get(end).copied().unwrap_or(pattern.len());
+    let delimiters = Delimiters::new(&chars);
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 == chars.len() => {
+                return Err(PatternError::new(PatternErrorKind::DanglingEscape, span(i, i + 1)));
+            }
+            '\\' => {
+                i += 2;
+                continue;
+            }
+            '[' => match delimiters.class_end(i) {
+                Some(class_end) => {
+                    // 逆順の範囲（z-a）はどの文字にもマッチしないため誤りとして報告する
+                    let (_, body) = split_class_negation(&chars[i + 1..class_end], options.class_bang);
+                    let body_start = class_end - body.len();
+                    for (range, item) in class_item_spans(body) {
+                        if let ClassItem::Range(start, end) = item {
+                            if start > end {
+                                return Err(PatternError::new(
+                                    PatternErrorKind::ReversedRange,
+                                    span(body_start + range.start, body_start + range.end),
+                                ));
+                            }
+                        }
+                    }
+                    i = class_end + 1;
+                    continue;
+                }
+                None => return Err(PatternError::new(PatternErrorKind::UnclosedClass, span(i, i + 1))),
+            },
+            '{' if !options.nobrace && delimiters.matching_brace(i).is_none() => {
+                return Err(PatternError::new(PatternErrorKind::UnclosedBrace, span(i, i + 1)));
+            }
+            '@' | '?' | '!' | '+' | '*'
+                if !options.noext && chars.get(i + 1) == Some(&'(') && delimiters.extglob(i).is_none() =>
+            {
+                return Err(PatternError::new(PatternErrorKind::UnclosedExtglob, span(i, i + 2)));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if !options.nobrace {
+        check_expansion(pattern).map_err(|kind| whole_pattern_error(kind, pattern))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_accepts_well_formed_patterns() {
+        for pattern in ["src/**/*.rs", "[a-z]*.{js,ts}", "@(a|b)/!(c).js", "{a}", "[{]", "\\!a", "[a-a]", "[a-]", "[]-a]", ""] {
+            assert_eq!(validate(pattern, &MatchOptions::default()), Ok(()), "{}", pattern);
+        }
+        // class_bang を切ると ! はリテラルなので、[!--!] は !-- の範囲と ! になる
+        let options = MatchOptions::new().class_bang(false);
+        assert_eq!(validate("[!--!]", &options), Ok(()));
+        assert_eq!(validate("[!--!]", &MatchOptions::default()).unwrap_err().kind(), &PatternErrorKind::ReversedRange);
+    }
+
+    #[test]
+    fn test_validate_rejects_malformed_patterns() {
+        let kind = |pattern: &str| validate(pattern, &MatchOptions::default()).unwrap_err().kind().clone();
+        assert_eq!(kind("src/[abc"), PatternErrorKind::UnclosedClass);
+        assert_eq!(kind("*.{js,ts"), PatternErrorKind::UnclosedBrace);
+        assert_eq!(kind("@(a|b"), PatternErrorKind::UnclosedExtglob);
+        assert_eq!(kind("a?(b"), PatternErrorKind::UnclosedExtglob);
+        assert_eq!(kind("a\\"), PatternErrorKind::DanglingEscape);
+        assert_eq!(kind("[z-a]"), PatternErrorKind::ReversedRange);
+        assert_eq!(kind("[^a-c9-0]"), PatternErrorKind::ReversedRange);
+        assert_eq!(kind(&"{a,b,c,d,e,f,g,h,i,j}".repeat(5)), PatternErrorKind::TooManyExpansions);
+        assert_eq!(kind(&format!("{}x{}", "{a,".repeat(18), "}".repeat(18))), PatternErrorKind::TooDeeplyNested);
+
+        // 無効にした構文はリテラルなので閉じていなくてもよい
+        let options = MatchOptions::new().nobrace(true).noext(true);
+        assert_eq!(validate("*.{js,ts", &options), Ok(()));
+        assert_eq!(validate("@(a|b", &options), Ok(()));
+        assert_eq!(validate(&"{a,b,c,d,e,f,g,h,i,j}".repeat(5), &options), Ok(()));
+        assert_eq!(validate("[abc", &options).unwrap_err().kind(), &PatternErrorKind::UnclosedClass);
+    }
+
+    #[test]
+    fn test_error_spans_are_byte_offsets() {
+        assert_eq!(validate("src/[abc", &MatchOptions::default()).unwrap_err().span(), 4..5);
+        assert_eq!(validate("a?(b", &MatchOptions::default()).unwrap_err().span(), 1..3);
+        assert_eq!(validate("a\\", &MatchOptions::default()).unwrap_err().span(), 1..2);
+        assert_eq!(validate("x[ab\\]-\\!]", &MatchOptions::default()).unwrap_err().span(), 4..9);
+        // マルチバイト文字の後ろではバイト単位の位置を返す
+        let error = validate("日本/{a,b", &MatchOptions::default()).unwrap_err();
+        assert_eq!(error.span(), 7..8);
+        assert_eq!(error.to_string(), "unterminated brace group at byte 7");
+    }
+
+    #[test]
+    fn test_limit_errors() {
+        assert_eq!(check_length("abcd", 4), Ok(()));
+        let error = check_length("abcdef", 4).unwrap_err();
+        assert_eq!((error.kind(), error.span()), (&PatternErrorKind::TooLong, 4..6));
+        let error = whole_pattern_error(PatternErrorKind::TooManySegments, "日本*");
+        assert_eq!(error.span(), 0..7);
+        assert_eq!(error.to_string(), "pattern exceeds the segment limit at byte 0");
+    }
+}
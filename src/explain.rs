@@ -0,0 +1,96 @@
+//! Reports on how a pattern matched an input, for [`Pattern::explain`](crate::Pattern::explain).
+
+use std::fmt;
+use std::ops::Range;
+
+/// How [`Pattern::explain`](crate::Pattern::explain) saw an input.
+///
+/// The `Display` form lists each brace alternative that was tried with the
+/// text each segment consumed, or where it stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTrace {
+    /// The input as matched, after the normalization of the
+    /// [`windows`](crate::MatchOptions::windows) and
+    /// [`strip_dot_slash`](crate::MatchOptions::strip_dot_slash) options.
+    /// Spans index into it.
+    pub input: String,
+    /// Whether the pattern matches the input, negation included.
+    pub matched: bool,
+    /// Whether the input was rejected before matching because it contains
+    /// `//` under [`strict_slashes`](crate::MatchOptions::strict_slashes).
+    pub rejected: bool,
+    /// The brace alternatives tried, in order, up to the first that matched.
+    pub alternatives: Vec<AlternativeTrace>,
+}
+
+/// How one brace alternative fared in a [`MatchTrace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternativeTrace {
+    /// The alternative as compiled, after brace expansion.
+    pub pattern: String,
+    /// What happened when matching it.
+    pub outcome: TraceOutcome,
+}
+
+/// The result of matching one brace alternative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceOutcome {
+    /// The alternative matched, with the span each of its segments consumed.
+    Matched(Vec<SegmentSpan>),
+    /// The input starts with `.` and the alternative with a wildcard, which
+    /// does not match dotfiles without the [`dot`](crate::MatchOptions::dot)
+    /// option.
+    DotFile,
+    /// No way of matching got past byte `offset` of the input, where
+    /// `segment` could not continue. `segment` is `None` when the pattern
+    /// ran out with input left over.
+    Failed {
+        offset: usize,
+        segment: Option<String>,
+    },
+    /// Matching gave up after
+    /// [`max_steps`](crate::MatchOptions::max_steps) steps.
+    StepLimitExceeded,
+}
+
+/// A pattern segment and the bytes of the input it consumed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentSpan {
+    /// The segment as pattern text, such as `src/`, `*` or `**/`.
+    pub segment: String,
+    /// The byte range of [`MatchTrace::input`] the segment consumed.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for MatchTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.input, if self.matched { "match" } else { "no match" })?;
+        if self.rejected {
+            write!(f, " (input contains //)")?;
+        }
+        for alternative in &self.alternatives {
+            write!(f, "\n  {}: ", alternative.pattern)?;
+            match &alternative.outcome {
+                TraceOutcome::Matched(spans) => {
+                    for (idx, span) in spans.iter().enumerate() {
+                        if idx > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{} = {:?}", span.segment, &self.input[span.span.clone()])?;
+                    }
+                }
+                TraceOutcome::DotFile => f.write_str("wildcards do not match a leading '.'")?,
+                TraceOutcome::StepLimitExceeded => f.write_str("gave up at the step limit")?,
+                TraceOutcome::Failed { offset, segment } => {
+                    let rest = &self.input[*offset..];
+                    match segment {
+                        Some(segment) => write!(f, "{segment} does not match {rest:?} at byte {offset}")?,
+                        None => write!(f, "input left over at byte {offset}: {rest:?}")?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
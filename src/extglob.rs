@@ -0,0 +1,100 @@
+//! Syntax helpers for extended globs (`@(a|b)`, `?(a|b)`, `!(a|b)`, `+(a|b)`,
+//! `*(a|b)`).
+//!
+//! The segment parser and the [`ast`](crate::ast) parser find extglobs
+//! through [`Delimiters`](crate::delimiters::Delimiters) and use these
+//! helpers to split their pattern lists. The segment-based `Search` then
+//! matches each alternative with a nested search over the text the extglob
+//! may consume.
+
+/// The operator preceding an extglob pattern list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtglobKind {
+    /// `@(a|b)` — exactly one of the alternatives.
+    ExactlyOne,
+    /// `?(a|b)` — zero or one of the alternatives.
+    ZeroOrOne,
+    /// `!(a|b)` — anything except the alternatives.
+    Negated,
+    /// `+(a|b)` — one or more repetitions of the alternatives.
+    OneOrMore,
+    /// `*(a|b)` — zero or more repetitions of the alternatives.
+    ZeroOrMore,
+}
+
+impl ExtglobKind {
+    /// Returns the operator character written before the `(`.
+    pub fn operator(self) -> char {
+        match self {
+            Self::ExactlyOne => '@',
+            Self::ZeroOrOne => '?',
+            Self::Negated => '!',
+            Self::OneOrMore => '+',
+            Self::ZeroOrMore => '*',
+        }
+    }
+
+    pub(crate) fn from_char(c: char) -> Option<Self> {
+        match c {
+            '@' => Some(Self::ExactlyOne),
+            '?' => Some(Self::ZeroOrOne),
+            '!' => Some(Self::Negated),
+            '+' => Some(Self::OneOrMore),
+            '*' => Some(Self::ZeroOrMore),
+            _ => None,
+        }
+    }
+}
+
+/// Splits the body of an extglob (`a|b|c`) on top-level `|` separators.
+pub(crate) fn split_alternatives(body: &[char]) -> Vec<Vec<char>> {
+    let mut alternatives = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    let mut escaped = false;
+    for &c in body {
+        match c {
+            // エスケープされた文字は区切りや括弧として扱わない
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 => {
+                alternatives.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    alternatives.push(current);
+    alternatives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delimiters::Delimiters;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_find_extglob() {
+        let find_extglob = |pattern: &[char], start| Delimiters::new(pattern).extglob(start);
+        assert_eq!(find_extglob(&chars("@(a|b).js"), 0), Some((ExtglobKind::ExactlyOne, 5)));
+        assert_eq!(find_extglob(&chars("x?(a(b))"), 1), Some((ExtglobKind::ZeroOrOne, 7)));
+        assert_eq!(find_extglob(&chars("!(a)/b"), 0), Some((ExtglobKind::Negated, 3)));
+        assert_eq!(find_extglob(&chars("+(a)"), 0), Some((ExtglobKind::OneOrMore, 3)));
+        assert_eq!(find_extglob(&chars("*(a)"), 0), Some((ExtglobKind::ZeroOrMore, 3)));
+        assert_eq!(find_extglob(&chars("@(a|b"), 0), None);
+        assert_eq!(find_extglob(&chars("?.js"), 0), None);
+    }
+
+    #[test]
+    fn test_split_alternatives() {
+        assert_eq!(split_alternatives(&chars("a|b|")), vec![chars("a"), chars("b"), chars("")]);
+        assert_eq!(split_alternatives(&chars("a|@(b|c)")), vec![chars("a"), chars("@(b|c)")]);
+    }
+}
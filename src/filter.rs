@@ -0,0 +1,106 @@
+//! Iterator adapters that filter paths with compiled patterns.
+
+use crate::{GlobSet, Pattern};
+
+/// Adds glob filtering to any iterator over strings.
+///
+/// ```
+/// use satch::{GlobFilterExt, GlobSetBuilder, Pattern};
+///
+/// let pattern = Pattern::new("*.rs");
+/// let sources: Vec<&str> = ["main.rs", "README.md", "lib.rs"].into_iter().glob_filter(&pattern).collect();
+/// assert_eq!(sources, ["main.rs", "lib.rs"]);
+///
+/// let set = GlobSetBuilder::new().add(Pattern::new("*.md")).add(Pattern::new("lib.*")).build();
+/// let lines = "main.rs\nREADME.md\nlib.rs".lines();
+/// assert_eq!(lines.glob_filter_set(&set).collect::<Vec<_>>(), ["README.md", "lib.rs"]);
+/// ```
+pub trait GlobFilterExt: Iterator + Sized
+where
+    Self::Item: AsRef<str>,
+{
+    /// Keeps the items matching `pattern`.
+    fn glob_filter(self, pattern: &Pattern) -> GlobFilter<'_, Self> {
+        GlobFilter {
+            iter: self,
+            matcher: Matcher::Pattern(pattern),
+        }
+    }
+
+    /// Keeps the items matching any pattern in `set`.
+    fn glob_filter_set(self, set: &GlobSet) -> GlobFilter<'_, Self> {
+        GlobFilter {
+            iter: self,
+            matcher: Matcher::Set(set),
+        }
+    }
+}
+
+impl<I> GlobFilterExt for I
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+}
+
+/// The iterator returned by [`GlobFilterExt::glob_filter`] and
+/// [`GlobFilterExt::glob_filter_set`].
+#[derive(Debug, Clone)]
+pub struct GlobFilter<'a, I> {
+    iter: I,
+    matcher: Matcher<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Matcher<'a> {
+    Pattern(&'a Pattern),
+    Set(&'a GlobSet),
+}
+
+impl Matcher<'_> {
+    fn is_match(self, input: &str) -> bool {
+        match self {
+            Matcher::Pattern(pattern) => pattern.is_match(input),
+            Matcher::Set(set) => set.is_match(input),
+        }
+    }
+}
+
+impl<I> Iterator for GlobFilter<'_, I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let matcher = self.matcher;
+        self.iter.find(|item| matcher.is_match(item.as_ref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GlobSetBuilder;
+
+    #[test]
+    fn test_glob_filter_keeps_owned_items() {
+        let pattern = Pattern::new("src/*");
+        let paths = vec!["src/a".to_string(), "lib/b".to_string(), "src/c".to_string()];
+        let kept: Vec<String> = paths.into_iter().glob_filter(&pattern).collect();
+        assert_eq!(kept, ["src/a", "src/c"]);
+    }
+
+    #[test]
+    fn test_glob_filter_set() {
+        let set = GlobSetBuilder::new().add(Pattern::new("*.js")).add(Pattern::new("!*.md")).build();
+        let kept: Vec<&str> = ["a.js", "b.md", "c.ts"].into_iter().glob_filter_set(&set).collect();
+        assert_eq!(kept, ["a.js", "c.ts"]);
+        assert_eq!(["a.js"].into_iter().glob_filter_set(&GlobSet::empty()).count(), 0);
+    }
+}
@@ -0,0 +1,356 @@
+//! Gitignore-style ignore rules.
+//!
+//! A rule without a `/` (other than a trailing one) matches at any depth;
+//! one with a `/` is anchored to the directory holding the ignore file. A
+//! trailing `/` restricts the rule to directories, a leading `!` re-includes
+//! what earlier rules excluded, and the last matching rule wins.
+//!
+//! ```
+//! use satch::gitignore::GitignoreMatcher;
+//!
+//! let mut matcher = GitignoreMatcher::new();
+//! matcher.add_lines("target/\n*.log\n!keep.log\n");
+//! assert!(matcher.is_ignored("target", true));
+//! assert!(matcher.is_ignored("target/debug/app", false));
+//! assert!(matcher.is_ignored("logs/a.log", false));
+//! assert!(!matcher.is_ignored("keep.log", false));
+//! ```
+
+use std::path::Path;
+
+use crate::brace::expand_braces;
+use crate::delimiters::Delimiters;
+use crate::{split_negation, strip_dot_slash, MatchOptions, Pattern};
+
+/// A single parsed ignore rule.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl Rule {
+    pub(crate) fn new(pattern: Pattern, negated: bool, dir_only: bool) -> Self {
+        Self {
+            pattern,
+            negated,
+            dir_only,
+        }
+    }
+
+    /// Returns the glob the rule was translated to.
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Returns `true` for a `!` rule, which re-includes what it matches.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Returns `true` for a rule ending in `/`, which only matches directories.
+    pub fn is_dir_only(&self) -> bool {
+        self.dir_only
+    }
+
+    /// Returns `true` if the rule applies to `path`, given relative to the
+    /// directory holding the ignore file.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && self.pattern.matches_path(path)
+    }
+}
+
+/// Parses one line of an ignore file. Blank lines and `#` comments yield `None`.
+pub fn parse_line(line: &str) -> Option<Rule> {
+    let line = trim_trailing_spaces(line);
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, body) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, body) = match body.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, body),
+    };
+    if body.is_empty() {
+        return None;
+    }
+
+    // 途中に / を含むルールはignoreファイルのディレクトリを基点とする
+    let anchored = body.contains('/');
+    let body = body.strip_prefix('/').unwrap_or(body);
+    let mut glob = if anchored { String::new() } else { String::from("**/") };
+    glob.push_str(&to_glob(body));
+
+    let options = MatchOptions::new().dot(true).strict_globstar(true);
+    Some(Rule {
+        pattern: Pattern::with_options(&glob, &options),
+        negated,
+        dir_only,
+    })
+}
+
+/// Writes a satch glob as ignore-file lines matching the same paths, one
+/// per brace alternative.
+///
+/// A line without an inner `/` gets a leading one, since a glob only
+/// matches from the root. Returns `None` for negated patterns and
+/// extglobs, which ignore files cannot express. Ignore files are a little
+/// looser than globs: `*` also matches dotfiles there, and `/**/` also
+/// matches a single `/`.
+///
+/// ```
+/// use satch::gitignore::from_glob;
+///
+/// assert_eq!(from_glob("{dist,out}/").unwrap(), ["/dist/", "/out/"]);
+/// assert_eq!(from_glob("**/*.{log,tmp}").unwrap(), ["**/*.log", "**/*.tmp"]);
+/// assert_eq!(from_glob("!*.rs"), None);
+/// ```
+pub fn from_glob(pattern: &str) -> Option<Vec<String>> {
+    let (negated, body) = split_negation(pattern);
+    if negated {
+        return None;
+    }
+    expand_braces(body).iter().map(|alternative| glob_to_line(alternative)).collect()
+}
+
+/// Writes one brace-free glob as an ignore-file line.
+fn glob_to_line(glob: &str) -> Option<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let delimiters = Delimiters::new(&chars);
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 2;
+                continue;
+            }
+            '[' => {
+                if let Some(end) = delimiters.class_end(i) {
+                    i = end + 1;
+                    continue;
+                }
+            }
+            _ if delimiters.extglob(i).is_some() => return None,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let glob = strip_dot_slash(glob);
+    let body = glob.strip_suffix('/').unwrap_or(glob);
+    if body.is_empty() {
+        return None;
+    }
+    let mut line = String::with_capacity(glob.len() + 2);
+    if !body.contains('/') {
+        // 区切りのないルールはどの階層にもマッチするので、ルートに固定する
+        line.push('/');
+    } else if glob.starts_with(['#', '!']) {
+        line.push('\\');
+    }
+    line.push_str(glob);
+    // 末尾の空白は読み込み時に削られるのでエスケープする
+    if line.ends_with(' ') {
+        line.insert(line.len() - 1, '\\');
+    }
+    Some(line)
+}
+
+/// Removes trailing spaces unless they are escaped with `\`.
+fn trim_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Rewrites gitignore syntax as a satch glob.
+///
+/// Braces and parentheses are literal in ignore files, so they are escaped,
+/// and `/**/` is allowed to match a single `/` as git does.
+pub(crate) fn to_glob(body: &str) -> String {
+    escape_literal_syntax(body).replace("/**/", "{/,/**/}")
+}
+
+/// Escapes the brace and extglob syntax that other glob dialects read as
+/// literal text, keeping existing `\` escapes.
+pub(crate) fn escape_literal_syntax(body: &str) -> String {
+    let mut glob = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                glob.push(c);
+                if let Some(escaped) = chars.next() {
+                    glob.push(escaped);
+                }
+            }
+            '{' | '}' | '(' | ')' => {
+                glob.push('\\');
+                glob.push(c);
+            }
+            _ => glob.push(c),
+        }
+    }
+    glob
+}
+
+/// An ordered list of rules read from one or more ignore files.
+#[derive(Debug, Clone, Default)]
+pub struct GitignoreMatcher {
+    rules: Vec<Rule>,
+}
+
+impl GitignoreMatcher {
+    /// Creates a matcher with no rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the rule on one line, returning `false` for blank lines and
+    /// comments.
+    pub fn add_line(&mut self, line: &str) -> bool {
+        let rule = parse_line(line);
+        let added = rule.is_some();
+        self.rules.extend(rule);
+        added
+    }
+
+    /// Appends the rules in the contents of an ignore file.
+    pub fn add_lines(&mut self, contents: &str) {
+        self.rules.extend(contents.lines().filter_map(parse_line));
+    }
+
+    /// Returns the rules in the order they were added.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Returns `true` if no rules have been added.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns `Some(true)` if the last rule matching `path` ignores it,
+    /// `Some(false)` if it re-includes it, and `None` if no rule matches.
+    /// `path` is relative to the directory holding the rules. Parent
+    /// directories are not consulted; see [`GitignoreMatcher::is_ignored`].
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .map(|rule| !rule.negated)
+    }
+
+    /// Returns `true` if `path` or one of its parent directories is ignored.
+    ///
+    /// As in git, a file inside an ignored directory stays ignored even if
+    /// a later `!` rule matches the file itself.
+    pub fn is_ignored(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        let path = path.as_ref();
+        // 親ディレクトリが除外されていれば中身は再び含められない
+        let parent_ignored = path
+            .ancestors()
+            .skip(1)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.matched(ancestor, true) == Some(true));
+        parent_ignored || self.matched(path, is_dir) == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(contents: &str) -> GitignoreMatcher {
+        let mut matcher = GitignoreMatcher::new();
+        matcher.add_lines(contents);
+        matcher
+    }
+
+    #[test]
+    fn test_parse_line_skips_comments_and_blanks() {
+        assert!(parse_line("").is_none());
+        assert!(parse_line("   ").is_none());
+        assert!(parse_line("# comment").is_none());
+        assert!(parse_line("\\#file").is_some());
+    }
+
+    #[test]
+    fn test_unanchored_and_anchored_rules() {
+        let matcher = matcher("*.log\n/build\ndocs/*.md\n");
+        assert_eq!(matcher.matched(Path::new("a.log"), false), Some(true));
+        assert_eq!(matcher.matched(Path::new("x/y/a.log"), false), Some(true));
+        assert_eq!(matcher.matched(Path::new("build"), true), Some(true));
+        assert_eq!(matcher.matched(Path::new("src/build"), true), None);
+        assert_eq!(matcher.matched(Path::new("docs/a.md"), false), Some(true));
+        assert_eq!(matcher.matched(Path::new("x/docs/a.md"), false), None);
+    }
+
+    #[test]
+    fn test_directory_only_and_negated_rules() {
+        let matcher = matcher("target/\n*.txt\n!keep.txt\n");
+        assert_eq!(matcher.matched(Path::new("target"), true), Some(true));
+        assert_eq!(matcher.matched(Path::new("target"), false), None);
+        assert_eq!(matcher.matched(Path::new("a.txt"), false), Some(true));
+        assert_eq!(matcher.matched(Path::new("keep.txt"), false), Some(false));
+    }
+
+    #[test]
+    fn test_is_ignored_checks_parent_directories() {
+        let matcher = matcher("build/\n!build/keep.txt\n*.tmp\n!important.tmp\n");
+        assert!(matcher.is_ignored("build/keep.txt", false));
+        assert!(matcher.is_ignored("build/a/b.txt", false));
+        assert!(!matcher.is_ignored("src/build.rs", false));
+        assert!(matcher.is_ignored("a.tmp", false));
+        assert!(!matcher.is_ignored("important.tmp", false));
+        let rule = parse_line("!docs/").unwrap();
+        assert!(rule.is_negated() && rule.is_dir_only());
+        assert_eq!(rule.pattern().as_str(), "**/docs");
+    }
+
+    #[test]
+    fn test_from_glob() {
+        assert_eq!(from_glob("*.log").unwrap(), ["/*.log"]);
+        assert_eq!(from_glob("./src/*.{js,ts}").unwrap(), ["src/*.js", "src/*.ts"]);
+        assert_eq!(from_glob("#notes/a").unwrap(), ["\\#notes/a"]);
+        assert_eq!(from_glob("a ").unwrap(), ["/a\\ "]);
+        assert_eq!(from_glob("[!(]*").unwrap(), ["/[!(]*"]);
+        assert_eq!(from_glob("\\@(x)").unwrap(), ["/\\@(x)"]);
+        assert_eq!(from_glob("src/@(a|b).rs"), None);
+        assert_eq!(from_glob("{a,!(b)}"), None);
+        assert_eq!(from_glob(""), None);
+
+        // 読み戻したルールは元のグロブと同じパスにマッチする
+        let glob = "{target,node_modules}/**/*.{o,so}";
+        let lines = from_glob(glob).unwrap();
+        let rules = matcher(&lines.join("\n"));
+        for path in ["target/x/a.o", "target/x/y/b.so", "node_modules/c/d.so", "src/target/a.o", "target/a.rs"] {
+            assert_eq!(rules.matched(Path::new(path), false) == Some(true), crate::is_match(path, glob), "{}", path);
+        }
+        // ignoreファイルの /**/ は / 1つにもマッチする
+        assert_eq!(rules.matched(Path::new("target/a.o"), false), Some(true));
+        let rules = matcher(&from_glob("*.md").unwrap().join("\n"));
+        assert_eq!(rules.matched(Path::new("README.md"), false), Some(true));
+        assert_eq!(rules.matched(Path::new("docs/a.md"), false), None);
+    }
+
+    #[test]
+    fn test_globstar_and_literal_syntax() {
+        let matcher = matcher("a/**/b\n{x,y}\n.env \n");
+        assert_eq!(matcher.matched(Path::new("a/b"), false), Some(true));
+        assert_eq!(matcher.matched(Path::new("a/x/y/b"), false), Some(true));
+        // ブレースは展開されない
+        assert_eq!(matcher.matched(Path::new("x"), false), None);
+        assert_eq!(matcher.matched(Path::new("{x,y}"), false), Some(true));
+        // 末尾の空白は無視される
+        assert_eq!(matcher.matched(Path::new(".env"), false), Some(true));
+    }
+}
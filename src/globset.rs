@@ -0,0 +1,393 @@
+//! Matching a path against many patterns at once.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use aho_corasick::AhoCorasick;
+
+use crate::pattern::normalize_path;
+use crate::Pattern;
+
+/// Collects patterns and compiles them into a [`GlobSet`].
+///
+/// ```
+/// use satch::{GlobSetBuilder, Pattern};
+///
+/// let set = GlobSetBuilder::new()
+///     .add(Pattern::new("**/*.rs"))
+///     .add(Pattern::new("src/**"))
+///     .add(Pattern::new("*.md"))
+///     .build();
+///
+/// assert_eq!(set.matches("src/main.rs"), vec![0, 1]);
+/// assert_eq!(set.matches("README.md"), vec![2]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GlobSetBuilder {
+    patterns: Vec<Pattern>,
+}
+
+impl GlobSetBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a pattern. Its index in the built set is its insertion order.
+    pub fn add(&mut self, pattern: Pattern) -> &mut Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Compiles the collected patterns into a set.
+    pub fn build(&self) -> GlobSet {
+        GlobSet {
+            patterns: self.patterns.clone(),
+            prefilter: Prefilter::new(&self.patterns),
+        }
+    }
+}
+
+/// A path prepared once for matching against many patterns and sets.
+///
+/// The path is normalized once when the candidate is created rather than
+/// by every pattern it is matched against, and its base name and
+/// extension are located up front.
+///
+/// ```
+/// use std::path::Path;
+/// use satch::{Candidate, GlobSetBuilder, Pattern};
+///
+/// let candidate = Candidate::from_path(Path::new("./src/lib.rs"));
+/// assert_eq!(candidate.as_str(), "src/lib.rs");
+/// assert_eq!(candidate.basename(), "lib.rs");
+/// assert_eq!(candidate.extension(), Some("rs"));
+///
+/// let set = GlobSetBuilder::new().add(Pattern::new("*.md")).add(Pattern::new("src/*.rs")).build();
+/// assert_eq!(set.matches_candidate(&candidate), vec![1]);
+/// assert!(Pattern::new("**/*.rs").is_match_candidate(&candidate));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate<'a> {
+    path: Cow<'a, str>,
+    /// ファイルシステムのパスから作った場合は、先頭の // を UNC のルートとして扱う
+    is_path: bool,
+    /// 最後の要素の開始位置（バイト）
+    basename: usize,
+    /// 拡張子の開始位置（. の次のバイト）
+    extension: Option<usize>,
+    /// ディレクトリかどうか。不明なら None
+    is_dir: Option<bool>,
+}
+
+impl<'a> Candidate<'a> {
+    /// Prepares `input` to be matched as [`Pattern::is_match`] would.
+    pub fn new(input: &'a str) -> Self {
+        Self::with_path(Cow::Borrowed(input), false)
+    }
+
+    /// Prepares the filesystem `path`, normalized as in
+    /// [`Pattern::matches_path`].
+    pub fn from_path(path: &Path) -> Candidate<'static> {
+        Candidate::with_path(Cow::Owned(normalize_path(path)), true)
+    }
+
+    fn with_path(path: Cow<'a, str>, is_path: bool) -> Self {
+        let basename = path.rfind('/').map_or(0, |slash| slash + 1);
+        // .bashrc のような先頭のドットは拡張子の区切りではない
+        let extension = path[basename..]
+            .rfind('.')
+            .filter(|&dot| dot > 0)
+            .map(|dot| basename + dot + 1);
+        Self {
+            path,
+            is_path,
+            basename,
+            extension,
+            is_dir: None,
+        }
+    }
+
+    /// Marks the candidate as naming a directory or not, so patterns ending
+    /// in `/` match it as in [`Pattern::is_match_entry`].
+    ///
+    /// ```
+    /// use satch::{Candidate, Pattern};
+    ///
+    /// let pattern = Pattern::new("target/");
+    /// assert!(pattern.is_match_candidate(&Candidate::new("target").dir(true)));
+    /// assert!(!pattern.is_match_candidate(&Candidate::new("target").dir(false)));
+    /// ```
+    pub fn dir(mut self, is_dir: bool) -> Self {
+        self.is_dir = Some(is_dir);
+        self
+    }
+
+    /// Returns the (normalized) path.
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the final path component.
+    pub fn basename(&self) -> &str {
+        &self.path[self.basename..]
+    }
+
+    /// Returns the text after the last `.` of the base name, if the base
+    /// name has a `.` other than a leading one.
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.map(|start| &self.path[start..])
+    }
+
+    /// Returns `true` if a leading `//` is the root of a UNC path, as for
+    /// candidates made with [`Candidate::from_path`].
+    pub(crate) fn is_path(&self) -> bool {
+        self.is_path
+    }
+
+    /// Returns whether the candidate names a directory, if it was marked
+    /// with [`Candidate::dir`].
+    pub(crate) fn is_dir(&self) -> Option<bool> {
+        self.is_dir
+    }
+
+    /// Returns the text the prefilter scans: the path, followed by `/` for
+    /// directories so the literals of patterns ending in `/` are found.
+    fn prefilter_text(&self) -> Cow<'_, str> {
+        match self.is_dir {
+            Some(true) if !self.path.ends_with('/') => Cow::Owned(format!("{}/", self.path)),
+            _ => Cow::Borrowed(&self.path),
+        }
+    }
+}
+
+/// A compiled set of patterns that reports which of them match an input.
+///
+/// Patterns whose every brace alternative contains literal text, such as
+/// the `.js` of `**/*.js`, are only run on inputs containing one of those
+/// literals, found with a single Aho-Corasick scan of the input.
+#[derive(Debug, Clone, Default)]
+pub struct GlobSet {
+    patterns: Vec<Pattern>,
+    prefilter: Option<Prefilter>,
+}
+
+/// An automaton over the literals the patterns of a set require.
+#[derive(Debug, Clone)]
+struct Prefilter {
+    automaton: AhoCorasick,
+    /// owners[i]: i番目のリテラルを必要とするパターンのインデックス
+    owners: Vec<Vec<usize>>,
+    /// リテラルで絞り込めず、常に照合するパターン
+    unfiltered: Vec<bool>,
+}
+
+impl Prefilter {
+    fn new(patterns: &[Pattern]) -> Option<Self> {
+        let mut ids = HashMap::new();
+        let mut literals = Vec::new();
+        let mut owners: Vec<Vec<usize>> = Vec::new();
+        let mut unfiltered = vec![true; patterns.len()];
+        for (index, pattern) in patterns.iter().enumerate() {
+            let Some(required) = pattern.required_literals() else {
+                continue;
+            };
+            unfiltered[index] = false;
+            for literal in required {
+                let id = *ids.entry(literal.clone()).or_insert_with(|| {
+                    literals.push(literal);
+                    owners.push(Vec::new());
+                    owners.len() - 1
+                });
+                owners[id].push(index);
+            }
+        }
+        if unfiltered.iter().all(|&unfiltered| unfiltered) {
+            return None;
+        }
+        // 構築に失敗したら絞り込まずにすべて照合する
+        let automaton = AhoCorasick::new(&literals).ok()?;
+        Some(Self {
+            automaton,
+            owners,
+            unfiltered,
+        })
+    }
+
+    /// Returns which patterns may match `input`.
+    fn candidates(&self, input: &str) -> Vec<bool> {
+        let mut candidates = self.unfiltered.clone();
+        for found in self.automaton.find_overlapping_iter(input) {
+            for &index in &self.owners[found.pattern()] {
+                candidates[index] = true;
+            }
+        }
+        candidates
+    }
+}
+
+impl GlobSet {
+    /// Creates a set that matches nothing.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Returns `true` if the set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns `true` if any pattern in the set matches `input`.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.matching(input, |pattern| pattern.is_match(input)).next().is_some()
+    }
+
+    /// Returns the indices of every pattern matching `input`, in ascending order.
+    pub fn matches(&self, input: &str) -> Vec<usize> {
+        self.matching(input, |pattern| pattern.is_match(input)).collect()
+    }
+
+    /// Runs [`GlobSet::matches`] on each of `inputs` on the rayon thread
+    /// pool, returning the results in the order of `inputs`.
+    ///
+    /// Requires the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn matches_par<S: AsRef<str> + Sync>(&self, inputs: &[S]) -> Vec<Vec<usize>> {
+        use rayon::prelude::*;
+
+        inputs.par_iter().map(|input| self.matches(input.as_ref())).collect()
+    }
+
+    /// Like [`GlobSet::matches`], for a filesystem path normalized as in
+    /// [`Pattern::matches_path`].
+    pub fn matches_path(&self, path: &Path) -> Vec<usize> {
+        self.matches_candidate(&Candidate::from_path(path))
+    }
+
+    /// Returns `true` if any pattern in the set matches `candidate`.
+    pub fn is_match_candidate(&self, candidate: &Candidate<'_>) -> bool {
+        self.matching(&candidate.prefilter_text(), |pattern| pattern.is_match_candidate(candidate))
+            .next()
+            .is_some()
+    }
+
+    /// Like [`GlobSet::matches`], for a prepared [`Candidate`].
+    pub fn matches_candidate(&self, candidate: &Candidate<'_>) -> Vec<usize> {
+        self.matching(&candidate.prefilter_text(), |pattern| pattern.is_match_candidate(candidate)).collect()
+    }
+
+    /// Yields the indices of the patterns for which `is_match` holds,
+    /// skipping those the prefilter rules out for `input`.
+    fn matching<'a>(
+        &'a self,
+        input: &str,
+        is_match: impl Fn(&Pattern) -> bool + 'a,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let candidates = self.prefilter.as_ref().map(|prefilter| prefilter.candidates(input));
+        self.patterns
+            .iter()
+            .enumerate()
+            .filter(move |(index, pattern)| {
+                candidates.as_ref().is_none_or(|candidates| candidates[*index]) && is_match(pattern)
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatchOptions;
+
+    fn build(patterns: &[&str]) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Pattern::new(pattern));
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_matches_reports_indices() {
+        let set = build(&["**/*.rs", "src/**", "*.md", "!**/*.rs"]);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.matches("src/main.rs"), vec![0, 1]);
+        assert_eq!(set.matches("README.md"), vec![2, 3]);
+        assert!(set.is_match("README.md"));
+    }
+
+    #[test]
+    fn test_empty_set() {
+        let set = GlobSet::empty();
+        assert!(set.is_empty());
+        assert!(!set.is_match("anything"));
+        assert!(set.matches("anything").is_empty());
+    }
+
+    #[test]
+    fn test_prefilter_keeps_results() {
+        let set = build(&["**/*.{js,ts,jsx,tsx}", "src/**", "*", "!*.md", "docs/*.md", "{a,*}"]);
+        let prefilter = set.prefilter.as_ref().unwrap();
+        // 否定パターンとリテラルを持たないパターンは常に照合する
+        assert_eq!(prefilter.unfiltered, vec![false, false, true, true, false, true]);
+        assert_eq!(prefilter.candidates("README.md"), vec![false, false, true, true, false, true]);
+        assert_eq!(set.matches("lib/a.tsx"), vec![0, 3]);
+        assert_eq!(set.matches("src/a.js"), vec![0, 1, 3]);
+        assert_eq!(set.matches("docs/a.md"), vec![3, 4]);
+        assert_eq!(set.matches_path(Path::new("./docs/b.md")), vec![3, 4]);
+
+        let set = GlobSetBuilder::new()
+            .add(Pattern::with_options("*.JS", &MatchOptions::new().nocase(true)))
+            .add(Pattern::with_options("src/*.ts", &MatchOptions::new().windows(true)))
+            .build();
+        assert_eq!(set.matches("a.js"), vec![0]);
+        assert_eq!(set.matches("src\\a.ts"), vec![1]);
+    }
+
+    #[test]
+    fn test_candidate_parts() {
+        let candidate = Candidate::new("a.b/c.tar.gz");
+        assert_eq!(candidate.basename(), "c.tar.gz");
+        assert_eq!(candidate.extension(), Some("gz"));
+        assert_eq!(Candidate::new(".bashrc").extension(), None);
+        assert_eq!(Candidate::new("a.b/c").extension(), None);
+        assert_eq!(Candidate::new("a/").basename(), "");
+
+        let set = build(&["**/*.gz", "a.b/*", "*.gz"]);
+        assert_eq!(set.matches_candidate(&candidate), set.matches("a.b/c.tar.gz"));
+        assert!(set.is_match_candidate(&candidate));
+        assert!(!set.is_match_candidate(&Candidate::new("x/y")));
+    }
+
+    #[test]
+    fn test_dir_candidates() {
+        let set = build(&["**/target/", "target/*", "*.rs"]);
+        assert_eq!(set.matches_candidate(&Candidate::new("target").dir(true)), vec![0]);
+        assert!(set.matches_candidate(&Candidate::new("target").dir(false)).is_empty());
+        assert_eq!(set.matches_candidate(&Candidate::new("a/target/").dir(true)), vec![0]);
+        assert_eq!(set.matches_candidate(&Candidate::new("target/debug").dir(true)), vec![1]);
+        assert_eq!(set.matches_candidate(&Candidate::new("main.rs").dir(false)), vec![2]);
+        assert!(set.matches_candidate(&Candidate::new("a/target")).is_empty());
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let set = build(&["src/*.rs", "*.toml"]);
+        assert_eq!(set.matches_path(Path::new("./src/lib.rs")), vec![0]);
+        assert_eq!(set.matches_path(Path::new("Cargo.toml")), vec![1]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_matches_par_keeps_input_order() {
+        let set = build(&["*.js", "a*"]);
+        let inputs = ["a.js", "b.ts", "b.js", "a.ts"];
+        assert_eq!(set.matches_par(&inputs), vec![vec![0, 1], vec![], vec![0], vec![1]]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Ignore files in the dialects of different tools.
+//!
+//! [`IgnoreMatcher`] evaluates `.gitignore`, `.dockerignore` and
+//! `.npmignore` files through one API, applying each format's rules:
+//!
+//! - **Git**: see [`crate::gitignore`].
+//! - **Docker**: every pattern is anchored to the build context, `**`
+//!   matches any number of directories, a pattern matching a directory
+//!   also matches everything inside it, and `!` exceptions can re-include
+//!   files inside an excluded directory.
+//! - **npm**: gitignore rules, plus the files npm always leaves out of a
+//!   package (`.git`, `node_modules`, `.npmrc`, ...) and the ones it always
+//!   keeps (`package.json`, `README*` and `LICENSE*` at the root).
+//!
+//! ```
+//! use satch::ignore::{IgnoreDialect, IgnoreMatcher};
+//!
+//! let docker = IgnoreMatcher::from_contents(IgnoreDialect::Docker, "*.log\nbuild\n!build/keep");
+//! assert!(docker.is_ignored("a.log", false));
+//! assert!(!docker.is_ignored("src/a.log", false));
+//! assert!(docker.is_ignored("build/out", false));
+//! assert!(!docker.is_ignored("build/keep", false));
+//!
+//! let npm = IgnoreMatcher::from_contents(IgnoreDialect::Npm, "*.md");
+//! assert!(npm.is_ignored("docs/guide.md", false));
+//! assert!(!npm.is_ignored("README.md", false));
+//! ```
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::gitignore::{to_glob, GitignoreMatcher, Rule};
+use crate::{MatchOptions, Pattern};
+
+/// Files npm never publishes, whatever the ignore file says.
+const NPM_ALWAYS_IGNORED: &str = "\
+.git
+CVS
+.svn
+.hg
+.lock-wscript
+.wafpickle-*
+.*.swp
+.DS_Store
+._*
+npm-debug.log
+.npmrc
+node_modules
+config.gypi
+*.orig
+package-lock.json
+";
+
+/// Root files npm always publishes, compared without regard to case.
+const NPM_ALWAYS_INCLUDED: &str = "{package.json,README,README.*,LICENSE,LICENSE.*,LICENCE,LICENCE.*}";
+
+/// The ignore file format a matcher follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IgnoreDialect {
+    /// `.gitignore` and `.ignore` files.
+    Git,
+    /// `.dockerignore` files.
+    Docker,
+    /// `.npmignore` files.
+    Npm,
+}
+
+impl IgnoreDialect {
+    /// Returns the dialect of an ignore file from its name, such as
+    /// `.dockerignore`.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        Some(match name {
+            ".gitignore" | ".ignore" => IgnoreDialect::Git,
+            ".dockerignore" => IgnoreDialect::Docker,
+            ".npmignore" => IgnoreDialect::Npm,
+            _ => return None,
+        })
+    }
+}
+
+/// Rules read from ignore files of one dialect.
+///
+/// Paths are given relative to the directory holding the ignore file.
+#[derive(Debug, Clone)]
+pub struct IgnoreMatcher {
+    dialect: IgnoreDialect,
+    git: GitignoreMatcher,
+    docker: Vec<Rule>,
+}
+
+impl IgnoreMatcher {
+    /// Creates a matcher with no rules of its own. An npm matcher still
+    /// applies npm's built-in rules.
+    pub fn new(dialect: IgnoreDialect) -> Self {
+        Self {
+            dialect,
+            git: GitignoreMatcher::new(),
+            docker: Vec::new(),
+        }
+    }
+
+    /// Creates a matcher from the contents of an ignore file.
+    pub fn from_contents(dialect: IgnoreDialect, contents: &str) -> Self {
+        let mut matcher = Self::new(dialect);
+        matcher.add_lines(contents);
+        matcher
+    }
+
+    /// Returns the dialect the rules are read in.
+    pub fn dialect(&self) -> IgnoreDialect {
+        self.dialect
+    }
+
+    /// Appends the rules in the contents of an ignore file.
+    pub fn add_lines(&mut self, contents: &str) {
+        match self.dialect {
+            IgnoreDialect::Git | IgnoreDialect::Npm => self.git.add_lines(contents),
+            IgnoreDialect::Docker => self.docker.extend(contents.lines().filter_map(parse_docker_line)),
+        }
+    }
+
+    /// Returns `true` if the dialect's rules exclude `path`.
+    pub fn is_ignored(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        let path = path.as_ref();
+        match self.dialect {
+            IgnoreDialect::Git => self.git.is_ignored(path, is_dir),
+            IgnoreDialect::Docker => self.docker_ignored(path),
+            IgnoreDialect::Npm => npm_ignored(&self.git, path, is_dir),
+        }
+    }
+
+    fn docker_ignored(&self, path: &Path) -> bool {
+        // パス自身か親ディレクトリのいずれかにマッチすればそのルールが適用される
+        self.docker
+            .iter()
+            .rev()
+            .find(|rule| {
+                path.ancestors()
+                    .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                    .any(|ancestor| rule.matches(ancestor, true))
+            })
+            .is_some_and(|rule| !rule.is_negated())
+    }
+}
+
+/// Parses one line of a `.dockerignore` file.
+fn parse_docker_line(line: &str) -> Option<Rule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, body) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, line),
+    };
+
+    // Go の filepath.Clean と同様に空の要素と . を取り除く
+    let body = body
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if body.is_empty() {
+        return None;
+    }
+
+    let options = MatchOptions::new().dot(true);
+    Some(Rule::new(Pattern::with_options(&to_glob(&body), &options), negated, false))
+}
+
+fn npm_ignored(rules: &GitignoreMatcher, path: &Path, is_dir: bool) -> bool {
+    static BUILT_IN: OnceLock<(GitignoreMatcher, Pattern)> = OnceLock::new();
+    let (always_ignored, always_included) = BUILT_IN.get_or_init(|| {
+        let mut ignored = GitignoreMatcher::new();
+        ignored.add_lines(NPM_ALWAYS_IGNORED);
+        let included = Pattern::with_options(NPM_ALWAYS_INCLUDED, &MatchOptions::new().nocase(true));
+        (ignored, included)
+    });
+
+    // ルート直下の package.json などは常に含める
+    let at_root = path.components().count() == 1;
+    if at_root && !is_dir && always_included.matches_path(path) {
+        return false;
+    }
+    always_ignored.is_ignored(path, is_dir) || rules.is_ignored(path, is_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_patterns_are_anchored() {
+        let matcher = IgnoreMatcher::from_contents(IgnoreDialect::Docker, "# comment\n  *.md  \n/docs/*.txt\n./tmp/\n");
+        assert!(matcher.is_ignored("README.md", false));
+        assert!(!matcher.is_ignored("src/README.md", false));
+        assert!(matcher.is_ignored("docs/a.txt", false));
+        assert!(matcher.is_ignored("tmp", true));
+        assert!(matcher.is_ignored("tmp/cache/x", false));
+        assert!(!matcher.is_ignored("src/tmp", true));
+    }
+
+    #[test]
+    fn test_docker_globstar_and_exceptions() {
+        let matcher = IgnoreMatcher::from_contents(IgnoreDialect::Docker, "**/*.go\n!cmd/**/main.go\nvendor\n!vendor/keep\n");
+        assert!(matcher.is_ignored("a.go", false));
+        assert!(matcher.is_ignored("pkg/x/a.go", false));
+        assert!(!matcher.is_ignored("cmd/main.go", false));
+        assert!(!matcher.is_ignored("cmd/tool/main.go", false));
+        assert!(matcher.is_ignored("vendor/lib/a.c", false));
+        // docker では除外したディレクトリの中身も例外で戻せる
+        assert!(!matcher.is_ignored("vendor/keep", false));
+    }
+
+    #[test]
+    fn test_npm_built_in_rules() {
+        let matcher = IgnoreMatcher::from_contents(IgnoreDialect::Npm, "*.md\n!node_modules\n");
+        assert!(matcher.is_ignored("docs/a.md", false));
+        assert!(!matcher.is_ignored("readme.md", false));
+        assert!(!matcher.is_ignored("LICENSE", false));
+        assert!(matcher.is_ignored("node_modules/x/index.js", false));
+        assert!(matcher.is_ignored(".git", true));
+        assert!(matcher.is_ignored("src/.DS_Store", false));
+        assert!(!matcher.is_ignored("src/index.js", false));
+        assert!(IgnoreMatcher::new(IgnoreDialect::Npm).is_ignored("package-lock.json", false));
+    }
+
+    #[test]
+    fn test_git_dialect_and_file_names() {
+        let matcher = IgnoreMatcher::from_contents(IgnoreDialect::Git, "*.md\n");
+        assert!(matcher.is_ignored("src/README.md", false));
+        assert_eq!(IgnoreDialect::from_file_name(".dockerignore"), Some(IgnoreDialect::Docker));
+        assert_eq!(IgnoreDialect::from_file_name(".ignore"), Some(IgnoreDialect::Git));
+        assert_eq!(IgnoreDialect::from_file_name("ignore"), None);
+    }
+}
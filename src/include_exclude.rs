@@ -0,0 +1,143 @@
+//! Pairing include patterns with exclude patterns.
+
+use std::path::Path;
+
+use crate::Pattern;
+
+/// Include and exclude patterns evaluated together.
+///
+/// A path is included when it matches no exclude pattern and either
+/// matches an include pattern or there are no include patterns at all.
+/// Excludes take precedence over includes.
+///
+/// ```
+/// use satch::{IncludeDecision, IncludeExclude};
+///
+/// let filter = IncludeExclude::new(&["src/**", "*.toml"], &["**/*.test.rs"]);
+/// assert!(filter.is_included("src/lib.rs"));
+/// assert!(!filter.is_included("src/lib.test.rs"));
+/// assert!(!filter.is_included("README.md"));
+/// assert_eq!(filter.decide("Cargo.toml"), IncludeDecision::Included(1));
+/// assert_eq!(filter.decide("src/a.test.rs"), IncludeDecision::Excluded(0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IncludeExclude {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+/// Why [`IncludeExclude`] included or excluded a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IncludeDecision {
+    /// Included by the include pattern at this index.
+    Included(usize),
+    /// Included because there are no include patterns.
+    IncludedByDefault,
+    /// Excluded by the exclude pattern at this index.
+    Excluded(usize),
+    /// Excluded because no include pattern matched.
+    NotIncluded,
+}
+
+impl IncludeDecision {
+    /// Returns `true` for the decisions that include the path.
+    pub fn is_included(self) -> bool {
+        matches!(self, IncludeDecision::Included(_) | IncludeDecision::IncludedByDefault)
+    }
+}
+
+impl IncludeExclude {
+    /// Compiles the include and exclude patterns with the default options.
+    pub fn new<I: AsRef<str>, E: AsRef<str>>(includes: &[I], excludes: &[E]) -> Self {
+        Self {
+            includes: includes.iter().map(|pattern| Pattern::new(pattern.as_ref())).collect(),
+            excludes: excludes.iter().map(|pattern| Pattern::new(pattern.as_ref())).collect(),
+        }
+    }
+
+    /// Builds the matcher from patterns compiled elsewhere, for example
+    /// with custom [`MatchOptions`](crate::MatchOptions).
+    pub fn from_patterns(includes: Vec<Pattern>, excludes: Vec<Pattern>) -> Self {
+        Self { includes, excludes }
+    }
+
+    /// Returns the include patterns.
+    pub fn includes(&self) -> &[Pattern] {
+        &self.includes
+    }
+
+    /// Returns the exclude patterns.
+    pub fn excludes(&self) -> &[Pattern] {
+        &self.excludes
+    }
+
+    /// Returns `true` if `input` is included.
+    pub fn is_included(&self, input: &str) -> bool {
+        self.decide(input).is_included()
+    }
+
+    /// Returns whether `input` is included and which pattern decided it.
+    /// When several patterns match, the first one is reported.
+    pub fn decide(&self, input: &str) -> IncludeDecision {
+        self.decide_with(|pattern| pattern.is_match(input))
+    }
+
+    /// Like [`IncludeExclude::is_included`], for a filesystem path
+    /// normalized as in [`Pattern::matches_path`].
+    pub fn is_included_path(&self, path: &Path) -> bool {
+        self.decide_path(path).is_included()
+    }
+
+    /// Like [`IncludeExclude::decide`], for a filesystem path.
+    pub fn decide_path(&self, path: &Path) -> IncludeDecision {
+        self.decide_with(|pattern| pattern.matches_path(path))
+    }
+
+    fn decide_with(&self, matches: impl Fn(&Pattern) -> bool) -> IncludeDecision {
+        // 除外パターンが優先される
+        if let Some(index) = self.excludes.iter().position(&matches) {
+            return IncludeDecision::Excluded(index);
+        }
+        if self.includes.is_empty() {
+            return IncludeDecision::IncludedByDefault;
+        }
+        match self.includes.iter().position(matches) {
+            Some(index) => IncludeDecision::Included(index),
+            None => IncludeDecision::NotIncluded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatchOptions;
+
+    #[test]
+    fn test_excludes_take_precedence() {
+        let filter = IncludeExclude::new(&["**/*.js", "lib/**"], &["vendor/**", "**/*.min.js"]);
+        assert_eq!(filter.decide("src/app.js"), IncludeDecision::Included(0));
+        assert_eq!(filter.decide("lib/a.css"), IncludeDecision::Included(1));
+        assert_eq!(filter.decide("vendor/x.js"), IncludeDecision::Excluded(0));
+        assert_eq!(filter.decide("lib/app.min.js"), IncludeDecision::Excluded(1));
+        assert_eq!(filter.decide("src/a.css"), IncludeDecision::NotIncluded);
+        assert!(!filter.is_included("src/a.css"));
+    }
+
+    #[test]
+    fn test_empty_includes_include_everything() {
+        let filter = IncludeExclude::new::<&str, _>(&[], &["*.log"]);
+        assert_eq!(filter.decide("main.rs"), IncludeDecision::IncludedByDefault);
+        assert!(!filter.is_included("a.log"));
+        assert!(IncludeExclude::default().is_included("anything"));
+    }
+
+    #[test]
+    fn test_from_patterns_and_paths() {
+        let options = MatchOptions::new().nocase(true);
+        let filter = IncludeExclude::from_patterns(vec![Pattern::with_options("src/*.RS", &options)], Vec::new());
+        assert!(filter.is_included("src/main.rs"));
+        assert!(filter.is_included_path(Path::new("./src/lib.rs")));
+        assert_eq!(filter.decide_path(Path::new("tests/a.rs")), IncludeDecision::NotIncluded);
+    }
+}
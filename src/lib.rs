@@ -7,6 +7,9 @@
 //! - Globstars (`**`) for recursive directory matching
 //! - Character classes (`[abc]`, `[a-z]`, `[^abc]`)
 //! - Complex multi-globstar patterns (`**/test/**/*.js`)
+//! - Brace expansion (`*.{js,ts}`, `file{1..10}.txt`)
+//! - Extglobs (`@(a|b)`, `?(a|b)`, `!(a|b)`, `+(a|b)`, `*(a|b)`)
+//! - Negated patterns (`!**/*.test.js`)
 //! 
 //! ## Performance
 //! 
@@ -14,6 +17,12 @@
 //! - Memoization to avoid redundant calculations
 //! - Zero-copy string processing
 //! - Efficient segment-based pattern parsing
+//! - Matching in time proportional to pattern length times input length,
+//!   however many wildcards or extglob repetitions they contain; only an
+//!   extglob that can start at many places in one path segment, as in
+//!   `*@(a*)`, costs up to the square of that segment's length
+//! - `memchr` scans for the literal following a wildcard instead of trying
+//!   every position
 //! 
 //! ## Examples
 //! 
@@ -23,176 +32,1820 @@
 //! assert!(is_match("src/main.rs", "src/*.rs"));
 //! assert!(is_match("deep/nested/file.js", "**/file.js"));
 //! assert!(is_match("test.js", "[^.]*.js"));
+//! assert!(is_match("src/app.ts", "src/*.{js,ts}"));
+//! assert!(is_match("lib/index.js", "@(src|lib)/*.js"));
 //! ```
 
+mod ast;
+mod automaton;
+mod brace;
+mod builder;
+mod cache;
+mod casefold;
+mod class;
+mod compiled;
+mod complexity;
+mod delimiters;
+mod error;
+mod explain;
+mod extglob;
+mod filter;
+pub mod gitignore;
+mod globset;
+pub mod ignore;
+mod include_exclude;
+mod list;
+mod pattern;
+mod regexp;
+pub mod rsync;
+mod scan;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "stats")]
+mod stats;
+mod template;
+mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use ast::{parse, walk_alternatives, walk_segment, walk_segments, Ast, Segment, Visitor};
+pub use brace::expand;
+pub use builder::PatternBuilder;
+#[cfg(feature = "cache")]
+pub use cache::{clear_cache, set_cache_capacity};
+pub use class::{ClassItem, PosixClass};
+pub use complexity::{Complexity, WorstCase};
+pub use extglob::ExtglobKind;
+pub use filter::{GlobFilter, GlobFilterExt};
+pub use error::{PatternError, PatternErrorKind, StepLimitExceeded};
+pub use explain::{AlternativeTrace, MatchTrace, SegmentSpan, TraceOutcome};
+pub use globset::{Candidate, GlobSet, GlobSetBuilder};
+pub use include_exclude::{IncludeDecision, IncludeExclude};
+pub use list::Matcher;
+pub use pattern::Pattern;
+#[cfg(feature = "regex")]
+pub use regexp::make_regex;
+pub use regexp::to_regex;
+pub use scan::{is_static, scan, ScanResult};
+#[cfg(feature = "stats")]
+pub use stats::MatchStats;
+pub use template::expand_template;
+pub use walk::{glob, Walk, WalkBuilder};
+
+/// Items `satch-macros` expands to. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::compiled::{Compiled, CompiledAlternative, Token};
+}
+
+use class::{class_items, split_class_negation};
+use delimiters::Delimiters;
+use extglob::split_alternatives;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::Path;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// One element of a brace-free pattern, as the matcher sees it.
 #[derive(Debug, Clone, PartialEq)]
-enum GlobSegment {
-    Literal(String),
+pub(crate) enum GlobSegment {
+    /// Text compared character by character, with escapes removed.
+    Literal(Vec<char>),
+    /// `?`
+    AnyChar,
+    /// `*`
     Wildcard,
-    Globstar,
-    CharClass(String),
+    /// `**`, together with the `/` after it when `slash` is set.
+    Globstar {
+        slash: bool,
+        /// `prefix/**/*.ext` の形では ** が空文字列にマッチしない
+        requires_directory: bool,
+    },
+    /// A bracket expression, holding the text between the brackets.
+    CharClass(Vec<char>),
+    Extglob {
+        kind: ExtglobKind,
+        alternatives: Vec<Vec<GlobSegment>>,
+        /// 代替パターンが / を含む場合だけパス区切りを越えられる
+        crosses_separator: bool,
+    },
 }
 
-pub fn is_match(input: &str, pattern: &str) -> bool {
-    let input_chars: Vec<char> = input.chars().collect();
-    let pattern_chars: Vec<char> = pattern.chars().collect();
+impl GlobSegment {
+    /// Returns `true` for the segments a pattern starting with `*` begins
+    /// with, which are subject to the dotfile check.
+    fn starts_with_star(&self) -> bool {
+        match self {
+            GlobSegment::Wildcard | GlobSegment::Globstar { .. } => true,
+            GlobSegment::Extglob { kind, .. } => *kind == ExtglobKind::ZeroOrMore,
+            _ => false,
+        }
+    }
 
-    // 連続スラッシュを含む入力は無効とする
-    if input.contains("//") {
-        return false;
+    /// Returns `true` if a literal or a class body in this segment names
+    /// `c`. Any other character reaches only wildcards and negated classes.
+    pub(crate) fn mentions(&self, c: char, options: &MatchOptions) -> bool {
+        match self {
+            GlobSegment::Literal(literal) => literal.iter().any(|&literal_char| chars_match(c, literal_char, options)),
+            GlobSegment::CharClass(content) => {
+                let (_, content) = split_class_negation(content, options.class_bang);
+                is_char_in_class(c, content, options)
+            }
+            GlobSegment::Extglob { alternatives, .. } => {
+                alternatives.iter().flatten().any(|segment| segment.mentions(c, options))
+            }
+            _ => false,
+        }
     }
-    
-    // ドットファイルのチェック: パターンが*で始まり、入力が.で始まる場合はマッチしない
-    if !pattern_chars.is_empty()
-        && pattern_chars[0] == '*'
-        && !input_chars.is_empty()
-        && input_chars[0] == '.'
-    {
-        return false;
+
+    /// Returns `true` for `*` and for `**` without a `/` after it, the
+    /// segments that still match once the input is used up.
+    pub(crate) fn is_star(&self) -> bool {
+        matches!(self, GlobSegment::Wildcard | GlobSegment::Globstar { slash: false, .. })
     }
+}
 
-    // 複雑なglobstarパターンの場合は新しいアルゴリズムを使用
-    if has_multiple_globstars(&pattern_chars) {
-        let segments = parse_glob_segments(&pattern_chars);
-        return match_with_segments(input, &segments);
+/// Writes the segment as pattern text, with literal text escaped.
+impl std::fmt::Display for GlobSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlobSegment::Literal(literal) => f.write_str(&escape(&literal.iter().collect::<String>())),
+            GlobSegment::AnyChar => f.write_str("?"),
+            GlobSegment::Wildcard => f.write_str("*"),
+            GlobSegment::Globstar { slash, .. } => f.write_str(if *slash { "**/" } else { "**" }),
+            GlobSegment::CharClass(content) => write!(f, "[{}]", content.iter().collect::<String>()),
+            GlobSegment::Extglob { kind, alternatives, .. } => {
+                write!(f, "{}(", kind.operator())?;
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str("|")?;
+                    }
+                    for segment in alternative {
+                        write!(f, "{segment}")?;
+                    }
+                }
+                f.write_str(")")
+            }
+        }
     }
+}
 
-    match_pattern(input_chars, pattern_chars, 0, 0)
+/// Options controlling how [`is_match_with`] interprets patterns and inputs.
+///
+/// `MatchOptions::default()` reproduces the behavior of [`is_match`].
+///
+/// ```
+/// use satch::{is_match_with, MatchOptions};
+///
+/// let options = MatchOptions::new().strict_slashes(false);
+/// assert!(is_match_with("src//main.rs", "src/**/main.rs", &options));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MatchOptions {
+    strict_slashes: bool,
+    windows: bool,
+    posix_slashes: bool,
+    dot: bool,
+    match_base: bool,
+    nocase: bool,
+    class_bang: bool,
+    strict_globstar: bool,
+    noglobstar: bool,
+    nobrace: bool,
+    noext: bool,
+    nonegate: bool,
+    extended_classes: bool,
+    graphemes: bool,
+    normalize_unicode: bool,
+    named_captures: bool,
+    star_matches_empty: bool,
+    strip_dot_slash: bool,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "serialize::deserialize_separator"))]
+    separator: char,
+    max_pattern_len: usize,
+    max_segments: usize,
+    max_steps: Option<usize>,
 }
 
-fn match_pattern(
-    input: Vec<char>,
-    pattern: Vec<char>,
-    input_idx: usize,
-    pattern_idx: usize,
-) -> bool {
-    // 両方とも末尾に到達
-    if pattern_idx >= pattern.len() && input_idx >= input.len() {
-        return true;
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            strict_slashes: true,
+            windows: false,
+            posix_slashes: false,
+            dot: false,
+            match_base: false,
+            nocase: false,
+            class_bang: true,
+            strict_globstar: false,
+            noglobstar: false,
+            nobrace: false,
+            noext: false,
+            nonegate: false,
+            extended_classes: false,
+            graphemes: false,
+            normalize_unicode: false,
+            named_captures: false,
+            star_matches_empty: true,
+            strip_dot_slash: true,
+            separator: '/',
+            max_pattern_len: 65_536,
+            max_segments: 1_024,
+            max_steps: None,
+        }
     }
+}
 
-    // パターンのみ末尾に到達
-    if pattern_idx >= pattern.len() {
-        return false;
+impl MatchOptions {
+    /// Creates options with the default matching semantics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects inputs containing consecutive slashes (`a//b`). Enabled by default.
+    pub fn strict_slashes(mut self, yes: bool) -> Self {
+        self.strict_slashes = yes;
+        self
+    }
+
+    /// Treats `\` in inputs as a path separator, so Windows paths such as
+    /// `src\main.rs` match patterns written with `/`. Disabled by default.
+    ///
+    /// Absolute paths keep their prefix: `C:\src` becomes `C:/src` and the
+    /// UNC path `\\server\share` becomes `//server/share`, whose leading
+    /// `//` is not rejected by [`strict_slashes`](Self::strict_slashes).
+    /// `\\?\` and `\\.\` markers are removed.
+    pub fn windows(mut self, yes: bool) -> Self {
+        self.windows = yes;
+        self
+    }
+
+    /// Replaces every `\` in inputs with `/` before matching, like
+    /// picomatch's `posixSlashes`. Unlike [`windows`](Self::windows), drive
+    /// and UNC prefixes get no special treatment. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().posix_slashes(true);
+    /// assert!(is_match_with("src\\lib\\mod.rs", "src/**/*.rs", &options));
+    /// assert!(!is_match_with("src\\lib\\mod.rs", "src/**/*.rs", &MatchOptions::new()));
+    /// ```
+    pub fn posix_slashes(mut self, yes: bool) -> Self {
+        self.posix_slashes = yes;
+        self
+    }
+
+    /// Whether `\` in inputs becomes `/`, under either
+    /// [`windows`](Self::windows) or [`posix_slashes`](Self::posix_slashes).
+    pub(crate) fn converts_backslashes(&self) -> bool {
+        self.windows || self.posix_slashes
+    }
+
+    /// Lets a pattern starting with a wildcard match inputs starting with
+    /// `.`, so `*.js` matches `.eslintrc.js`. Disabled by default.
+    pub fn dot(mut self, yes: bool) -> Self {
+        self.dot = yes;
+        self
+    }
+
+    /// Matches patterns without a `/` against only the final path
+    /// component of the input, like minimatch's `matchBase`. With braces,
+    /// each expanded alternative is considered on its own. Disabled by
+    /// default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().match_base(true);
+    /// assert!(is_match_with("src/lib/mod.rs", "*.rs", &options));
+    /// assert!(!is_match_with("src/lib/mod.rs", "src/*.rs", &options));
+    /// ```
+    pub fn match_base(mut self, yes: bool) -> Self {
+        self.match_base = yes;
+        self
+    }
+
+    /// Ignores case when comparing literals and character classes, so
+    /// `*.TXT` matches `notes.txt` and `[a-z]` matches `Q`. Disabled by
+    /// default.
+    ///
+    /// Literals are compared after Unicode default case folding, so
+    /// `STRASSE` matches `straße`. Wildcards and classes see the folded
+    /// text, in which `ß` is two characters.
+    pub fn nocase(mut self, yes: bool) -> Self {
+        self.nocase = yes;
+        self
+    }
+
+    /// Treats a leading `!` in a character class as negation, so `[!abc]`
+    /// is the same as `[^abc]`. Enabled by default; disable it to make `!`
+    /// an ordinary member. Either way `[!]...]` keeps `]` as a member.
+    pub fn class_bang(mut self, yes: bool) -> Self {
+        self.class_bang = yes;
+        self
+    }
+
+    /// Treats `**` as a globstar only when it is a whole path segment
+    /// (`**`, `a/**`, `**/b`), as bash and picomatch do. Elsewhere, such as
+    /// in `a**b`, it behaves like `*` and stays within one segment.
+    /// Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().strict_globstar(true);
+    /// assert!(is_match_with("a/x/b", "a/**/b", &options));
+    /// assert!(!is_match_with("a/x/b", "a**b", &options));
+    /// ```
+    pub fn strict_globstar(mut self, yes: bool) -> Self {
+        self.strict_globstar = yes;
+        self
+    }
+
+    /// Treats every `**` like `*`, so no wildcard crosses a `/`, as in
+    /// shells without `globstar` and minimatch's `noglobstar`. Disabled by
+    /// default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().noglobstar(true);
+    /// assert!(is_match_with("a/x/b", "a/**/b", &options));
+    /// assert!(!is_match_with("a/x/y/b", "a/**/b", &options));
+    /// assert!(!is_match_with("a/b", "**", &options));
+    /// ```
+    pub fn noglobstar(mut self, yes: bool) -> Self {
+        self.noglobstar = yes;
+        self
+    }
+
+    /// Turns off brace expansion, so `{` and `}` match themselves, like
+    /// minimatch's `nobrace`. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().nobrace(true);
+    /// assert!(is_match_with("a{b,c}", "a{b,c}", &options));
+    /// assert!(!is_match_with("ab", "a{b,c}", &options));
+    /// ```
+    pub fn nobrace(mut self, yes: bool) -> Self {
+        self.nobrace = yes;
+        self
+    }
+
+    /// Turns off extglobs, so in `+(a|b)` the `(` and everything after it
+    /// match themselves, like minimatch's `noext`. `*` and `?` before the
+    /// `(` are still wildcards. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().noext(true);
+    /// assert!(is_match_with("a@(b|c)", "a@(b|c)", &options));
+    /// assert!(is_match_with("x(b|c)", "*(b|c)", &options));
+    /// assert!(!is_match_with("b", "@(b|c)", &options));
+    /// ```
+    pub fn noext(mut self, yes: bool) -> Self {
+        self.noext = yes;
+        self
+    }
+
+    /// Turns off negation, so a leading `!` matches itself, like
+    /// minimatch's `nonegate`. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().nonegate(true);
+    /// assert!(is_match_with("!important.txt", "!*.txt", &options));
+    /// assert!(!is_match_with("notes.md", "!*.txt", &options));
+    /// ```
+    pub fn nonegate(mut self, yes: bool) -> Self {
+        self.nonegate = yes;
+        self
+    }
+
+    /// Reads the regex shorthands `\d` (digits), `\w` (letters, digits and
+    /// `_`) and `\s` (whitespace) as character classes, and `\D`, `\W` and
+    /// `\S` as their negations. Inside brackets, as in `[\d.]`, only the
+    /// lowercase forms are supported. Unlike `[^...]`, the negations never
+    /// match `/`. Disabled by default, when `\d` is an escaped `d`.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().extended_classes(true);
+    /// assert!(is_match_with("file-07.txt", "file-\\d\\d.txt", &options));
+    /// assert!(is_match_with("v1.2", "v[\\d.]*", &options));
+    /// assert!(!is_match_with("file-07.txt", "file-\\d\\d.txt", &MatchOptions::new()));
+    /// ```
+    pub fn extended_classes(mut self, yes: bool) -> Self {
+        self.extended_classes = yes;
+        self
+    }
+
+    /// Makes `?` match one extended grapheme cluster instead of one `char`,
+    /// so a letter with combining accents or an emoji sequence counts as a
+    /// single character, as users see it. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// // "e" と結合アクセント記号
+    /// let name = "caf\u{65}\u{301}.txt";
+    /// assert!(is_match_with(name, "caf?.txt", &MatchOptions::new().graphemes(true)));
+    /// assert!(!is_match_with(name, "caf?.txt", &MatchOptions::new()));
+    /// ```
+    pub fn graphemes(mut self, yes: bool) -> Self {
+        self.graphemes = yes;
+        self
+    }
+
+    /// Brings inputs and patterns to Unicode Normalization Form C before
+    /// matching, so a name stored decomposed (as macOS does) matches a
+    /// pattern typed precomposed, and the other way round. Captures and
+    /// other matched text are returned normalized. Disabled by default.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// // "e" と結合アクセント記号
+    /// let name = "cafe\u{301}.txt";
+    /// assert!(is_match_with(name, "caf\u{e9}.*", &MatchOptions::new().normalize_unicode(true)));
+    /// assert!(!is_match_with(name, "caf\u{e9}.*", &MatchOptions::new()));
+    /// ```
+    pub fn normalize_unicode(mut self, yes: bool) -> Self {
+        self.normalize_unicode = yes;
+        self
+    }
+
+    /// Reads a path segment made of a name in braces, such as `{version}`
+    /// in `releases/{version}/*.md`, as a `*` that
+    /// [`Pattern::named_captures`] reports under that name. Names start with
+    /// a letter or `_` and go on with letters, digits and `_`. Disabled by
+    /// default, when `{version}` is literal text.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().named_captures(true);
+    /// assert!(is_match_with("releases/1.2/notes.md", "releases/{version}/*.md", &options));
+    /// assert!(!is_match_with("releases/1.2/notes.md", "releases/{version}/*.md", &MatchOptions::new()));
+    /// ```
+    pub fn named_captures(mut self, yes: bool) -> Self {
+        self.named_captures = yes;
+        self
+    }
+
+    /// Lets a `*` that makes up a whole path segment, as in `src/*` or
+    /// `a/*/b`, match an empty segment. Enabled by default; disable it for
+    /// picomatch's behavior, where such a `*` needs at least one character.
+    ///
+    /// A `*` next to other text, as in `a*b`, always matches the empty
+    /// string.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().star_matches_empty(false);
+    /// assert!(!is_match_with("src/", "src/*", &options));
+    /// assert!(is_match_with("src/ab", "src/a*b", &options));
+    /// ```
+    pub fn star_matches_empty(mut self, yes: bool) -> Self {
+        self.star_matches_empty = yes;
+        self
+    }
+
+    /// Ignores a leading `./` on inputs and on each brace alternative of the
+    /// pattern, so `./src/lib/mod.rs` matches `src/**/*.rs` and `./*.rs`
+    /// matches `main.rs`. Enabled by default.
+    ///
+    /// Only the `./` is ignored: `prefix/**/*.ext` still needs a directory
+    /// between `prefix` and the file, so `./src/main.rs` matches `src/*.rs`
+    /// but not `src/**/*.rs`.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// assert!(is_match_with("./src/lib/mod.rs", "src/**/*.rs", &MatchOptions::new()));
+    /// assert!(!is_match_with("./src/main.rs", "src/**/*.rs", &MatchOptions::new()));
+    /// let options = MatchOptions::new().strip_dot_slash(false);
+    /// assert!(!is_match_with("./src/lib/mod.rs", "src/**/*.rs", &options));
+    /// ```
+    pub fn strip_dot_slash(mut self, yes: bool) -> Self {
+        self.strip_dot_slash = yes;
+        self
+    }
+
+    /// Splits inputs and patterns at `separator` instead of `/`, for
+    /// matching keys such as `a.b.c` or `user:42:name`. `*` and `?` stop at
+    /// the separator, `**` crosses it, and `/` becomes an ordinary
+    /// character. [`strip_dot_slash`](Self::strip_dot_slash) only applies
+    /// with `/`. Defaults to `/`.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().separator('.');
+    /// assert!(is_match_with("server.http.port", "server.*.port", &options));
+    /// assert!(!is_match_with("server.http.tls.port", "server.*.port", &options));
+    /// assert!(is_match_with("server.http.tls.port", "server.**.port", &options));
+    /// assert!(is_match_with("a/b", "*", &options));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `separator` is not ASCII, or is one of the characters
+    /// with a meaning in patterns: `\ * ? [ ] { } ( ) | , ! @ +`.
+    pub fn separator(mut self, separator: char) -> Self {
+        assert!(is_valid_separator(separator), "{separator:?} cannot be used as a separator");
+        self.separator = separator;
+        self
+    }
+
+    /// Whether a leading `./` is stripped, which needs both the option and
+    /// the `/` separator.
+    pub(crate) fn strips_dot_slash(&self) -> bool {
+        self.strip_dot_slash && self.separator == '/'
+    }
+
+    /// Rejects patterns longer than `bytes`. Such a pattern matches
+    /// nothing, and [`Pattern::try_with_options`] reports it as
+    /// [`PatternErrorKind::TooLong`]. Defaults to 65,536 bytes.
+    pub fn max_pattern_len(mut self, bytes: usize) -> Self {
+        self.max_pattern_len = bytes;
+        self
+    }
+
+    /// Rejects patterns made of more than `segments` literal runs,
+    /// wildcards, classes and extglobs, counting those inside extglobs in
+    /// each brace alternative. Such a pattern matches nothing, and
+    /// [`Pattern::try_with_options`] reports it as
+    /// [`PatternErrorKind::TooManySegments`]. Defaults to 1,024.
+    ///
+    /// The matcher remembers the failed states of a search in a table of
+    /// input length times segment count entries, so this bounds that
+    /// table, the work of parsing the pattern, and how deep the search
+    /// nests when it branches at wildcards and extglobs.
+    pub fn max_segments(mut self, segments: usize) -> Self {
+        self.max_segments = segments;
+        self
+    }
+
+    /// Gives up on an input after the matcher has visited `steps` states,
+    /// treating it as not matching. Unlimited by default.
+    /// [`Pattern::try_is_match`] reports the limit as an error instead.
+    ///
+    /// Matching takes time proportional to the pattern length times the
+    /// input length except inside extglobs, which can take longer.
+    ///
+    /// ```
+    /// use satch::{is_match_with, MatchOptions};
+    ///
+    /// let options = MatchOptions::new().max_steps(100);
+    /// assert!(is_match_with("src/main.rs", "src/*.rs", &options));
+    /// assert!(!is_match_with(&"a".repeat(1000), "+(a|aa)b", &options));
+    /// ```
+    pub fn max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = Some(steps);
+        self
     }
+}
 
-    // 入力のみ末尾に到達
-    if input_idx >= input.len() {
-        // 残りのパターンが全て*であれば一致
-        return pattern[pattern_idx..].iter().all(|&c| c == '*');
+/// Returns `true` if `input` matches the glob `pattern` using the default options.
+pub fn is_match(input: &str, pattern: &str) -> bool {
+    is_match_with(input, pattern, &MatchOptions::default())
+}
+
+/// Returns `true` if `input` matches the glob `pattern` under the given `options`.
+///
+/// A pattern starting with `!` is negated: it matches every input the rest of
+/// the pattern does not. Write `\!` to match a literal leading `!`.
+///
+/// ```
+/// use satch::{is_match_with, MatchOptions};
+///
+/// let options = MatchOptions::default();
+/// assert!(is_match_with("src/app.js", "!**/*.test.js", &options));
+/// assert!(!is_match_with("src/app.test.js", "!**/*.test.js", &options));
+/// ```
+pub fn is_match_with(input: &str, pattern: &str, options: &MatchOptions) -> bool {
+    cache::with_pattern(pattern, options, |pattern| pattern.is_match(input))
+}
+
+/// Like [`is_match`], but rejects malformed patterns instead of reading them
+/// as literal text.
+///
+/// ```
+/// use satch::{try_match, PatternErrorKind};
+///
+/// assert_eq!(try_match("src/main.rs", "src/*.rs"), Ok(true));
+/// let error = try_match("src/main.rs", "src/[a-z.rs").unwrap_err();
+/// assert_eq!(error.kind(), &PatternErrorKind::UnclosedClass);
+/// ```
+pub fn try_match(input: &str, pattern: &str) -> Result<bool, PatternError> {
+    Ok(Pattern::try_new(pattern)?.is_match(input))
+}
+
+/// Compiles `pattern` once and returns a predicate for reuse, for example
+/// with [`Iterator::filter`] or [`Vec::retain`].
+///
+/// ```
+/// use satch::{matcher, MatchOptions};
+///
+/// let is_source = matcher("**/*.rs", &MatchOptions::default());
+/// let paths = ["src/main.rs", "README.md", "src/lib/mod.rs"];
+/// let sources: Vec<&str> = paths.into_iter().filter(|path| is_source(path)).collect();
+/// assert_eq!(sources, ["src/main.rs", "src/lib/mod.rs"]);
+/// ```
+pub fn matcher(pattern: &str, options: &MatchOptions) -> impl Fn(&str) -> bool {
+    let pattern = Pattern::with_options(pattern, options);
+    move |input: &str| pattern.is_match(input)
+}
+
+/// Returns `true` if `pattern` matches some part of `input`, like
+/// micromatch's `contains`. See [`Pattern::contains`].
+///
+/// ```
+/// use satch::contains;
+///
+/// assert!(contains("aa/bb/cc", "*b"));
+/// assert!(contains("aa/bb/cc", "bb/c?"));
+/// assert!(!contains("aa/bb/cc", "*d"));
+/// ```
+pub fn contains(input: &str, pattern: &str) -> bool {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.contains(input))
+}
+
+/// Returns the text matched by each wildcard of `pattern` in `input`, or
+/// `None` if `input` does not match.
+///
+/// The argument order follows micromatch's `capture(pattern, input)`. See
+/// [`Pattern::captures`] for details.
+///
+/// ```
+/// use satch::capture;
+///
+/// assert_eq!(capture("test/*.js", "test/foo.js"), Some(vec!["foo".to_string()]));
+/// ```
+pub fn capture(pattern: &str, input: &str) -> Option<Vec<String>> {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.captures(input))
+}
+
+/// Returns the byte range of `input` matched by each wildcard of
+/// `pattern`, or `None` if `input` does not match.
+///
+/// See [`Pattern::match_spans`] for details.
+///
+/// ```
+/// use satch::match_spans;
+///
+/// assert_eq!(match_spans("test/*.js", "test/foo.js"), Some(vec![5..8]));
+/// ```
+pub fn match_spans(pattern: &str, input: &str) -> Option<Vec<Range<usize>>> {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.match_spans(input))
+}
+
+/// Returns `true` if `input` matches at least one of `patterns`.
+///
+/// Stops at the first matching pattern.
+///
+/// ```
+/// use satch::is_match_any;
+///
+/// assert!(is_match_any("src/main.rs", &["*.js", "src/*.rs"]));
+/// assert!(!is_match_any("src/main.rs", &["*.js", "*.ts"]));
+/// ```
+pub fn is_match_any<P: AsRef<str>>(input: &str, patterns: &[P]) -> bool {
+    patterns.iter().any(|pattern| is_match(input, pattern.as_ref()))
+}
+
+/// Returns `true` if `input` matches every one of `patterns`.
+///
+/// Stops at the first pattern that does not match. An empty pattern list
+/// matches every input.
+///
+/// ```
+/// use satch::is_match_all;
+///
+/// assert!(is_match_all("src/main.rs", &["src/**", "*/*.rs"]));
+/// assert!(!is_match_all("src/main.rs", &["src/**", "!**/main.rs"]));
+/// ```
+pub fn is_match_all<P: AsRef<str>>(input: &str, patterns: &[P]) -> bool {
+    patterns.iter().all(|pattern| is_match(input, pattern.as_ref()))
+}
+
+/// Returns whether each of `paths` matches `pattern`, like calling
+/// [`is_match`] on each.
+///
+/// `paths` should be sorted, as in a directory listing: consecutive paths
+/// in the same directory reuse its match and only match their file names.
+/// See [`Pattern::match_sorted`].
+///
+/// ```
+/// use satch::match_sorted;
+///
+/// let paths = ["src/a/lib.rs", "src/a/main.rs", "src/a/notes.md", "src/b.rs"];
+/// assert_eq!(match_sorted(&paths, "src/*/*.rs"), vec![true, true, false, false]);
+/// ```
+pub fn match_sorted<S: AsRef<str>>(paths: &[S], pattern: &str) -> Vec<bool> {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.match_sorted(paths))
+}
+
+/// Returns the `paths` that survive `patterns`, like micromatch's
+/// `micromatch(list, patterns)`.
+///
+/// Patterns apply in order: a path is kept once a positive pattern matches
+/// it, and dropped again if a later negated pattern (`!…`) excludes it. A
+/// later positive match brings it back. When every pattern is negated, all
+/// paths not excluded survive; with no patterns, none do. The result keeps
+/// the order of `paths`.
+///
+/// ```
+/// use satch::match_list;
+///
+/// let paths = ["a.js", "b.js", "c.md", "vendor/d.js"];
+/// assert_eq!(match_list(&paths, &["**/*.js", "!vendor/**"]), ["a.js", "b.js"]);
+/// assert_eq!(match_list(&paths, &["!*.md"]), ["a.js", "b.js", "vendor/d.js"]);
+/// ```
+pub fn match_list<'a, S: AsRef<str>, P: AsRef<str>>(paths: &'a [S], patterns: &[P]) -> Vec<&'a str> {
+    filter_list(paths, patterns, true)
+}
+
+/// Returns the `paths` that [`match_list`] would drop, like micromatch's
+/// `not(list, patterns)`. The result keeps the order of `paths`.
+///
+/// ```
+/// use satch::not;
+///
+/// let paths = ["a.js", "b.js", "c.md", "vendor/d.js"];
+/// assert_eq!(not(&paths, &["**/*.js", "!vendor/**"]), ["c.md", "vendor/d.js"]);
+/// ```
+pub fn not<'a, S: AsRef<str>, P: AsRef<str>>(paths: &'a [S], patterns: &[P]) -> Vec<&'a str> {
+    filter_list(paths, patterns, false)
+}
+
+/// Returns `true` if any of `paths` survives `patterns`, like micromatch's
+/// `some(list, patterns)`.
+///
+/// Paths are matched with the same rules as [`match_list`], stopping at the
+/// first one kept. An empty `paths` returns `false`.
+///
+/// ```
+/// use satch::some;
+///
+/// assert!(some(&["README.md", "src/lib.rs"], &["**/*.rs"]));
+/// assert!(!some(&["README.md", "src/lib.rs"], &["**/*.rs", "!src/**"]));
+/// ```
+pub fn some<S: AsRef<str>, P: AsRef<str>>(paths: &[S], patterns: &[P]) -> bool {
+    let matcher = Matcher::new(patterns);
+    paths.iter().any(|path| matcher.is_match(path.as_ref()))
+}
+
+/// Returns `true` if every one of `paths` survives `patterns`, like
+/// micromatch's `every(list, patterns)`.
+///
+/// Paths are matched with the same rules as [`match_list`], stopping at the
+/// first one dropped. An empty `paths` returns `true`.
+///
+/// ```
+/// use satch::every;
+///
+/// assert!(every(&["src/lib.rs", "src/main.rs"], &["src/*.rs"]));
+/// assert!(!every(&["src/lib.rs", "src/main.rs"], &["src/*.rs", "!**/main.rs"]));
+/// ```
+pub fn every<S: AsRef<str>, P: AsRef<str>>(paths: &[S], patterns: &[P]) -> bool {
+    let matcher = Matcher::new(patterns);
+    paths.iter().all(|path| matcher.is_match(path.as_ref()))
+}
+
+/// Keeps the entries of `map` whose keys survive `patterns`, like
+/// micromatch's `matchKeys(object, patterns)`.
+///
+/// Keys are matched with the same rules as [`match_list`]. Any map that
+/// can be iterated and collected, such as `HashMap` or `BTreeMap`, works.
+///
+/// ```
+/// use satch::filter_map_keys;
+/// use std::collections::BTreeMap;
+///
+/// let sizes = BTreeMap::from([("dist/app.js", 120), ("dist/app.js.map", 480), ("src/app.ts", 96)]);
+/// let bundles = filter_map_keys(sizes, &["dist/**", "!**/*.map"]);
+/// assert_eq!(bundles, BTreeMap::from([("dist/app.js", 120)]));
+/// ```
+pub fn filter_map_keys<M, K, V, P>(map: M, patterns: &[P]) -> M
+where
+    M: IntoIterator<Item = (K, V)> + FromIterator<(K, V)>,
+    K: AsRef<str>,
+    P: AsRef<str>,
+{
+    let matcher = Matcher::new(patterns);
+    map.into_iter().filter(|(key, _)| matcher.is_match(key.as_ref())).collect()
+}
+
+/// Like [`match_list`], matching the paths on the rayon thread pool.
+///
+/// Requires the `parallel` feature. The result keeps the order of `paths`.
+#[cfg(feature = "parallel")]
+pub fn match_list_par<'a, S: AsRef<str> + Sync, P: AsRef<str>>(paths: &'a [S], patterns: &[P]) -> Vec<&'a str> {
+    use rayon::prelude::*;
+
+    let matcher = Matcher::new(patterns);
+    paths
+        .par_iter()
+        .map(AsRef::as_ref)
+        .filter(|path| matcher.is_match(path))
+        .collect()
+}
+
+/// Keeps the paths whose [`Matcher::is_match`] result equals `keep`.
+fn filter_list<'a, S: AsRef<str>, P: AsRef<str>>(paths: &'a [S], patterns: &[P], keep: bool) -> Vec<&'a str> {
+    let matcher = Matcher::new(patterns);
+    paths
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|path| matcher.is_match(path) == keep)
+        .collect()
+}
+
+/// Applies `patterns` in order using micromatch's list rules, with
+/// `matches` deciding whether each pattern accepts the path.
+fn list_keeps_with(patterns: &[Pattern], all_negated: bool, matches: impl Fn(&Pattern) -> bool) -> bool {
+    let mut kept = false;
+    let mut omitted = false;
+    for pattern in patterns {
+        if pattern.is_negated() {
+            // 否定パターンは本体にマッチしたパスを除外する
+            if !matches(pattern) {
+                omitted = true;
+            }
+        } else if matches(pattern) {
+            kept = true;
+            omitted = false;
+        }
     }
+    (kept || all_negated) && !omitted
+}
+
+/// Returns `true` if the filesystem `path` matches the glob `pattern`.
+///
+/// See [`Pattern::matches_path`] for how the path is normalized.
+///
+/// ```
+/// use std::path::Path;
+/// use satch::is_match_path;
+///
+/// assert!(is_match_path(Path::new("./src/main.rs"), "src/*.rs"));
+/// ```
+pub fn is_match_path(path: &Path, pattern: &str) -> bool {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.matches_path(path))
+}
+
+/// Returns `true` if the raw bytes `input` match the glob `pattern`.
+///
+/// See [`Pattern::is_match_bytes`] for how invalid UTF-8 is read.
+///
+/// ```
+/// use satch::is_match_bytes;
+///
+/// assert!(is_match_bytes(b"caf\xe9.txt", "*.txt"));
+/// ```
+pub fn is_match_bytes(input: &[u8], pattern: &str) -> bool {
+    cache::with_pattern(pattern, &MatchOptions::default(), |pattern| pattern.is_match_bytes(input))
+}
+
+/// Returns the longest `/`-terminated literal directory prefix shared by
+/// every pattern, or an empty string when there is none, so a walk can
+/// start from one directory for the whole set.
+///
+/// Each pattern contributes its [`Pattern::literal_prefix`], so escapes are
+/// removed, and a negated pattern (which can match anywhere) leaves no
+/// common prefix.
+///
+/// ```
+/// use satch::common_prefix;
+///
+/// assert_eq!(common_prefix(&["src/**/*.ts", "src/lib/*.js"]), "src/");
+/// assert_eq!(common_prefix(&["src/app/*.ts", "src/api/*.ts"]), "src/");
+/// assert_eq!(common_prefix(&["src/*.ts", "test/*.ts"]), "");
+/// ```
+pub fn common_prefix<P: AsRef<str>>(patterns: &[P]) -> String {
+    let mut prefixes = patterns.iter().map(|pattern| Pattern::new(pattern.as_ref()).literal_prefix());
+    let Some(mut common) = prefixes.next() else {
+        return String::new();
+    };
+    for prefix in prefixes {
+        let shared = common
+            .char_indices()
+            .zip(prefix.chars())
+            .find(|&((_, a), b)| a != b)
+            .map_or(common.len().min(prefix.len()), |((idx, _), _)| idx);
+        common.truncate(shared);
+    }
+    // ディレクトリ名の途中で切れた部分（src/app と src/api の src/ap）は含めない
+    let end = common.rfind('/').map_or(0, |slash| slash + 1);
+    common.truncate(end);
+    common
+}
+
+/// Escapes the glob syntax in `text`, giving a pattern that matches exactly
+/// `text`. The result can be embedded in a larger pattern, including inside
+/// braces and extglobs.
+///
+/// ```
+/// use satch::{escape, is_match};
+///
+/// assert_eq!(escape("weird[file]*.txt"), "weird\\[file\\]\\*.txt");
+/// assert!(is_match("weird[file]*.txt", &escape("weird[file]*.txt")));
+/// assert!(is_match("docs/a{1,2}.md", &format!("docs/{}", escape("a{1,2}.md"))));
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        // , と | はブレースや extglob の中に埋め込んだときだけ意味を持つ
+        if matches!(c, '\\' | '*' | '?' | '[' | ']' | '{' | '}' | '(' | ')' | '!' | ',' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
 
-    let pattern_char = pattern[pattern_idx];
-    let input_char = input[input_idx];
+/// Returns `false` for the characters [`MatchOptions::separator`] rejects:
+/// non-ASCII ones and those with a meaning in patterns.
+pub(crate) fn is_valid_separator(separator: char) -> bool {
+    separator.is_ascii() && !"\\*?[]{}()|,!@+".contains(separator)
+}
+
+/// Strips leading `!` operators, returning whether the pattern is negated.
+///
+/// Each `!` toggles the negation (`!!a` is the same as `a`). A `!(...)`
+/// extglob is not a negation operator, and `\!` escapes a literal `!`.
+pub(crate) fn split_negation(pattern: &str) -> (bool, &str) {
+    let mut negated = false;
+    let mut rest = pattern;
+    while let Some(stripped) = rest.strip_prefix('!') {
+        // extglob になりうるのは !( で始まるときだけで、その後の残りは ( で始まるので調べるのは1度きり
+        if stripped.starts_with('(') && Delimiters::new(&rest.chars().collect::<Vec<_>>()).extglob(0).is_some() {
+            break;
+        }
+        negated = !negated;
+        rest = stripped;
+    }
+    (negated, rest)
+}
 
-    match pattern_char {
-        '*' => {
-            // **パターンをチェック
-            if pattern_idx + 1 < pattern.len() && pattern[pattern_idx + 1] == '*' {
-                // **は0文字以上の任意文字にマッチ（/を含む）
-                return match_globstar(input.clone(), pattern.clone(), input_idx, pattern_idx + 2);
+/// Rewrites `pattern` into the syntax the matcher understands, following
+/// the options that add or remove syntax.
+pub(crate) fn rewrite_syntax<'a>(pattern: &'a str, options: &MatchOptions) -> Cow<'a, str> {
+    // 正規化で変わるパターンだけ、正規化した文字列を書き換える
+    match options.normalize_unicode.then(|| to_nfc(pattern)) {
+        Some(Cow::Owned(normalized)) => Cow::Owned(rewrite_normalized_syntax(&normalized, options).into_owned()),
+        _ => rewrite_normalized_syntax(pattern, options),
+    }
+}
+
+fn rewrite_normalized_syntax<'a>(pattern: &'a str, options: &MatchOptions) -> Cow<'a, str> {
+    if !options.extended_classes {
+        return escape_disabled_syntax(pattern, options);
+    }
+    match expand_shorthand_classes(pattern, options.separator) {
+        Cow::Borrowed(pattern) => escape_disabled_syntax(pattern, options),
+        Cow::Owned(expanded) => Cow::Owned(escape_disabled_syntax(&expanded, options).into_owned()),
+    }
+}
+
+/// The POSIX class items for a lowercase regex shorthand letter.
+fn shorthand_class_items(letter: char) -> Option<&'static str> {
+    match letter {
+        'd' => Some("[:digit:]"),
+        'w' => Some("[:alnum:]_"),
+        's' => Some("[:space:]"),
+        _ => None,
+    }
+}
+
+/// Replaces the regex shorthands `\d`, `\w` and `\s` (and their uppercase
+/// negations outside brackets) with bracket expressions, for
+/// [`MatchOptions::extended_classes`]. The negations leave out `separator`.
+fn expand_shorthand_classes(pattern: &str, separator: char) -> Cow<'_, str> {
+    if !pattern.contains('\\') {
+        return Cow::Borrowed(pattern);
+    }
+    let chars: Vec<char> = pattern.chars().collect();
+    let delimiters = Delimiters::new(&chars);
+    let mut expanded = String::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                let letter = chars[i + 1];
+                match shorthand_class_items(letter.to_ascii_lowercase()) {
+                    // 否定の直後の文字はリテラルなので、区切り文字が - や ^ でも構わない
+                    Some(items) if letter.is_ascii_uppercase() => {
+                        expanded.push_str(&format!("[^{separator}{items}]"))
+                    }
+                    Some(items) => expanded.push_str(&format!("[{items}]")),
+                    None => expanded.extend([chars[i], letter]),
+                }
+                i += 2;
+            }
+            '[' => {
+                let Some(class_end) = delimiters.class_end(i) else {
+                    expanded.push('[');
+                    i += 1;
+                    continue;
+                };
+                // ブラケットの中では小文字の省略形だけを POSIX クラスに置き換える
+                let mut j = i;
+                while j <= class_end {
+                    if chars[j] == '\\' && j + 1 < class_end {
+                        match shorthand_class_items(chars[j + 1]) {
+                            Some(items) => expanded.push_str(items),
+                            None => expanded.extend([chars[j], chars[j + 1]]),
+                        }
+                        j += 2;
+                    } else {
+                        expanded.push(chars[j]);
+                        j += 1;
+                    }
+                }
+                i = class_end + 1;
             }
-            
-            // *は0文字以上の任意文字にマッチ（ただし/は除く）
-            // 次のパターンがない場合、残りの入力全てをマッチ（/を除く）
-            if pattern_idx + 1 >= pattern.len() {
-                return !input[input_idx..].contains(&'/');
+            c => {
+                expanded.push(c);
+                i += 1;
             }
+        }
+    }
+    Cow::Owned(expanded)
+}
 
-            // 0文字マッチを試す
-            if match_pattern(input.clone(), pattern.clone(), input_idx, pattern_idx + 1) {
-                return true;
+/// Escapes the syntax turned off by [`MatchOptions::nobrace`],
+/// [`MatchOptions::noext`] and [`MatchOptions::nonegate`], so that it
+/// matches literally.
+pub(crate) fn escape_disabled_syntax<'a>(pattern: &'a str, options: &MatchOptions) -> Cow<'a, str> {
+    if !options.nobrace && !options.noext && !options.nonegate {
+        return Cow::Borrowed(pattern);
+    }
+    let chars: Vec<char> = pattern.chars().collect();
+    let delimiters = Delimiters::new(&chars);
+    let mut escaped = String::with_capacity(pattern.len() + 1);
+    // 否定の演算子として読まれる先頭の ! だけをエスケープすればよい
+    if options.nonegate && split_negation(pattern).1.len() < pattern.len() {
+        escaped.push('\\');
+    }
+    let mut i = 0;
+    while i < chars.len() {
+        let end = match chars[i] {
+            '\\' => (i + 2).min(chars.len()),
+            '[' => delimiters.class_end(i).map_or(i + 1, |class_end| class_end + 1),
+            '{' | '}' if options.nobrace => {
+                escaped.push('\\');
+                i + 1
+            }
+            // extglob は演算子の後に ( が続くときだけなので、( をリテラルにすれば演算子は普通の文字に戻る
+            '(' if options.noext => {
+                escaped.push('\\');
+                i + 1
             }
+            _ => i + 1,
+        };
+        escaped.extend(&chars[i..end]);
+        i = end;
+    }
+    Cow::Owned(escaped)
+}
+
+/// Removes every leading `./` from `text`.
+pub(crate) fn strip_dot_slash(mut text: &str) -> &str {
+    while let Some(rest) = text.strip_prefix("./") {
+        text = rest;
+    }
+    text
+}
 
-            // 1文字ずつマッチを試す（/以外の文字のみ）
-            for i in input_idx..input.len() {
-                if input[i] == '/' {
-                    break;
+/// Replaces each path segment of `pattern` (one brace alternative) that is
+/// a `{name}` placeholder with `*`, for [`MatchOptions::named_captures`].
+/// Returns the rewritten pattern and, for each placeholder, its position
+/// in it and its name.
+pub(crate) fn replace_named_placeholders(pattern: &[char]) -> (Vec<char>, Vec<(usize, String)>) {
+    let delimiters = Delimiters::new(pattern);
+    let mut replaced = Vec::with_capacity(pattern.len());
+    let mut names = Vec::new();
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => {
+                replaced.extend(&pattern[i..(i + 2).min(pattern.len())]);
+                i += 2;
+                continue;
+            }
+            '[' => {
+                if let Some(class_end) = delimiters.class_end(i) {
+                    replaced.extend(&pattern[i..=class_end]);
+                    i = class_end + 1;
+                    continue;
                 }
-                if match_pattern(input.clone(), pattern.clone(), i + 1, pattern_idx + 1) {
-                    return true;
+            }
+            '{' if i == 0 || pattern[i - 1] == '/' => {
+                // セグメント全体が {名前} のときだけプレースホルダとして読む
+                let close = pattern[i + 1..].iter().position(|&c| c == '}').map(|offset| i + 1 + offset);
+                if let Some(close) = close.filter(|&close| matches!(pattern.get(close + 1), None | Some('/'))) {
+                    let name: String = pattern[i + 1..close].iter().collect();
+                    let mut name_chars = name.chars();
+                    let is_name = name_chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                        && name_chars.all(|c| c.is_alphanumeric() || c == '_');
+                    if is_name {
+                        names.push((replaced.len(), name));
+                        replaced.push('*');
+                        i = close + 1;
+                        continue;
+                    }
                 }
             }
-            false
+            _ => {}
         }
-        '?' => {
-            // ?は任意の1文字にマッチ（ただし/は除く）
-            if input_char != '/' {
-                match_pattern(input, pattern, input_idx + 1, pattern_idx + 1)
-            } else {
+        replaced.push(pattern[i]);
+        i += 1;
+    }
+    (replaced, names)
+}
+
+/// Converts `text` to Unicode Normalization Form C, for
+/// [`MatchOptions::normalize_unicode`].
+pub(crate) fn to_nfc(text: &str) -> Cow<'_, str> {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.nfc().collect())
+    }
+}
+
+/// Exchanges `separator` and `/` in `text`, so that text split at a custom
+/// separator can be matched as if it were split at `/`. Swapping twice
+/// restores the text, and byte offsets are kept since the separator is ASCII.
+pub(crate) fn swap_separator(text: &str, separator: char) -> Cow<'_, str> {
+    if separator == '/' || !text.contains([separator, '/']) {
+        return Cow::Borrowed(text);
+    }
+    let swapped = text
+        .chars()
+        .map(|c| match c {
+            '/' => separator,
+            c if c == separator => '/',
+            c => c,
+        })
+        .collect();
+    Cow::Owned(swapped)
+}
+
+/// 各ワイルドカードが消費した入力範囲（文字インデックス）。
+/// マッチ成功時に後ろのワイルドカードから順に積まれる。**/ の範囲は直後の / を含む
+pub(crate) type CaptureSpans = Vec<(usize, usize)>;
+
+pub(crate) fn match_expanded_pattern(input: &str, segments: &[GlobSegment], options: &MatchOptions) -> bool {
+    match_expanded_pattern_with(input, segments, options, None)
+}
+
+/// Matches `input` against a brace-free pattern parsed with
+/// [`parse_glob_segments`], optionally recording the spans consumed by each
+/// wildcard in `captures` (in reverse order).
+pub(crate) fn match_expanded_pattern_with(
+    input: &str,
+    segments: &[GlobSegment],
+    options: &MatchOptions,
+    captures: Option<&mut CaptureSpans>,
+) -> bool {
+    trace_expanded_pattern(input, segments, options, captures).is_ok()
+}
+
+/// Why [`trace_expanded_pattern`] did not match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mismatch {
+    /// The dotfile check rejected the input before matching.
+    DotFile,
+    /// The search failed, getting no further than this
+    /// `(input_idx, segment_idx)` state.
+    Failed(usize, usize),
+    /// The search ran out of [`max_steps`](MatchOptions::max_steps).
+    StepLimit,
+}
+
+/// Like [`match_expanded_pattern_with`], reporting why the input did not
+/// match.
+pub(crate) fn trace_expanded_pattern(
+    input: &str,
+    segments: &[GlobSegment],
+    options: &MatchOptions,
+    captures: Option<&mut CaptureSpans>,
+) -> Result<(), Mismatch> {
+    // ドットファイルのチェック: パターンが*で始まり、入力が.で始まる場合はマッチしない
+    if !options.dot && segments.first().is_some_and(GlobSegment::starts_with_star) && input.starts_with('.') {
+        return Err(Mismatch::DotFile);
+    }
+
+    // ASCIIの入力は1バイトが1文字なので、charへ展開せずバイト列のまま照合する
+    if input.is_ascii() {
+        Search::new(input.as_bytes(), segments, options).run(captures)
+    } else {
+        let input_chars: Vec<char> = input.chars().collect();
+        Search::new(&input_chars, segments, options).run(captures)
+    }
+}
+
+/// Returns `true` if some part of `input` matches `segments`, for
+/// [`Pattern::contains`].
+///
+/// One search tries every start position. It keeps the states known to
+/// fail across starts, so the scan takes time proportional to the input
+/// length times the pattern length rather than one match per substring.
+pub(crate) fn find_expanded_pattern(input: &str, segments: &[GlobSegment], options: &MatchOptions) -> bool {
+    if input.is_ascii() {
+        find_piece(input.as_bytes(), segments, options)
+    } else {
+        let input_chars: Vec<char> = input.chars().collect();
+        find_piece(&input_chars, segments, options)
+    }
+}
+
+fn find_piece<C: InputUnit>(input: &[C], segments: &[GlobSegment], options: &MatchOptions) -> bool {
+    // ドットファイルのチェックは部分ごとに、その先頭の文字に当てはめる
+    let skips_dot = !options.dot && segments.first().is_some_and(GlobSegment::starts_with_star);
+    // 部分の両端はそれぞれ入力全体として照合したときと同じく、パスの区切りとして扱う
+    let mut search = Search {
+        nested: false,
+        ..Search::pieces(input, segments, options)
+    };
+    (0..=input.len()).any(|start| {
+        if skips_dot && input.get(start).is_some_and(|&c| c.into() == '.') {
+            return false;
+        }
+        search.matches(start, 0, None);
+        search.found_piece
+    })
+}
+
+/// Returns `true` if the `*` at `idx` makes up a whole path segment, so
+/// [`MatchOptions::star_matches_empty`] applies to it. `at_start` and
+/// `at_end` tell whether the ends of `segments` are path boundaries, which
+/// they are not inside extglobs.
+pub(crate) fn star_fills_segment(segments: &[GlobSegment], idx: usize, at_start: bool, at_end: bool) -> bool {
+    let starts = match idx.checked_sub(1).map(|prev| &segments[prev]) {
+        None => at_start,
+        Some(GlobSegment::Literal(literal)) => literal.last().is_some_and(|&c| is_slash(c)),
+        Some(_) => false,
+    };
+    let ends = match segments.get(idx + 1) {
+        None => at_end,
+        Some(GlobSegment::Literal(literal)) => literal.first().is_some_and(|&c| is_slash(c)),
+        Some(_) => false,
+    };
+    starts && ends
+}
+
+/// Removes the `/` ending each `**/` span in `spans` (in pattern order,
+/// as recorded for `segments` on `input`), so the span covers only the
+/// directories.
+pub(crate) fn trim_globstar_spans(spans: &mut CaptureSpans, segments: &[GlobSegment], input: &[char]) {
+    let wildcards = segments.iter().enumerate().filter(|(_, segment)| !matches!(segment, GlobSegment::Literal(_)));
+    for ((segment_idx, segment), span) in wildcards.zip(spans.iter_mut()) {
+        // 末尾の **/ は残りの入力全体を消費するので、/ で終わっていてもそのまま
+        let is_last = segment_idx + 1 == segments.len();
+        if matches!(segment, GlobSegment::Globstar { slash: true, .. })
+            && !is_last
+            && span.1 > span.0
+            && is_slash(input[span.1 - 1])
+        {
+            span.1 -= 1;
+        }
+    }
+}
+
+fn is_slash<C: Into<char>>(c: C) -> bool {
+    c.into() == '/'
+}
+
+/// A unit of matcher input: a byte for ASCII input, a `char` otherwise.
+trait InputUnit: Copy + Into<char> {
+    /// Returns the offset of the first `needle` in `haystack`, or of the
+    /// first `/` as well when `or_slash` is set.
+    fn find(haystack: &[Self], needle: char, or_slash: bool) -> Option<usize>;
+}
+
+impl InputUnit for u8 {
+    fn find(haystack: &[u8], needle: char, or_slash: bool) -> Option<usize> {
+        // ASCIIの入力に非ASCIIの文字は現れない
+        match (u8::try_from(needle).ok().filter(u8::is_ascii), or_slash) {
+            (Some(byte), true) => memchr::memchr2(byte, b'/', haystack),
+            (Some(byte), false) => memchr::memchr(byte, haystack),
+            (None, true) => memchr::memchr(b'/', haystack),
+            (None, false) => None,
+        }
+    }
+}
+
+impl InputUnit for char {
+    fn find(haystack: &[char], needle: char, or_slash: bool) -> Option<usize> {
+        haystack.iter().position(|&c| c == needle || (or_slash && c == '/'))
+    }
+}
+
+/// Returns, for each position of `input`, the end of the extended
+/// grapheme cluster it is part of, for [`MatchOptions::graphemes`].
+fn grapheme_ends<C: InputUnit>(input: &[C]) -> Vec<usize> {
+    let text: String = input.iter().map(|&c| c.into()).collect();
+    let mut ends = Vec::with_capacity(input.len());
+    for grapheme in text.graphemes(true) {
+        let len = grapheme.chars().count();
+        let end = ends.len() + len;
+        ends.extend(std::iter::repeat_n(end, len));
+    }
+    ends
+}
+
+fn push_capture(captures: Option<&mut CaptureSpans>, start: usize, end: usize) {
+    if let Some(captures) = captures {
+        captures.push((start, end));
+    }
+}
+
+/// One run of the matcher over an input, remembering which
+/// `(input_idx, segment_idx)` states are known to fail.
+struct Search<'a, C> {
+    input: &'a [C],
+    segments: &'a [GlobSegment],
+    options: &'a MatchOptions,
+    /// 失敗した状態の記録。最初に分岐が失敗したときに確保する
+    failed: Vec<bool>,
+    /// ** のセグメントごとに、続きがすべて失敗すると分かっている入力位置
+    exhausted: Vec<usize>,
+    /// 訪れた状態の数。extglob の代替パターンの照合も含む
+    steps: usize,
+    /// 失敗した状態のうち入力を最も先まで読んだもの
+    furthest: (usize, usize),
+    /// extglob の代替パターンの照合中か。代替パターンの両端はパスの区切りではない
+    nested: bool,
+    /// 書記素クラスタ単位で ? を照合するときの、各位置から次のクラスタ境界までの位置
+    grapheme_ends: Vec<usize>,
+    /// extglob の代替パターンを照合するときに、パターンを使い切れた入力位置を記録する。
+    /// 記録した後も失敗として探索を続けるので、すべての終わり方が集まる
+    piece_ends: Option<Vec<bool>>,
+    /// piece_ends に1つでも記録したか
+    found_piece: bool,
+    #[cfg(feature = "stats")]
+    stats: stats::MatchStats,
+}
+
+impl<'a, C: InputUnit> Search<'a, C> {
+    fn new(input: &'a [C], segments: &'a [GlobSegment], options: &'a MatchOptions) -> Self {
+        Self {
+            input,
+            segments,
+            options,
+            failed: Vec::new(),
+            exhausted: Vec::new(),
+            steps: 0,
+            furthest: (0, 0),
+            nested: false,
+            grapheme_ends: if options.graphemes { grapheme_ends(input) } else { Vec::new() },
+            piece_ends: None,
+            found_piece: false,
+            #[cfg(feature = "stats")]
+            stats: stats::MatchStats::default(),
+        }
+    }
+
+    /// Creates the search for one alternative of an extglob spanning
+    /// `input`, which records every position a piece of `input` matching
+    /// `segments` can end at instead of matching all of `input`.
+    fn pieces(input: &'a [C], segments: &'a [GlobSegment], options: &'a MatchOptions) -> Self {
+        Self {
+            nested: true,
+            piece_ends: Some(vec![false; input.len() + 1]),
+            ..Self::new(input, segments, options)
+        }
+    }
+
+    /// Decides the outcome once the segments are used up at `input_idx`:
+    /// the input must be used up too, except when collecting piece ends,
+    /// where the end is recorded and the search goes on.
+    fn reach_end(&mut self, input_idx: usize) -> bool {
+        match &mut self.piece_ends {
+            Some(ends) => {
+                ends[input_idx] = true;
+                self.found_piece = true;
                 false
             }
+            None => input_idx >= self.input.len(),
+        }
+    }
+
+    /// Returns `true` if a piece started so far can end at `input_idx`.
+    fn piece_ends_at(&self, input_idx: usize) -> bool {
+        self.piece_ends.as_ref().is_some_and(|ends| ends[input_idx])
+    }
+
+    /// Returns where the single character `?` matches at `input_idx` ends.
+    fn any_char_end(&self, input_idx: usize) -> usize {
+        match self.grapheme_ends.get(input_idx) {
+            // 前置文字と / のようにクラスタが / を含んでも、区切りは越えない
+            Some(&end) => {
+                C::find(&self.input[input_idx + 1..end], '/', false).map_or(end, |offset| input_idx + 1 + offset)
+            }
+            None => input_idx + 1,
         }
-        '[' => {
-            // 文字クラス（[abc], [a-z], [^abc]など）をマッチ
-            match_character_class(input, pattern, input_idx, pattern_idx)
+    }
+
+    /// Matches the whole input. A search that runs out of
+    /// [`max_steps`](MatchOptions::max_steps) fails even if it matched.
+    fn run(mut self, captures: Option<&mut CaptureSpans>) -> Result<(), Mismatch> {
+        let matched = self.matches(0, 0, captures);
+        #[cfg(feature = "stats")]
+        {
+            self.stats.steps = self.steps;
+            self.stats.peak_memo_size = self.failed.len() + self.exhausted.len();
+            stats::add(&self.stats);
         }
-        _ => {
-            // 通常文字の場合は完全一致が必要
-            if input_char == pattern_char {
-                match_pattern(input, pattern, input_idx + 1, pattern_idx + 1)
+        if self.over_budget() {
+            Err(Mismatch::StepLimit)
+        } else if matched {
+            Ok(())
+        } else {
+            Err(Mismatch::Failed(self.furthest.0, self.furthest.1))
+        }
+    }
+
+    /// Counts a continuation tried at a branch, for [`MatchStats`].
+    fn count_branch(&mut self, _memo_hit: bool, _failed: bool) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.branches += 1;
+            if _memo_hit {
+                self.stats.memo_hits += 1;
             } else {
-                false
+                self.stats.memo_misses += 1;
+                self.stats.backtracks += usize::from(_failed);
             }
         }
     }
-}
 
-fn match_globstar(input: Vec<char>, pattern: Vec<char>, input_idx: usize, pattern_idx: usize) -> bool {
-    // **の後の文字をスキップ（通常は/）
-    let mut next_pattern_idx = pattern_idx;
-    let has_slash_after_globstar = next_pattern_idx < pattern.len() && pattern[next_pattern_idx] == '/';
-    if has_slash_after_globstar {
-        next_pattern_idx += 1;
+    fn over_budget(&self) -> bool {
+        self.options.max_steps.is_some_and(|max_steps| self.steps > max_steps)
     }
-    
-    // パターンの末尾に到達した場合、**は残りの入力全てにマッチ
-    if next_pattern_idx >= pattern.len() {
-        return true;
+
+    /// Returns `true` if the input from `input_idx` matches the segments
+    /// from `segment_idx`.
+    fn matches(&mut self, mut input_idx: usize, mut segment_idx: usize, mut captures: Option<&mut CaptureSpans>) -> bool {
+        let (input, segments) = (self.input, self.segments);
+        self.steps += 1;
+        if self.over_budget() {
+            return false;
+        }
+        // ? と文字クラスが消費した範囲。後ろのワイルドカードより後に積むため、成功が確定するまで保留する
+        let mut pending = Vec::new();
+
+        // 1文字ずつ消費するセグメントはループで進め、* / ** / extglob の分岐でだけ再帰する
+        let matched = loop {
+            let Some(segment) = segments.get(segment_idx) else {
+                break self.reach_end(input_idx);
+            };
+
+            // extglob（@(a|b), *(a|b) など）は入力の残りに関係なく評価する
+            if let GlobSegment::Extglob { .. } = segment {
+                break self.match_extglob(input_idx, segment_idx, captures.as_deref_mut());
+            }
+
+            // 入力のみ末尾に到達
+            if input_idx >= input.len() {
+                // 空文字列にマッチしうる extglob が後に残っていれば、* と ** も通常どおり評価する
+                if segment.is_star() && segments[segment_idx..].iter().any(|rest| matches!(rest, GlobSegment::Extglob { .. })) {
+                    break match segment {
+                        GlobSegment::Wildcard => self.match_star(input_idx, segment_idx, captures.as_deref_mut()),
+                        _ => self.match_globstar(input_idx, segment_idx, captures.as_deref_mut()),
+                    };
+                }
+                // 残りのパターンが全て*であれば一致し、それぞれ空文字列をキャプチャする
+                let rest = &segments[segment_idx..];
+                let all_empty = (segment_idx..segments.len()).all(|idx| self.star_allows_empty(idx));
+                if !rest.iter().all(GlobSegment::is_star) || !all_empty {
+                    break false;
+                }
+                for _ in rest {
+                    push_capture(captures.as_deref_mut(), input_idx, input_idx);
+                }
+                break self.reach_end(input_idx);
+            }
+
+            match segment {
+                GlobSegment::Literal(literal) => {
+                    // 通常文字の場合は完全一致が必要
+                    let end = input_idx + literal.len();
+                    if end > input.len()
+                        || !input[input_idx..end]
+                            .iter()
+                            .zip(literal)
+                            .all(|(&input_char, &literal_char)| chars_match(input_char.into(), literal_char, self.options))
+                    {
+                        break false;
+                    }
+                    input_idx = end;
+                }
+                GlobSegment::AnyChar => {
+                    // ?は任意の1文字にマッチ（ただし/は除く）
+                    if is_slash(input[input_idx]) {
+                        break false;
+                    }
+                    let end = self.any_char_end(input_idx);
+                    if captures.is_some() {
+                        pending.push((input_idx, end));
+                    }
+                    input_idx = end;
+                }
+                GlobSegment::CharClass(content) => {
+                    // 文字クラス（[abc], [a-z], [^abc]など）をマッチ
+                    let (is_negated, content) = split_class_negation(content, self.options.class_bang);
+                    if is_char_in_class(input[input_idx].into(), content, self.options) == is_negated {
+                        break false;
+                    }
+                    if captures.is_some() {
+                        pending.push((input_idx, input_idx + 1));
+                    }
+                    input_idx += 1;
+                }
+                GlobSegment::Wildcard => break self.match_star(input_idx, segment_idx, captures.as_deref_mut()),
+                GlobSegment::Globstar { .. } => {
+                    break self.match_globstar(input_idx, segment_idx, captures.as_deref_mut())
+                }
+                GlobSegment::Extglob { .. } => unreachable!("extglob segments are handled above"),
+            }
+            segment_idx += 1;
+        };
+
+        if matched {
+            if let Some(captures) = captures {
+                captures.extend(pending.into_iter().rev());
+            }
+        } else {
+            self.furthest = self.furthest.max((input_idx, segment_idx));
+        }
+        matched
     }
-    
-    // スラッシュ後のパターンがある場合は、少なくとも1つのディレクトリ境界を要求
-    if has_slash_after_globstar {
-        // 0文字マッチを試す（**が空文字にマッチする場合）- ただし後に/がある場合は制限的
-        // src/**/*.jsのような場合、src/main.jsはマッチしないべき（中間ディレクトリが必要）
-        // しかし src/**/main.js の場合、src/main.js はマッチするべき
-        // test/**/*.js の場合、test/main.test.js もマッチするべき
-        let should_require_intermediate = needs_intermediate_directory(&pattern, pattern_idx, next_pattern_idx) 
-            && has_multiple_path_components_after_globstar(&pattern, next_pattern_idx);
-        if !should_require_intermediate {
-            if match_pattern(input.clone(), pattern.clone(), input_idx, next_pattern_idx) {
-                return true;
+
+    /// Like [`Search::matches`], for the continuations tried at a branch.
+    /// A state that failed once fails again, so it is only searched once.
+    fn matches_from(&mut self, input_idx: usize, segment_idx: usize, captures: Option<&mut CaptureSpans>) -> bool {
+        // 成功は即座に呼び出し元へ伝播するため、記録が必要なのは失敗だけ
+        if self.has_failed(input_idx, segment_idx) {
+            self.count_branch(true, true);
+            return false;
+        }
+        if self.matches(input_idx, segment_idx, captures) {
+            self.count_branch(false, false);
+            return true;
+        }
+        self.count_branch(false, true);
+        self.mark_failed(input_idx..input_idx + 1, segment_idx);
+        false
+    }
+
+    fn has_failed(&self, input_idx: usize, segment_idx: usize) -> bool {
+        let state = input_idx * (self.segments.len() + 1) + segment_idx;
+        self.failed.get(state) == Some(&true)
+    }
+
+    fn mark_failed(&mut self, input_range: Range<usize>, segment_idx: usize) {
+        let width = self.segments.len() + 1;
+        if self.failed.is_empty() {
+            self.failed = vec![false; (self.input.len() + 1) * width];
+        }
+        for input_idx in input_range {
+            self.failed[input_idx * width + segment_idx] = true;
+        }
+    }
+
+    /// Returns the input position from which every continuation after the
+    /// `**` at `segment_idx` is known to fail.
+    fn exhausted_from(&self, segment_idx: usize) -> usize {
+        self.exhausted.get(segment_idx).copied().unwrap_or(usize::MAX)
+    }
+
+    fn mark_exhausted(&mut self, segment_idx: usize, input_idx: usize) {
+        if self.exhausted.is_empty() {
+            self.exhausted = vec![usize::MAX; self.segments.len()];
+        }
+        let exhausted = &mut self.exhausted[segment_idx];
+        *exhausted = (*exhausted).min(input_idx);
+    }
+
+    /// Returns `false` for a `*` that must consume at least one character
+    /// under [`MatchOptions::star_matches_empty`].
+    fn star_allows_empty(&self, segment_idx: usize) -> bool {
+        // パスの1区間全体を占める * は、オプションによっては空文字列にマッチしない
+        self.options.star_matches_empty
+            || !matches!(self.segments[segment_idx], GlobSegment::Wildcard)
+            || !star_fills_segment(self.segments, segment_idx, !self.nested, !self.nested)
+    }
+
+    fn match_star(&mut self, input_idx: usize, segment_idx: usize, mut captures: Option<&mut CaptureSpans>) -> bool {
+        let input = self.input;
+        if self.has_failed(input_idx, segment_idx) {
+            self.count_branch(true, true);
+            return false;
+        }
+
+        // *は0文字以上の任意文字にマッチ（ただし/は除く）
+        let allows_empty = self.star_allows_empty(segment_idx);
+
+        // 次のパターンがない場合、残りの入力全てをマッチ（/を除く）
+        if segment_idx + 1 >= self.segments.len() {
+            if let Some(ends) = &mut self.piece_ends {
+                // 代替パターンの照合では / の手前のどこで終わってもよい
+                let stop = input[input_idx..].iter().position(|&c| is_slash(c)).map_or(input.len(), |offset| input_idx + offset);
+                let first = if allows_empty { input_idx } else { input_idx + 1 };
+                ends.iter_mut().take(stop + 1).skip(first).for_each(|end| *end = true);
+                self.found_piece |= first <= stop;
+                // stop までのどの位置から始めても、記録した終わり方のどれかにしかならない
+                self.mark_failed(input_idx..stop + 1, segment_idx);
+                return false;
+            }
+            if !allows_empty && input_idx >= input.len() {
+                return false;
             }
+            if let Some(offset) = input[input_idx..].iter().position(|&c| is_slash(c)) {
+                // / までのどの位置から始めても同じ / で失敗する
+                self.mark_failed(input_idx..input_idx + offset + 1, segment_idx);
+                return false;
+            }
+            push_capture(captures, input_idx, input.len());
+            return true;
         }
-        
-        // 少なくとも1つのスラッシュを含む場合のみマッチを試す
-        let mut found_slash = false;
-        for i in input_idx..input.len() {
-            if input[i] == '/' {
-                found_slash = true;
+
+        // 0文字マッチを試す
+        if allows_empty && self.matches_from(input_idx, segment_idx + 1, captures.as_deref_mut()) {
+            push_capture(captures, input_idx, input_idx);
+            return true;
+        }
+
+        // 1文字ずつマッチを試す（/以外の文字のみ）
+        // end までの位置から * を始めても、試した続きのどれかにしかならない
+        let mut end = input_idx;
+        while end < input.len() && !is_slash(input[end]) && !self.has_failed(end + 1, segment_idx) {
+            // 続きのリテラルが始まりえない位置は飛ばす。* は / を越えないので / でも止まる
+            end = self.next_candidate(end + 1, segment_idx, true) - 1;
+            if end >= input.len() || self.has_failed(end + 1, segment_idx) {
+                break;
             }
-            if found_slash && match_pattern(input.clone(), pattern.clone(), i + 1, next_pattern_idx) {
+            if self.matches_from(end + 1, segment_idx + 1, captures.as_deref_mut()) {
+                push_capture(captures, input_idx, end + 1);
                 return true;
             }
+            end += 1;
         }
-    } else {
-        // 0文字マッチを試す
-        if match_pattern(input.clone(), pattern.clone(), input_idx, next_pattern_idx) {
+        self.mark_failed(input_idx..end + 1, segment_idx);
+        false
+    }
+
+    /// Returns the first position from `from` where the segments after
+    /// `segment_idx` could start matching, or past the end of the input if
+    /// there is none. Only a literal next segment rules positions out; with
+    /// `or_slash`, a `/` is a candidate too.
+    fn next_candidate(&self, from: usize, segment_idx: usize, or_slash: bool) -> usize {
+        let first = match self.segments.get(segment_idx + 1) {
+            // nocase では畳み込んだ文字同士を比べるので、そのまま探せない
+            Some(GlobSegment::Literal(literal)) if !self.options.nocase => literal[0],
+            _ => return from,
+        };
+        match self.input.get(from..).and_then(|rest| C::find(rest, first, or_slash)) {
+            Some(offset) => from + offset,
+            None => self.input.len() + 1,
+        }
+    }
+
+    fn match_globstar(&mut self, input_idx: usize, segment_idx: usize, mut captures: Option<&mut CaptureSpans>) -> bool {
+        let input = self.input;
+        let GlobSegment::Globstar { slash, requires_directory } = self.segments[segment_idx] else {
+            unreachable!("caller passes a globstar segment");
+        };
+
+        // パターンの末尾に到達した場合、**は残りの入力全てにマッチ
+        if segment_idx + 1 >= self.segments.len() {
+            if let Some(ends) = &mut self.piece_ends {
+                // 代替パターンの照合ではどこで終わってもよい
+                ends[input_idx..].iter_mut().for_each(|end| *end = true);
+                self.found_piece = true;
+                return false;
+            }
+            push_capture(captures, input_idx, input.len());
             return true;
         }
-        
+
+        // 続きを試す最初の位置。スラッシュ後のパターンがある場合は、少なくとも1つのディレクトリ境界を要求
+        let first = if slash {
+            // 0文字マッチを試す（**が空文字にマッチする場合）- ただし中間ディレクトリが必要な場合を除く
+            if !requires_directory && self.matches_from(input_idx, segment_idx + 1, captures.as_deref_mut()) {
+                push_capture(captures, input_idx, input_idx);
+                return true;
+            }
+            match input[input_idx..].iter().position(|&c| is_slash(c)) {
+                Some(offset) => input_idx + offset + 1,
+                None => return false,
+            }
+        } else {
+            input_idx
+        };
+
         // 1文字以上マッチを試す（任意の文字、/を含む）
-        for i in input_idx..input.len() {
-            if match_pattern(input.clone(), pattern.clone(), i + 1, next_pattern_idx) {
+        // 以前に失敗した位置より後の続きは試さない
+        let exhausted = self.exhausted_from(segment_idx);
+        let mut next = first;
+        loop {
+            next = self.next_candidate(next, segment_idx, false);
+            if next > input.len() || next >= exhausted {
+                break;
+            }
+            if self.matches_from(next, segment_idx + 1, captures.as_deref_mut()) {
+                push_capture(captures, input_idx, next);
                 return true;
             }
+            next += 1;
+        }
+        self.mark_exhausted(segment_idx, first);
+        false
+    }
+
+    fn match_extglob(&mut self, input_idx: usize, segment_idx: usize, mut captures: Option<&mut CaptureSpans>) -> bool {
+        let (input, segments, options) = (self.input, self.segments, self.options);
+        let GlobSegment::Extglob { kind, alternatives, crosses_separator } = &segments[segment_idx] else {
+            unreachable!("caller passes an extglob segment");
+        };
+        if self.has_failed(input_idx, segment_idx) {
+            self.count_branch(true, true);
+            return false;
+        }
+
+        // 代替パターンが/を含まない限り、extglobはパス区切りを越えない
+        let limit = match input[input_idx..].iter().position(|&c| is_slash(c)) {
+            Some(offset) if !crosses_separator => input_idx + offset,
+            _ => input.len(),
+        };
+        // 代替パターンごとに1つの探索で、範囲内のどこまでマッチしうるかを集める。
+        // 失敗した状態は探索をまたいで覚えているので、繰り返しで開始位置が増えても各状態は1度しか調べない
+        let span = &input[input_idx..limit];
+        let mut pieces: Vec<Search<'a, C>> =
+            alternatives.iter().map(|alternative| Search::pieces(span, alternative, options)).collect();
+        self.start_pieces(&mut pieces, 0);
+        let repeats = matches!(kind, ExtglobKind::OneOrMore | ExtglobKind::ZeroOrMore);
+
+        // extglobが消費する範囲を1文字ずつ伸ばしながら、残りのパターンとのマッチを試す
+        for offset in 0..=span.len() {
+            // 繰り返しでは、直前の位置まで消費できていればそこから次の1回を始める
+            if repeats && offset > 1 && pieces.iter().any(|piece| piece.piece_ends_at(offset - 1)) {
+                self.start_pieces(&mut pieces, offset - 1);
+            }
+            if self.over_budget() {
+                return false;
+            }
+            let reached = pieces.iter().any(|piece| piece.piece_ends_at(offset));
+            let span_matches = match kind {
+                ExtglobKind::ExactlyOne | ExtglobKind::OneOrMore => reached,
+                ExtglobKind::ZeroOrOne | ExtglobKind::ZeroOrMore => offset == 0 || reached,
+                // どの代替パターンにもマッチしない範囲を受け入れる
+                ExtglobKind::Negated => !reached,
+            };
+            let end = input_idx + offset;
+            if span_matches && self.matches_from(end, segment_idx + 1, captures.as_deref_mut()) {
+                push_capture(captures, input_idx, end);
+                return true;
+            }
+        }
+        if repeats {
+            // 繰り返しで到達できた位置から始めても、到達できるのはここから到達できた位置の一部だけなので失敗する
+            for offset in 1..=span.len() {
+                if pieces.iter().any(|piece| piece.piece_ends_at(offset)) {
+                    self.mark_failed(input_idx + offset..input_idx + offset + 1, segment_idx);
+                }
+            }
+        }
+        false
+    }
+
+    /// Starts a piece at `offset` in the search of each alternative.
+    fn start_pieces(&mut self, pieces: &mut [Search<'a, C>], offset: usize) {
+        for piece in pieces {
+            piece.steps = self.steps;
+            piece.matches(offset, 0, None);
+            self.steps = piece.steps;
         }
     }
-    
-    false
 }
 
-fn needs_intermediate_directory(pattern: &Vec<char>, globstar_start: usize, next_idx: usize) -> bool {
+fn needs_intermediate_directory(pattern: &[char], globstar_start: usize, next_idx: usize) -> bool {
     // **の前と後両方にパターンがある場合、中間ディレクトリが必要
     // globstar_start は **の後の位置を指すので、実際の**の開始位置は globstar_start - 2
     let actual_globstar_start = globstar_start.saturating_sub(2);
@@ -202,7 +1855,7 @@ fn needs_intermediate_directory(pattern: &Vec<char>, globstar_start: usize, next
     has_prefix && has_suffix
 }
 
-fn has_multiple_path_components_after_globstar(pattern: &Vec<char>, next_idx: usize) -> bool {
+fn has_multiple_path_components_after_globstar(pattern: &[char], next_idx: usize) -> bool {
     // **/ の後に複数のパス要素があるかチェック
     // 例：**/*.js は1つのパス要素だが、prefix/**/*.js の形では中間ディレクトリが必要
     // この関数は、パターンが "prefix/**/*.ext" の形かどうかを判定する
@@ -222,7 +1875,10 @@ fn has_multiple_globstars(pattern: &[char]) -> bool {
     let mut globstar_count = 0;
     let mut i = 0;
     while i + 1 < pattern.len() {
-        if pattern[i] == '*' && pattern[i + 1] == '*' {
+        if pattern[i] == '\\' {
+            // エスケープされた * は数えない
+            i += 2;
+        } else if pattern[i] == '*' && pattern[i + 1] == '*' {
             globstar_count += 1;
             i += 2; // **をスキップ
             if globstar_count > 1 {
@@ -235,53 +1891,132 @@ fn has_multiple_globstars(pattern: &[char]) -> bool {
     false
 }
 
-fn parse_glob_segments(pattern: &[char]) -> Vec<GlobSegment> {
+/// Rewrites `**` as `*`: every one with `whole_segments`, for
+/// [`MatchOptions::noglobstar`], and otherwise those that are not a whole
+/// path segment, for [`MatchOptions::strict_globstar`].
+pub(crate) fn collapse_globstars(pattern: &[char], whole_segments: bool) -> Vec<char> {
+    let delimiters = Delimiters::new(pattern);
+    let mut collapsed = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        let end = match pattern[i] {
+            '\\' => (i + 2).min(pattern.len()),
+            '[' => delimiters.class_end(i).map_or(i + 1, |class_end| class_end + 1),
+            '*' => {
+                let end = i + pattern[i..].iter().take_while(|&&c| c == '*').count();
+                let whole_segment =
+                    (i == 0 || pattern[i - 1] == '/') && (end == pattern.len() || pattern[end] == '/');
+                // *( で始まる extglob の演算子はそのまま残す
+                if end - i >= 2 && (whole_segments || !whole_segment) && pattern.get(end) != Some(&'(') {
+                    collapsed.push('*');
+                    i = end;
+                    continue;
+                }
+                end
+            }
+            _ => i + 1,
+        };
+        collapsed.extend_from_slice(&pattern[i..end]);
+        i = end;
+    }
+    collapsed
+}
+
+/// Extglobs nested deeper than this are rejected, as matching them
+/// recurses once per level.
+const MAX_EXTGLOB_DEPTH: usize = 32;
+
+/// Parses a brace-free pattern into the segments the matcher runs on.
+/// Extglobs nested too deeply are kept as literal text.
+pub(crate) fn parse_glob_segments(pattern: &[char]) -> Vec<GlobSegment> {
+    parse_glob_segments_within(pattern, usize::MAX).unwrap_or_else(|_| vec![GlobSegment::Literal(pattern.to_vec())])
+}
+
+/// Like [`parse_glob_segments`], failing when the pattern has more than
+/// `max_segments` segments (counting those inside extglobs) or nests
+/// extglobs too deeply.
+pub(crate) fn parse_glob_segments_within(
+    pattern: &[char],
+    max_segments: usize,
+) -> Result<Vec<GlobSegment>, PatternErrorKind> {
+    let mut budget = max_segments;
+    parse_segments(pattern, !has_multiple_globstars(pattern), &mut budget, 0)
+}
+
+/// With `single_globstar`, a `prefix/**/*.ext` globstar must span at least
+/// one directory. Each segment is taken from `budget`.
+fn parse_segments(
+    pattern: &[char],
+    single_globstar: bool,
+    budget: &mut usize,
+    depth: usize,
+) -> Result<Vec<GlobSegment>, PatternErrorKind> {
+    let delimiters = Delimiters::new(pattern);
     let mut segments = Vec::new();
     let mut i = 0;
-    let mut current_literal = String::new();
+    let mut current_literal = Vec::new();
 
     while i < pattern.len() {
+        if let Some((kind, close_idx)) = delimiters.extglob(i) {
+            flush_literal(&mut segments, &mut current_literal, budget)?;
+            if depth >= MAX_EXTGLOB_DEPTH {
+                return Err(PatternErrorKind::TooDeeplyNested);
+            }
+            take_segment(budget)?;
+            let alternatives = split_alternatives(&pattern[i + 2..close_idx]);
+            let extglob = GlobSegment::Extglob {
+                kind,
+                crosses_separator: alternatives.iter().any(|alt| alt.contains(&'/')),
+                alternatives: alternatives
+                    .iter()
+                    .map(|alt| parse_segments(alt, single_globstar, budget, depth + 1))
+                    .collect::<Result<_, _>>()?,
+            };
+            segments.push(extglob);
+            i = close_idx + 1;
+            continue;
+        }
+
         match pattern[i] {
             '*' if i + 1 < pattern.len() && pattern[i + 1] == '*' => {
-                // Globstar (**) の処理
-                if !current_literal.is_empty() {
-                    segments.push(GlobSegment::Literal(current_literal.clone()));
-                    current_literal.clear();
-                }
-                segments.push(GlobSegment::Globstar);
-                i += 2;
-                // ** の後の / をスキップ
-                if i < pattern.len() && pattern[i] == '/' {
-                    i += 1;
-                }
+                // Globstar (**) の処理。直後の / は ** の一部として扱う
+                let slash = pattern.get(i + 2) == Some(&'/');
+                let next_idx = if slash { i + 3 } else { i + 2 };
+                // src/**/*.jsのような場合、src/main.jsはマッチしないべき（中間ディレクトリが必要）
+                // しかし src/**/main.js の場合、src/main.js はマッチするべき
+                // 複数の ** を含むパターンでは中間ディレクトリを要求しない
+                let requires_directory = single_globstar
+                    && slash
+                    && needs_intermediate_directory(pattern, i + 2, next_idx)
+                    && has_multiple_path_components_after_globstar(pattern, next_idx);
+                let globstar = GlobSegment::Globstar { slash, requires_directory };
+                push_segment(&mut segments, &mut current_literal, globstar, budget)?;
+                i = next_idx;
             }
             '*' => {
-                // 単一のワイルドカード
-                if !current_literal.is_empty() {
-                    segments.push(GlobSegment::Literal(current_literal.clone()));
-                    current_literal.clear();
-                }
-                segments.push(GlobSegment::Wildcard);
+                push_segment(&mut segments, &mut current_literal, GlobSegment::Wildcard, budget)?;
                 i += 1;
             }
-            '[' => {
-                // 文字クラスの処理
-                if !current_literal.is_empty() {
-                    segments.push(GlobSegment::Literal(current_literal.clone()));
-                    current_literal.clear();
-                }
-                
-                let mut class_content = String::new();
-                let mut j = i;
-                while j < pattern.len() {
-                    class_content.push(pattern[j]);
-                    if j > i && pattern[j] == ']' {
-                        break;
-                    }
-                    j += 1;
+            '?' => {
+                push_segment(&mut segments, &mut current_literal, GlobSegment::AnyChar, budget)?;
+                i += 1;
+            }
+            '[' => match delimiters.class_end(i) {
+                Some(class_end) => {
+                    let class = GlobSegment::CharClass(pattern[i + 1..class_end].to_vec());
+                    push_segment(&mut segments, &mut current_literal, class, budget)?;
+                    i = class_end + 1;
                 }
-                segments.push(GlobSegment::CharClass(class_content));
-                i = j + 1;
+                None => {
+                    // 終端が見つからない場合は、'['を通常の文字として扱う
+                    current_literal.push('[');
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < pattern.len() => {
+                // エスケープされた文字はリテラルに含める
+                current_literal.push(pattern[i + 1]);
+                i += 2;
             }
             ch => {
                 current_literal.push(ch);
@@ -290,201 +2025,88 @@ fn parse_glob_segments(pattern: &[char]) -> Vec<GlobSegment> {
         }
     }
 
-    if !current_literal.is_empty() {
-        segments.push(GlobSegment::Literal(current_literal));
-    }
-
-    segments
+    flush_literal(&mut segments, &mut current_literal, budget)?;
+    Ok(segments)
 }
 
-use std::collections::HashMap;
+/// Pushes `segment` after the literal text collected before it, taking
+/// both from `budget`.
+fn push_segment(
+    segments: &mut Vec<GlobSegment>,
+    current_literal: &mut Vec<char>,
+    segment: GlobSegment,
+    budget: &mut usize,
+) -> Result<(), PatternErrorKind> {
+    flush_literal(segments, current_literal, budget)?;
+    take_segment(budget)?;
+    segments.push(segment);
+    Ok(())
+}
 
-type MemoKey = (usize, usize); // (input_idx, segment_idx)
-type MemoCache = HashMap<MemoKey, bool>;
+fn flush_literal(
+    segments: &mut Vec<GlobSegment>,
+    current_literal: &mut Vec<char>,
+    budget: &mut usize,
+) -> Result<(), PatternErrorKind> {
+    if !current_literal.is_empty() {
+        take_segment(budget)?;
+        segments.push(GlobSegment::Literal(std::mem::take(current_literal)));
+    }
+    Ok(())
+}
 
-fn match_with_segments(input: &str, segments: &[GlobSegment]) -> bool {
-    let mut memo = MemoCache::new();
-    let input_chars: Vec<char> = input.chars().collect();
-    match_segments_with_memo(&input_chars, 0, segments, 0, &mut memo)
+fn take_segment(budget: &mut usize) -> Result<(), PatternErrorKind> {
+    *budget = budget.checked_sub(1).ok_or(PatternErrorKind::TooManySegments)?;
+    Ok(())
 }
 
-fn match_segments_with_memo(
-    input_chars: &[char], 
-    input_idx: usize, 
-    segments: &[GlobSegment], 
-    segment_idx: usize, 
-    memo: &mut MemoCache
-) -> bool {
-    let key = (input_idx, segment_idx);
-    
-    // メモ化されている場合は結果を返す
-    if let Some(&result) = memo.get(&key) {
-        return result;
-    }
-    
-    let result = match_segments_recursive_optimized(input_chars, input_idx, segments, segment_idx, memo);
-    memo.insert(key, result);
-    result
+/// Returns `true` if the character class `[content]` matches `/`.
+pub(crate) fn class_matches_slash(content: &[char], options: &MatchOptions) -> bool {
+    class_matches('/', content, options)
 }
 
-fn match_segments_recursive_optimized(
-    input_chars: &[char], 
-    input_idx: usize, 
-    segments: &[GlobSegment], 
-    segment_idx: usize, 
-    memo: &mut MemoCache
-) -> bool {
-    // 全セグメントを処理した場合
-    if segment_idx >= segments.len() {
-        return input_idx >= input_chars.len();
-    }
+/// Returns `true` if the character class `[content]` matches `c`,
+/// negation included.
+pub(crate) fn class_matches(c: char, content: &[char], options: &MatchOptions) -> bool {
+    let (is_negated, content) = split_class_negation(content, options.class_bang);
+    is_char_in_class(c, content, options) != is_negated
+}
 
-    // 入力が終了した場合
-    if input_idx >= input_chars.len() {
-        // 残りのセグメントが全てGlobstarであれば一致
-        return segments[segment_idx..].iter().all(|seg| matches!(seg, GlobSegment::Globstar));
+fn is_char_in_class(input_char: char, class_content: &[char], options: &MatchOptions) -> bool {
+    if options.nocase {
+        // 大文字・小文字のどちらかがクラスに含まれていれば一致
+        return case_variants(input_char).any(|c| class_contains(c, class_content));
     }
+    class_contains(input_char, class_content)
+}
 
-    match &segments[segment_idx] {
-        GlobSegment::Literal(lit) => {
-            let lit_chars: Vec<char> = lit.chars().collect();
-            if input_idx + lit_chars.len() <= input_chars.len() 
-                && input_chars[input_idx..input_idx + lit_chars.len()] == lit_chars {
-                match_segments_with_memo(input_chars, input_idx + lit_chars.len(), segments, segment_idx + 1, memo)
-            } else {
-                false
-            }
-        }
-        GlobSegment::Wildcard => {
-            // * は / 以外の文字を1文字以上マッチ
-            // 0文字マッチは許可しない（元の実装に合わせて）
-            for i in input_idx..input_chars.len() {
-                if input_chars[i] == '/' {
-                    break;
-                }
-                if match_segments_with_memo(input_chars, i + 1, segments, segment_idx + 1, memo) {
-                    return true;
-                }
-            }
-            false
-        }
-        GlobSegment::Globstar => {
-            // ** は任意の長さのパスにマッチ
-            // 0文字マッチを試す
-            if match_segments_with_memo(input_chars, input_idx, segments, segment_idx + 1, memo) {
-                return true;
-            }
+fn class_contains(input_char: char, class_content: &[char]) -> bool {
+    // 範囲指定（例: a-z）、POSIXクラス（例: [:alpha:]）、単一文字のいずれかに含まれるか
+    class_items(class_content).any(|item| item.contains(input_char))
+}
 
-            // 1文字以上マッチを試す
-            for i in input_idx..input_chars.len() {
-                if match_segments_with_memo(input_chars, i + 1, segments, segment_idx + 1, memo) {
-                    return true;
-                }
-            }
-            false
-        }
-        GlobSegment::CharClass(class) => {
-            if input_idx < input_chars.len() {
-                let ch = input_chars[input_idx];
-                if matches_char_class(ch, class) {
-                    match_segments_with_memo(input_chars, input_idx + 1, segments, segment_idx + 1, memo)
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        }
-    }
+/// Compares an input character with a literal pattern character.
+fn chars_match(input_char: char, pattern_char: char, options: &MatchOptions) -> bool {
+    input_char == pattern_char || (options.nocase && fold_case(input_char) == fold_case(pattern_char))
 }
 
-fn matches_char_class(ch: char, class: &str) -> bool {
-    // 簡単な文字クラス実装（既存のmatch_character_class関数を流用可能）
-    let chars: Vec<char> = class.chars().collect();
-    if chars.len() < 3 || chars[0] != '[' || chars[chars.len() - 1] != ']' {
-        return false;
-    }
-    
-    let content = &chars[1..chars.len() - 1];
-    let is_negated = !content.is_empty() && content[0] == '^';
-    let actual_content = if is_negated { &content[1..] } else { content };
-    
-    let matches = is_char_in_class(ch, actual_content);
-    if is_negated { !matches } else { matches }
+/// Lowercases `c` when its lowercase form is a single character.
+fn fold_case(c: char) -> char {
+    single_char(c.to_lowercase()).unwrap_or(c)
 }
 
-fn match_character_class(input: Vec<char>, pattern: Vec<char>, input_idx: usize, pattern_idx: usize) -> bool {
-    if input_idx >= input.len() {
-        return false;
-    }
-    
-    let input_char = input[input_idx];
-    
-    // 文字クラスの終端']'を見つける
-    let mut class_end = pattern_idx + 1;
-    let mut found_end = false;
-    while class_end < pattern.len() {
-        if pattern[class_end] == ']' {
-            found_end = true;
-            break;
-        }
-        class_end += 1;
-    }
-    
-    if !found_end {
-        // 終端が見つからない場合は、'['を通常の文字として扱う
-        if input_char == '[' {
-            return match_pattern(input, pattern, input_idx + 1, pattern_idx + 1);
-        } else {
-            return false;
-        }
-    }
-    
-    // 文字クラスの内容を抽出
-    let class_content: Vec<char> = pattern[(pattern_idx + 1)..class_end].to_vec();
-    
-    // 否定文字クラスかチェック
-    let is_negated = !class_content.is_empty() && class_content[0] == '^';
-    let content = if is_negated {
-        &class_content[1..]
-    } else {
-        &class_content
-    };
-    
-    // 文字クラス内でマッチするかチェック
-    let matches = is_char_in_class(input_char, content);
-    
-    // 否定文字クラスの場合は結果を反転
-    let result = if is_negated { !matches } else { matches };
-    
-    if result {
-        match_pattern(input, pattern, input_idx + 1, class_end + 1)
-    } else {
-        false
-    }
+/// Yields `c` followed by its single-character lowercase and uppercase forms.
+fn case_variants(c: char) -> impl Iterator<Item = char> {
+    std::iter::once(c)
+        .chain(single_char(c.to_lowercase()))
+        .chain(single_char(c.to_uppercase()))
 }
 
-fn is_char_in_class(input_char: char, class_content: &[char]) -> bool {
-    let mut i = 0;
-    while i < class_content.len() {
-        // 範囲指定かどうかをチェック: 現在位置+2が範囲内で、+1の位置が'-'
-        if i + 1 < class_content.len() && i + 2 < class_content.len() && class_content[i + 1] == '-' {
-            // 範囲指定（例: a-z）
-            let start = class_content[i];
-            let end = class_content[i + 2];
-            if input_char >= start && input_char <= end {
-                return true;
-            }
-            i += 3;
-        } else {
-            // 単一文字
-            if input_char == class_content[i] {
-                return true;
-            }
-            i += 1;
-        }
+fn single_char(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(only), None) => Some(only),
+        _ => None,
     }
-    false
 }
 
 #[cfg(test)]
@@ -592,7 +2214,7 @@ mod tests {
     }
 
     // 5. 大文字小文字の違いを無視するオプションのテスト
-    // Note: 現在のAPIは大文字小文字を区別します。将来的にオプションを追加する予定
+    // Note: デフォルトでは大文字小文字を区別します。無視するには nocase オプションを使用
     #[test]
     fn test_case_sensitive_match() {
         assert!(is_match("Hello", "Hello"));
@@ -600,6 +2222,19 @@ mod tests {
         assert!(!is_match("HELLO", "hello"));
     }
 
+    #[test]
+    fn test_nocase_option() {
+        let options = MatchOptions::new().nocase(true);
+        assert!(is_match_with("HELLO", "hello", &options));
+        assert!(is_match_with("src/Main.RS", "src/*.rs", &options));
+        assert!(is_match_with("File.txt", "[a-z]*.txt", &options));
+        assert!(is_match_with("file.txt", "[A-Z]*.TXT", &options));
+        assert!(!is_match_with("File.txt", "[^a-z]*.txt", &options));
+        assert!(is_match_with("LIB/A/B/X.JS", "**/a/**/*.js", &options));
+        assert!(is_match_with("FOO.js", "@(foo|bar).js", &options));
+        assert!(is_match_with("ÄBC", "äbc", &options));
+    }
+
     // 6. ドットファイルのマッチングのテスト
     #[test]
     fn test_dot_file_explicit_match() {
@@ -894,29 +2529,60 @@ mod tests {
         assert!(!is_match("filename", "file[^a-z]name"));
     }
 
+    #[test]
+    fn test_bang_negated_character_class() {
+        // シェル形式の [!abc] も否定として扱う
+        assert!(!is_match("a", "[!abc]"));
+        assert!(is_match("d", "[!abc]"));
+        assert!(is_match("testa.log", "test[!0-9].log"));
+        assert!(!is_match("test5.log", "test[!0-9].log"));
+        assert!(is_match("a/b/x", "**/b/**/[!0-9]"));
+        assert!(!is_match("a/b/1", "**/b/**/[!0-9]"));
+        assert!(!is_match("]", "[!]]"));
+
+        // 無効にすると ! は通常の要素になる
+        let options = MatchOptions::new().class_bang(false);
+        assert!(is_match_with("!", "[!abc]", &options));
+        assert!(is_match_with("a", "[!abc]", &options));
+        assert!(!is_match_with("d", "[!abc]", &options));
+        assert!(is_match_with("[!]", "[!]", &options));  // 閉じていない [ として扱われる
+        assert!(is_match_with("]", "[!]]", &options));
+    }
+
     // 8.5 特殊文字のエスケープ
     #[test]
     fn test_escaped_characters_in_class() {
-        // []] パターンのテスト - ] 文字自体にマッチ
-        // Note: この実装では最初の ] はクラスを閉じる文字として扱われる
-        // 実際の実装では適切なエスケープが必要
-        
         // ハイフンのリテラル使用
         assert!(is_match("-", "[-]"));      // ハイフン単体
         assert!(is_match("-", "[a-]"));     // 末尾のハイフン
         assert!(is_match("-", "[-z]"));     // 先頭のハイフン
         assert!(is_match("a", "[a-]"));     // 通常文字も含む
         assert!(!is_match("b", "[a-]"));    // 範囲ではない
+
+        // バックスラッシュでエスケープした文字は要素として扱う
+        assert!(is_match("]", "[\\]\\-]"));
+        assert!(is_match("-", "[\\]\\-]"));
+        assert!(!is_match("a", "[\\]\\-]"));
+        assert!(is_match("-", "[a\\-z]"));
+        assert!(is_match("z", "[a\\-z]"));
+        assert!(!is_match("m", "[a\\-z]"));
+        assert!(is_match("\\", "[\\\\]"));
+        assert!(is_match("^", "[\\^a]"));
+        assert!(is_match("a/b/-", "**/b/**/[a\\-z]"));
+        assert!(!is_match("a/b/m", "**/b/**/[a\\-z]"));
     }
 
     #[test]
     fn test_bracket_characters() {
-        // 角括弧文字そのもののテスト（実装依存）
-        // 実際の実装では [[] や []] のような形でエスケープが必要になる可能性
-        
-        // 単純なケースでテスト
+        // 角括弧文字そのもののテスト
+        // 先頭の ] はクラスを閉じずに要素として扱われる
         assert!(is_match("[", "[[]"));      // 開き括弧
-        // assert!(is_match("]", "[]]"));   // 閉じ括弧は実装が複雑
+        assert!(is_match("]", "[]]"));      // 閉じ括弧
+        assert!(is_match("]", "[]a]"));
+        assert!(is_match("a", "[]a]"));
+        assert!(!is_match("]", "[^]]"));
+        assert!(is_match("x", "[^]]"));
+        assert!(is_match("a/b/]", "**/b/**/[]]"));  // 複数の ** を含むパターン
     }
 
     // 8.6 複合パターン
@@ -959,8 +2625,9 @@ mod tests {
     // 8.7 エラーケース・エッジケース
     #[test]
     fn test_character_class_edge_cases() {
-        // 空の文字クラス（実装によっては無効）
-        // assert!(!is_match("a", "[]"));
+        // 空の文字クラスは閉じていない [ として扱われる
+        assert!(!is_match("a", "[]"));
+        assert!(is_match("[]", "[]"));
         
         // 閉じ括弧がない不完全なクラス（実装によっては通常文字として扱われる）
         // 現在の実装では '[' は通常文字として扱われるはず
@@ -970,8 +2637,862 @@ mod tests {
         assert!(is_match("a", "[a]"));
         assert!(!is_match("b", "[a]"));
         
-        // 範囲が逆転している場合（z-a）は実装依存
-        // 通常は無効な範囲として扱われる
+        // 範囲が逆転している場合（z-a）はどの文字にもマッチしない
+        // try_match では ReversedRange エラーになる
+        assert!(!is_match("a", "[z-a]"));
+        assert!(!is_match("m", "[z-a]"));
+    }
+
+    // 8.8 POSIX文字クラス
+    #[test]
+    fn test_posix_character_classes() {
+        assert!(is_match("a", "[[:alpha:]]"));
+        assert!(is_match("é", "[[:alpha:]]"));
+        assert!(!is_match("1", "[[:alpha:]]"));
+        assert!(is_match("7", "[[:digit:]]"));
+        assert!(is_match("x9", "[[:alpha:]][[:alnum:]]"));
+        assert!(is_match("a b", "a[[:space:]]b"));
+        assert!(is_match("A", "[[:upper:]]"));
+        assert!(!is_match("a", "[[:upper:]]"));
+        assert!(is_match("a", "[[:lower:]]"));
+        assert!(is_match("-", "[[:punct:]]"));
+        assert!(is_match("f", "[[:xdigit:]]"));
+        assert!(!is_match("g", "[[:xdigit:]]"));
+
+        // 他の要素や否定との組み合わせ
+        assert!(is_match("_", "[[:digit:]_]"));
+        assert!(is_match("file1.txt", "file[[:digit:]].txt"));
+        assert!(!is_match("file1.txt", "file[^[:digit:]].txt"));
+        assert!(is_match("filea.txt", "file[^[:digit:]].txt"));
+
+        // 複数の ** を含むパターンでも同じ規則で評価される
+        assert!(is_match("a/b/c1.txt", "**/b/**/c[[:digit:]].txt"));
+        assert!(!is_match("a/b/cx.txt", "**/b/**/c[[:digit:]].txt"));
+
+        // 大文字・小文字を区別しない場合は両方にマッチ
+        assert!(is_match_with("a", "[[:upper:]]", &MatchOptions::new().nocase(true)));
+    }
+
+    // 9. MatchOptions のテスト
+    #[test]
+    fn test_match_options_default_matches_is_match() {
+        let options = MatchOptions::default();
+        assert!(is_match_with("src/main.rs", "src/*.rs", &options));
+        assert!(!is_match_with(".env", "*", &options));
+        assert!(!is_match_with("src//main.js", "src/**/main.js", &options));
+    }
+
+    #[test]
+    fn test_match_options_strict_slashes() {
+        let options = MatchOptions::new().strict_slashes(false);
+        assert!(is_match_with("src//main.js", "src/**/main.js", &options));
+        assert!(is_match_with("a//b", "a//b", &options));
+    }
+
+    // 10. ブレース展開のテスト
+    #[test]
+    fn test_brace_alternation() {
+        assert!(is_match("src/a.ts", "src/*.{js,ts}"));
+        assert!(is_match("src/a.js", "src/*.{js,ts}"));
+        assert!(!is_match("src/a.rs", "src/*.{js,ts}"));
+        assert!(is_match("lib/index.js", "{src,lib}/index.js"));
+        assert!(!is_match("test/index.js", "{src,lib}/index.js"));
+    }
+
+    #[test]
+    fn test_brace_alternation_with_globstars() {
+        assert!(is_match("deep/nested/file.jsx", "**/*.{js,jsx}"));
+        assert!(is_match("a/test/b/c.ts", "**/test/**/*.{js,ts}"));
+        assert!(!is_match("a/test/b/c.rs", "**/test/**/*.{js,ts}"));
+    }
+
+    #[test]
+    fn test_brace_ranges() {
+        assert!(is_match("file2.txt", "file{1..3}.txt"));
+        assert!(!is_match("file4.txt", "file{1..3}.txt"));
+        assert!(is_match("log-b.txt", "log-{a..c}.txt"));
+        assert!(!is_match("log-d.txt", "log-{a..c}.txt"));
+        assert!(is_match("part07.bin", "part{01..10}.bin"));
+        assert!(!is_match("part7.bin", "part{01..10}.bin"));
+        let pattern = Pattern::new("{-9223372036854775808..9223372036854775807..-9223372036854775808}");
+        assert!(pattern.is_match("0"));
+        assert!(!pattern.is_match("9223372036854775807"));
+    }
+
+    #[test]
+    fn test_nested_braces() {
+        assert!(is_match("a", "{a,b{c,d}}"));
+        assert!(is_match("bd", "{a,b{c,d}}"));
+        assert!(!is_match("b", "{a,b{c,d}}"));
+        assert!(is_match("src/test/e2e/app.js", "src/{lib,test/{unit,e2e}}/*.js"));
+        assert!(!is_match("src/test/app.js", "src/{lib,test/{unit,e2e}}/*.js"));
+    }
+
+    #[test]
+    fn test_brace_literal_when_not_expandable() {
+        assert!(is_match("{a}", "{a}"));
+        assert!(is_match("{a,b", "{a,b"));
+    }
+
+    // 11. extglob のテスト
+    #[test]
+    fn test_extglob_exactly_one() {
+        assert!(is_match("foo.js", "@(foo|bar).js"));
+        assert!(is_match("bar.js", "@(foo|bar).js"));
+        assert!(!is_match(".js", "@(foo|bar).js"));
+        assert!(!is_match("foobar.js", "@(foo|bar).js"));
+        assert!(is_match("lib/index.js", "@(src|lib)/*.js"));
+        assert!(!is_match("test/index.js", "@(src|lib)/*.js"));
+    }
+
+    #[test]
+    fn test_extglob_zero_or_one() {
+        assert!(is_match("file.js", "file?(.min).js"));
+        assert!(is_match("file.min.js", "file?(.min).js"));
+        assert!(!is_match("file.min.min.js", "file?(.min).js"));
+        assert!(is_match("ab", "ab?(c|d)"));
+        assert!(is_match("abd", "ab?(c|d)"));
+    }
+
+    #[test]
+    fn test_trailing_extglob_matching_nothing() {
+        // 入力を使い切った後の * と extglob はどちらも空文字列にマッチする
+        assert!(is_match("x", "x*?(a)"));
+        assert!(is_match("a", "a*?(.txt)"));
+        assert!(is_match("a", "a*@(a|)"));
+        assert!(is_match("a", "a*!(b)"));
+        assert!(is_match("a/b", "a*?(a)/b"));
+        assert!(!is_match("a", "a*@(b)"));
+        assert!(!is_match("a", "a*+(b)"));
+        assert!(!is_match("a", "a*?(b)c"));
+        assert_eq!(capture("a*?(.txt)", "a"), Some(vec![String::new(), String::new()]));
+    }
+
+    #[test]
+    fn test_extglob_with_wildcards_inside() {
+        assert!(is_match("test.spec.ts", "*.@(spec|test).@(js|ts)"));
+        assert!(is_match("a1.txt", "@([a-z][0-9]|x).txt"));
+        assert!(!is_match("a/b.txt", "@(a*b).txt"));
+    }
+
+    #[test]
+    fn test_extglob_in_segment_engine() {
+        assert!(is_match("a/src/b/main.js", "**/@(src|lib)/**/*.js"));
+        assert!(is_match("a/lib/b/main.min.js", "**/@(src|lib)/**/*?(.min).js"));
+        assert!(!is_match("a/test/b/main.js", "**/@(src|lib)/**/*.js"));
+    }
+
+    #[test]
+    fn test_extglob_negation() {
+        assert!(is_match("bar.js", "!(foo).js"));
+        assert!(!is_match("foo.js", "!(foo).js"));
+        assert!(is_match("foobar.js", "!(foo).js"));
+        assert!(is_match("a.ts", "!(*.js)"));
+        assert!(!is_match("a.js", "!(*.js)"));
+        assert!(!is_match("b.css", "!(a|b).css"));
+        assert!(is_match("c.css", "!(a|b).css"));
+    }
+
+    #[test]
+    fn test_extglob_negation_excludes_directory() {
+        assert!(is_match("src/app.js", "!(node_modules)/*.js"));
+        assert!(!is_match("node_modules/app.js", "!(node_modules)/*.js"));
+        assert!(is_match("src/lib/deep/app.js", "!(node_modules)/**/*.js"));
+        assert!(!is_match("node_modules/lib/deep/app.js", "!(node_modules)/**/*.js"));
+        assert!(is_match("a/src/b/c.js", "**/!(test)/**/*.js"));
+    }
+
+    #[test]
+    fn test_extglob_repetition() {
+        assert!(is_match("123.txt", "+([0-9]).txt"));
+        assert!(is_match("7.txt", "+([0-9]).txt"));
+        assert!(!is_match(".txt", "+([0-9]).txt"));
+        assert!(!is_match("12a.txt", "+([0-9]).txt"));
+
+        assert!(is_match("v.txt", "v*([0-9]).txt"));
+        assert!(is_match("v42.txt", "v*([0-9]).txt"));
+        assert!(is_match("foobarfoo", "+(foo|bar)"));
+        assert!(!is_match("foobaz", "+(foo|bar)"));
+        assert!(is_match("ab", "a*(b|c)"));
+        assert!(is_match("a", "a*(b|c)"));
+        assert!(is_match("acbcb", "a*(b|c)"));
+
+        // 長さの異なる代替パターンの繰り返し
+        assert!(is_match("a", "+(a|bc)"));
+        assert!(is_match("abca", "+(a|bc)"));
+        assert!(!is_match("abc b", "+(a|bc)"));
+        assert!(!is_match("", "+(a|bc)"));
+        assert!(is_match("abcabca", "+(a|bc)"));
+        assert!(!is_match("abcab", "+(a|bc)"));
+    }
+
+    #[test]
+    fn test_extglob_repetition_in_segment_engine() {
+        assert!(is_match("a/v1/b/x.js", "**/v+([0-9])/**/*.js"));
+        assert!(!is_match("a/v/b/x.js", "**/v+([0-9])/**/*.js"));
+    }
+
+    #[test]
+    fn test_extglob_without_closing_paren_is_literal() {
+        assert!(is_match("@(a", "@(a"));
+        assert!(is_match("xa", "?a"));
+    }
+
+    // 12. 否定パターンのテスト
+    #[test]
+    fn test_negated_pattern() {
+        assert!(is_match("src/app.js", "!**/*.test.js"));
+        assert!(!is_match("src/app.test.js", "!**/*.test.js"));
+        assert!(is_match("main.rs", "!*.js"));
+        assert!(!is_match("main.js", "!*.js"));
+        assert!(is_match("src/a.ts", "!src/*.{js,jsx}"));
+    }
+
+    #[test]
+    fn test_double_negation() {
+        assert!(is_match("main.js", "!!*.js"));
+        assert!(!is_match("main.rs", "!!*.js"));
+    }
+
+    #[test]
+    fn test_negation_is_not_extglob() {
+        // !(...) は否定パターンではなくextglob
+        assert!(is_match("bar.js", "!(foo).js"));
+        assert!(!is_match("foo.js", "!(foo).js"));
+        assert!(!is_match("src/x.js", "!!(foo)/*.js"));
+        assert!(is_match("foo/x.js", "!!(foo)/*.js"));
+    }
+
+    #[test]
+    fn test_escaped_leading_exclamation() {
+        assert!(is_match("!important.txt", "\\!important.txt"));
+        assert!(!is_match("important.txt", "\\!important.txt"));
+        assert!(is_match("!a.txt", "\\!*.txt"));
+    }
+
+    // 13. 複数パターンのテスト
+    #[test]
+    fn test_is_match_any() {
+        assert!(is_match_any("src/main.rs", &["*.js", "src/*.rs"]));
+        assert!(!is_match_any("src/main.rs", &["*.js", "*.ts"]));
+        assert!(!is_match_any::<&str>("src/main.rs", &[]));
+
+        let owned = vec![String::from("**/*.rs")];
+        assert!(is_match_any("src/main.rs", &owned));
+    }
+
+    #[test]
+    fn test_is_match_all() {
+        assert!(is_match_all("src/main.rs", &["src/**", "**/*.rs"]));
+        assert!(!is_match_all("src/main.rs", &["src/**", "!**/main.rs"]));
+        assert!(is_match_all::<&str>("src/main.rs", &[]));
+    }
+
+    // 14. 不正なパターンの検出テスト
+    #[test]
+    fn test_try_match() {
+        assert_eq!(try_match("src/main.rs", "src/*.rs"), Ok(true));
+        assert_eq!(try_match("src/main.rs", "*.js"), Ok(false));
+        assert_eq!(try_match("a", "[a").unwrap_err().kind(), &PatternErrorKind::UnclosedClass);
+        assert_eq!(try_match("a", "+(a").unwrap_err().kind(), &PatternErrorKind::UnclosedExtglob);
+        assert_eq!(try_match("a", "[z-a]").unwrap_err().kind(), &PatternErrorKind::ReversedRange);
+    }
+
+    // 15. dotオプションのテスト
+    #[test]
+    fn test_dot_option() {
+        let options = MatchOptions::new().dot(true);
+        assert!(!is_match(".eslintrc.js", "*.js"));
+        assert!(is_match_with(".eslintrc.js", "*.js", &options));
+        assert!(is_match_with(".github/workflows/ci.yml", "**/*.yml", &options));
+        assert!(is_match_with(".env", "*", &options));
+        assert!(!is_match_with(".env", "*.js", &options));
+    }
+
+    // 16. エスケープのテスト
+    #[test]
+    fn test_escaped_metacharacters() {
+        assert!(is_match("foo*.txt", "foo\\*.txt"));
+        assert!(!is_match("foobar.txt", "foo\\*.txt"));
+        assert!(is_match("what?", "what\\?"));
+        assert!(!is_match("whats", "what\\?"));
+        assert!(is_match("[abc]", "\\[abc]"));
+        assert!(!is_match("a", "\\[abc]"));
+        assert!(is_match("a\\b", "a\\\\b"));
+        assert!(is_match("ab", "\\a\\b"));
+
+        // エスケープされた文字はブレースやextglobの構文にならない
+        assert!(is_match("{a,b}", "\\{a,b}"));
+        assert!(is_match("a,b", "{a\\,b,c}"));
+        assert!(is_match("@(a)", "@\\(a)"));
+        assert!(is_match("a|b", "@(a\\|b)"));
+
+        // ワイルドカードとの組み合わせ
+        assert!(is_match("x/*/y.js", "**/\\*/*.js"));
+        assert!(!is_match("x/z/y.js", "**/\\*/*.js"));
+        assert!(is_match("a/**/b/c", "a/\\*\\*/**/c"));
+        assert!(!is_match("a/x/b/c", "a/\\*\\*/**/c"));
+
+        // 末尾の \\ は通常文字として扱う
+        assert!(is_match("a\\", "a\\"));
+    }
+
+    // 17. strict_globstarオプションのテスト
+    #[test]
+    fn test_strict_globstar_option() {
+        let options = MatchOptions::new().strict_globstar(true);
+
+        // セグメント全体の ** は従来どおりディレクトリをまたぐ
+        assert!(is_match_with("a/x/y/b", "a/**/b", &options));
+        assert!(is_match_with("x/y/z.js", "**/*.js", &options));
+        assert!(is_match_with("a/x/y", "a/**", &options));
+
+        // セグメントの一部の ** は * と同じ
+        assert!(is_match_with("axyb", "a**b", &options));
+        assert!(!is_match_with("a/x/b", "a**b", &options));
+        assert!(!is_match_with("a/x/b.js", "a/**b.js", &options));
+        assert!(is_match_with("a/xb.js", "a/**b.js", &options));
+
+        // 既定では緩やかに扱う
+        assert!(is_match("a/x/b", "a**b"));
+
+        // エスケープや文字クラスは書き換えない
+        assert!(is_match_with("a**b", "a\\*\\*b", &options));
+        assert!(is_match_with("a*/b/c", "a[*]**/**/c", &options));
+    }
+
+    // 18. 部分一致（contains）のテスト
+    #[test]
+    fn test_contains() {
+        assert!(contains("aa/bb/cc", "*b"));
+        assert!(contains("aa/bb/cc", "b*"));
+        assert!(contains("aa/bb/cc", "bb"));
+        assert!(contains("aa/bb/cc", "a/**/c"));
+        assert!(contains("aa/bb/cc", "[abc]/[abc]"));
+        assert!(!contains("aa/bb/cc", "*d"));
+        assert!(!contains("aa/bb/cc", "a*c"));  // * は / をまたがない
+        assert!(contains("aa/bb/cc", "{x,bb}"));
+
+        // 否定パターンはどこにも現れない場合に真
+        assert!(contains("aa/bb/cc", "!*d"));
+        assert!(!contains("aa/bb/cc", "!bb"));
+
+        // 空のパターンは空文字列にマッチする
+        assert!(contains("abc", ""));
+        assert!(!contains("", "a"));
+
+        // 長い入力でも照合の手数は入力の長さに比例する
+        for (pattern, input) in [("**/y", "a/".repeat(4000)), ("*(x|y)z", "x".repeat(4000) + "/")] {
+            let options = MatchOptions::new().max_steps(40_000);
+            assert!(!Pattern::with_options(pattern, &options).contains(&input));
+            assert_eq!(Pattern::with_options(pattern, &options).try_is_match(&input), Ok(false));
+            assert!(Pattern::with_options(pattern, &options).contains(&(input + "yz")));
+        }
+    }
+
+    // 19. matcherのテスト
+    #[test]
+    fn test_matcher() {
+        let is_js = matcher("*.js", &MatchOptions::default());
+        assert!(is_js("a.js"));
+        assert!(!is_js("a.ts"));
+        assert!(!is_js(".a.js"));
+
+        let mut paths = vec!["a.js", "b.ts", "c.js"];
+        paths.retain(|path| is_js(path));
+        assert_eq!(paths, ["a.js", "c.js"]);
+
+        // オプションも保持される
+        let is_js = matcher("*.JS", &MatchOptions::new().nocase(true).dot(true));
+        assert!(is_js(".a.js"));
+    }
+
+    // 20. match_listのテスト
+    #[test]
+    fn test_match_list() {
+        let paths = ["a.js", "b.ts", "c.md", "lib/d.js", "lib/e.ts"];
+        assert_eq!(match_list(&paths, &["*.js"]), ["a.js"]);
+        assert_eq!(match_list(&paths, &["**/*.js", "*.ts"]), ["a.js", "b.ts", "lib/d.js"]);
+
+        // 否定パターンは順番に適用される
+        assert_eq!(match_list(&paths, &["**/*.{js,ts}", "!lib/**"]), ["a.js", "b.ts"]);
+        assert_eq!(match_list(&paths, &["**/*.{js,ts}", "!lib/**", "lib/*.ts"]), ["a.js", "b.ts", "lib/e.ts"]);
+
+        // 否定パターンだけの場合は除外されなかったもの全て
+        assert_eq!(match_list(&paths, &["!**/*.js", "!*.md"]), ["b.ts", "lib/e.ts"]);
+
+        // パターンが空なら何も残らない
+        assert!(match_list::<_, &str>(&paths, &[]).is_empty());
+
+        // String のスライスも受け付ける
+        let owned: Vec<String> = paths.iter().map(|path| path.to_string()).collect();
+        assert_eq!(match_list(&owned, &[String::from("*.md")]), ["c.md"]);
+    }
+
+    // 21. notのテスト
+    #[test]
+    fn test_not() {
+        let paths = ["a.js", "b.ts", "c.md", "lib/d.js", "lib/e.ts"];
+        assert_eq!(not(&paths, &["*.js"]), ["b.ts", "c.md", "lib/d.js", "lib/e.ts"]);
+        assert_eq!(not(&paths, &["**/*.{js,ts}", "!lib/**"]), ["c.md", "lib/d.js", "lib/e.ts"]);
+        assert_eq!(not(&paths, &["!**/*.js", "!*.md"]), ["a.js", "c.md", "lib/d.js"]);
+        assert_eq!(not::<_, &str>(&paths, &[]), paths);
+
+        // match_list と合わせると元のリストになる
+        let patterns = ["**/*.ts", "!b.*"];
+        let mut all = match_list(&paths, &patterns);
+        all.extend(not(&paths, &patterns));
+        all.sort_unstable();
+        assert_eq!(all, paths);
+    }
+
+    // 22. ASCIIの高速経路のテスト
+    #[test]
+    fn test_ascii_fast_path() {
+        // ASCIIの入力とそうでない入力で結果が一致する
+        assert!(is_match("src/a.rs", "src/?.rs"));
+        assert!(is_match("src/é.rs", "src/?.rs"));
+        assert!(is_match("b", "[a-é]"));
+        assert!(is_match("é", "[a-é]"));
+        assert!(!is_match("K", "*k"));
+        assert!(is_match_with("SRC/Main.RS", "src/*.rs", &MatchOptions::new().nocase(true)));
+        assert!(is_match_with("ÉTÉ.RS", "été.rs", &MatchOptions::new().nocase(true)));
+        assert_eq!(capture("*/?.@(js|ts)", "lib/a.ts"), Some(vec!["lib".into(), "a".into(), "ts".into()]));
+        assert_eq!(capture("*/?.@(js|ts)", "lïb/ä.ts"), Some(vec!["lïb".into(), "ä".into(), "ts".into()]));
+    }
+
+    // 23. globstarの数によらず同じ規則でマッチするテスト
+    #[test]
+    fn test_consistent_with_multiple_globstars() {
+        // * は空文字列にもマッチする
+        assert!(is_match("src/ab", "src/**/a*b"));
+        assert!(is_match("x/src/y/ab", "**/src/**/a*b"));
+        // ? は任意の1文字にマッチする
+        assert!(is_match("src/a1", "src/**/a?"));
+        assert!(is_match("x/src/a1", "**/src/**/a?"));
+        assert!(!is_match("x/src/a?/b", "**/src/**/a?"));
+        // **/ はディレクトリの途中から始まらない
+        assert!(!is_match("a/xb/c", "a/**/b/**/c"));
+        assert!(is_match("a/x/b/c", "a/**/b/**/c"));
+        assert_eq!(capture("**/src/**/*.rs", "x/src/y/z.rs"), Some(vec!["x".into(), "y".into(), "z".into()]));
+    }
+
+    // 24. 制限のテスト
+    #[test]
+    fn test_pattern_limits() {
+        let options = MatchOptions::new().max_pattern_len(7);
+        assert!(is_match_with("abc.js", "*.js", &options));
+        // 制限を超えるパターンは何にもマッチしない
+        assert!(!is_match_with("src/main.js", "src/*.js", &options));
+        let error = Pattern::try_with_options("src/*.js", &options).unwrap_err();
+        assert_eq!(error.kind(), &PatternErrorKind::TooLong);
+
+        let options = MatchOptions::new().max_segments(3);
+        assert!(is_match_with("a/b.js", "a/*.js", &options));
+        assert!(!is_match_with("a/b/c.js", "a/*/*.js", &options));
+        // extglob の中のセグメントも数える
+        assert!(!is_match_with("ab", "@(a|b)@(a|b)", &options));
+        let error = Pattern::try_with_options("a/*/*.js", &options).unwrap_err();
+        assert_eq!(error.kind(), &PatternErrorKind::TooManySegments);
+    }
+
+    #[test]
+    fn test_deeply_nested_extglobs() {
+        let nested = |depth| "*(".repeat(depth) + "a" + &")".repeat(depth);
+        assert!(is_match("aa", &nested(32)));
+        // 深すぎる入れ子はスタックを使い切らずに拒否する
+        assert!(!is_match("aa", &nested(10_000)));
+        let error = Pattern::try_new(&nested(33)).unwrap_err();
+        assert_eq!(error.kind(), &PatternErrorKind::TooDeeplyNested);
+    }
+
+    #[test]
+    fn test_brace_limits_are_errors() {
+        let pattern = "{a,b,c,d,e,f,g,h,i,j}".repeat(5);
+        // 上限を超えた展開は寛容なマッチではリテラル、厳密な API では誤り
+        assert!(is_match(&pattern, &pattern));
+        let error = try_match("aaaaa", &pattern).unwrap_err();
+        assert_eq!(error.kind(), &PatternErrorKind::TooManyExpansions);
+        assert_eq!(error.span(), 0..pattern.len());
+        assert_eq!(try_match("aaaa", &"{a,b,c,d,e,f,g,h,i,j}".repeat(4)), Ok(true));
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let input = "a".repeat(1_000);
+        let options = MatchOptions::new().max_steps(100_000);
+        assert!(is_match_with("src/app/main.js", "src/**/*.js", &options));
+        assert!(is_match_with(&input, "+(a|aa)", &options));
+        // 上限に達したら false を返す
+        let options = MatchOptions::new().max_steps(100);
+        assert!(!is_match_with(&input, "+(a|aa)", &options));
+        assert!(!is_match_with(&input, "+(a|aa)b", &options));
+
+        // try_is_match は上限に達したことをエラーで知らせる
+        let pattern = Pattern::with_options("+(a|aa)", &options);
+        assert_eq!(pattern.try_is_match(&input), Err(StepLimitExceeded::new(100)));
+        assert_eq!(pattern.try_is_match("aaa"), Ok(true));
+        assert_eq!(pattern.try_is_match("b"), Ok(false));
+        let negated = Pattern::with_options("!+(a|aa)", &options);
+        assert!(negated.is_match(&input));
+        assert!(negated.try_is_match(&input).is_err());
+        assert_eq!(Pattern::new("+(a|aa)").try_is_match(&input), Ok(true));
+    }
+
+    // 25. 続くリテラルの位置まで飛ばすテスト
+    #[test]
+    fn test_skip_to_next_literal() {
+        assert_eq!(u8::find(b"ab/cb", 'b', false), Some(1));
+        assert_eq!(u8::find(b"xa/cb", 'b', true), Some(2));
+        assert_eq!(u8::find(b"ab", 'é', false), None);
+        assert_eq!(char::find(&['x', 'é', '/'], 'é', true), Some(1));
+
+        // 飛ばした範囲に / があれば * はそこで止まる
+        assert!(!is_match("/ab", "*b"));
+        assert!(!is_match("a/xb", "a*b"));
+        assert!(is_match("axxxxb", "a*b"));
+        assert!(is_match("aébé", "a*é"));
+        assert!(is_match("a/b/c/d.js", "a/**/d.js"));
+        assert!(!is_match("a/b/c/d.jsx", "a/**/*.js"));
+        assert!(is_match_with("A.JS", "*.js", &MatchOptions::new().nocase(true)));
+        assert_eq!(capture("*b*c", "aabbcc"), Some(vec!["aa".into(), "bc".into()]));
+    }
+
+    // 26. エスケープのテスト
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("src/main.rs"), "src/main.rs");
+        assert_eq!(escape("!a(b)|c,d"), "\\!a\\(b\\)\\|c\\,d");
+        assert_eq!(escape("a\\b"), "a\\\\b");
+
+        for text in ["weird[file]*.txt", "!important", "a{1..3}", "@(x)", "+(y)", "a\\*", "?[!]", "x,y|z", "a/**/b"] {
+            let pattern = escape(text);
+            assert!(is_match(text, &pattern), "{text}");
+            assert!(is_static(&pattern), "{text}");
+            assert!(is_match(text, &format!("{{{pattern},other}}")), "{text}");
+            assert!(is_match(text, &format!("@({pattern}|other)")), "{text}");
+            assert!(!is_match("other", &pattern), "{text}");
+        }
+    }
+
+    // 27. 共通の前置部分のテスト
+    #[test]
+    fn test_common_prefix() {
+        let empty: [&str; 0] = [];
+        assert_eq!(common_prefix(&empty), "");
+        assert_eq!(common_prefix(&["src/lib/**/*.rs"]), "src/lib/");
+        assert_eq!(common_prefix(&["src/**/*.ts", "src/lib/*.js"]), "src/");
+        assert_eq!(common_prefix(&["src/lib/a/*.rs", "src/lib/b/*.rs", "src/lib/*.rs"]), "src/lib/");
+        assert_eq!(common_prefix(&["src/app/*.ts", "src/api/*.ts"]), "src/");
+        assert_eq!(common_prefix(&["src/a\\*b/*.ts", "src/a\\*b/c/*.js"]), "src/a*b/");
+        assert_eq!(common_prefix(&["/usr/include/*.h", "/usr/lib/*.so"]), "/usr/");
+        assert_eq!(common_prefix(&["src/*.ts", "*.md"]), "");
+        assert_eq!(common_prefix(&["src/*.ts", "!src/a.ts"]), "");
+        assert_eq!(common_prefix(&["src/ü/*.ts", "src/ü/x/*.ts"]), "src/ü/");
+    }
+
+    // 28. * が空文字列にマッチするかのテスト
+    #[test]
+    fn test_star_matches_empty() {
+        // ** の数によらず、他の文字に接する * は空文字列にマッチする
+        for pattern in ["src/**/a*b", "src/**/**/a*b", "**/src/**/a*b", "src/*/a*b"] {
+            assert!(is_match("src/x/ab", pattern), "{}", pattern);
+        }
+        assert!(is_match("src/", "src/*"));
+        assert!(is_match("a/x/", "a/*/*"));
+
+        let options = MatchOptions::new().star_matches_empty(false);
+        assert!(!is_match_with("src/", "src/*", &options));
+        assert!(is_match_with("src/a", "src/*", &options));
+        assert!(!is_match_with("", "*", &options));
+        assert!(!is_match_with("a/x/", "a/*/*", &options));
+        assert!(!is_match_with("src//b", "src/*/b", &options.clone().strict_slashes(false)));
+        for pattern in ["src/**/a*b", "src/**/**/a*b", "**/src/**/a*b", "src/*/a*b"] {
+            assert!(is_match_with("src/x/ab", pattern, &options), "{}", pattern);
+        }
+        // extglob の代替パターンの両端はパスの区切りではない
+        assert!(is_match_with("a/b", "a/@(*)b", &options));
+        assert!(is_match_with("a/b", "a/b@(*)", &options));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_match_list_par() {
+        let paths: Vec<String> = (0..2000)
+            .map(|i| format!("dir{}/file{}.{}", i % 7, i, if i % 3 == 0 { "js" } else { "ts" }))
+            .collect();
+        let patterns = ["**/*.js", "!dir0/**"];
+        assert_eq!(match_list_par(&paths, &patterns), match_list(&paths, &patterns));
+    }
+
+    // 29. 先頭の ./ のテスト
+    #[test]
+    fn test_strip_dot_slash() {
+        assert!(is_match("./src/lib/mod.rs", "src/**/*.rs"));
+        // ./ を除いても、prefix/**/*.ext は中間ディレクトリを要求する
+        assert!(!is_match("./src/main.rs", "src/**/*.rs"));
+        assert!(is_match("./src/main.rs", "src/*.rs"));
+        assert!(is_match("./main.rs", "*.rs"));
+        assert!(is_match("main.rs", "./*.rs"));
+        assert!(is_match("././a/b", "./a/*"));
+        assert!(is_match("b", "{./a,./b}"));
+        assert!(!is_match("./.env", "*"));
+        assert!(!is_match(".\\src\\main.rs", "src/*.rs"));
+        assert!(is_match_with(".\\src\\main.rs", "src/*.rs", &MatchOptions::new().windows(true)));
+        assert_eq!(capture("./src/*.rs", "./src/main.rs"), Some(vec!["main".to_string()]));
+
+        let options = MatchOptions::new().strip_dot_slash(false);
+        assert!(!is_match_with("./main.rs", "*.rs", &options));
+        assert!(!is_match_with("main.rs", "./*.rs", &options));
+        assert!(is_match_with("./main.rs", "./*.rs", &options));
+    }
+
+    // 30. 区切り文字の変更のテスト
+    #[test]
+    fn test_separator() {
+        let options = MatchOptions::new().separator('.');
+        assert!(is_match_with("a.b.c", "a.*.c", &options));
+        assert!(!is_match_with("a.b.x.c", "a.*.c", &options));
+        assert!(is_match_with("a.b.x.c", "a.**.c", &options));
+        assert!(is_match_with("a.c", "a.**.c", &options));
+        assert!(is_match_with("a.b", "a.?", &options));
+        assert!(!is_match_with("a..b", "a.*.b", &options));
+        // / は普通の文字になる
+        assert!(is_match_with("a/b.c", "*.c", &options));
+        assert!(is_match_with("a/b.c", "a[/]b.c", &options));
+        assert!(!is_match_with("a.b", "a/b", &options));
+        assert!(is_match_with("x.y", "{x,z}.@(y|w)", &options));
+        assert!(!is_match_with("./a", "a", &options));
+
+        let options = MatchOptions::new().separator(':');
+        assert!(is_match_with("user:42:name", "user:*:name", &options));
+        assert!(is_match_with("user:42:name", "user:**", &options));
+        assert!(!is_match_with("user:42:name", "user:*", &options));
+        assert!(is_match_with("a/b:c", "*:c", &options));
+
+        let pattern = Pattern::with_options("app:**:*/*", &options);
+        assert_eq!(pattern.captures("app:x:y:a/b"), Some(vec!["x:y".to_string(), "a".to_string(), "b".to_string()]));
+        assert_eq!(pattern.literal(), None);
+        assert_eq!(Pattern::with_options("a:b/c", &options).literal(), Some("a:b/c".to_string()));
+        assert_eq!(pattern.explain("app:x:a/b").input, "app:x:a/b");
+        assert!(!pattern.is_superset_of(&Pattern::new("app:**:*/*")));
+        assert!(Pattern::with_options("app:**", &options).is_superset_of(&pattern));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be used as a separator")]
+    fn test_separator_rejects_glob_syntax() {
+        let _ = MatchOptions::new().separator('*');
+    }
+
+    // 31. \ を / に変換するオプションのテスト
+    #[test]
+    fn test_posix_slashes() {
+        let options = MatchOptions::new().posix_slashes(true);
+        assert!(is_match_with("src\\main.rs", "src/*.rs", &options));
+        assert!(is_match_with("src\\lib/mod.rs", "src/**/*.rs", &options));
+        assert!(!is_match_with("src\\main.rs", "*.rs", &options));
+        assert!(is_match_with(".\\src\\main.rs", "src/*.rs", &options));
+        // ドライブや UNC のプレフィックスは特別扱いしない
+        assert!(!is_match_with("\\\\server\\share\\a.txt", "//server/share/*.txt", &options));
+        assert!(is_match_with("\\\\?\\C:\\a.txt", "//?/C:/*.txt", &options.clone().strict_slashes(false)));
+
+        let pattern = Pattern::with_options("src/**/*.rs", &options);
+        assert_eq!(pattern.captures("src\\a\\b.rs"), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(Pattern::with_options("src/main.rs", &options).literal(), None);
+        assert!(!pattern.is_superset_of(&Pattern::new("src/**/*.rs")));
+        assert!(pattern.is_superset_of(&Pattern::with_options("src/lib/*.rs", &options)));
+    }
+
+    // 32. ** を無効にするオプションのテスト
+    #[test]
+    fn test_noglobstar() {
+        let options = MatchOptions::new().noglobstar(true);
+        assert!(is_match_with("a.js", "**", &options));
+        assert!(!is_match_with("a/b.js", "**", &options));
+        assert!(is_match_with("a/b.js", "**/*.js", &options));
+        assert!(!is_match_with("a/b/c.js", "**/*.js", &options));
+        assert!(is_match_with("src/x/y", "src/**/y", &options));
+        assert!(!is_match_with("src/y", "src/**/y", &options));
+        assert!(is_match_with("axb", "a**b", &options));
+        assert!(!is_match_with("a/b", "a**b", &options));
+        // エスケープした * はリテラルのまま
+        assert!(is_match_with("a**b", "a\\*\\*b", &options));
+        assert!(!is_match_with("axb", "a\\*\\*b", &options));
+    }
+
+    // 33. ブレース・extglob・否定を無効にするオプションのテスト
+    #[test]
+    fn test_disabled_syntax() {
+        let options = MatchOptions::new().nobrace(true);
+        assert!(is_match_with("{a,b}.js", "{a,b}.js", &options));
+        assert!(!is_match_with("a.js", "{a,b}.js", &options));
+        assert!(is_match_with("x{1..3}", "*{1..3}", &options));
+        assert!(is_match_with("{", "[{]", &options));
+        assert!(is_match_with("a.js", "@(a|b).js", &options));
+        assert_eq!(Pattern::with_options("src/{a,b}/*.rs", &options).literal_prefix(), "src/{a,b}/");
+
+        let options = MatchOptions::new().noext(true);
+        assert!(is_match_with("@(a|b).js", "@(a|b).js", &options));
+        assert!(!is_match_with("a.js", "@(a|b).js", &options));
+        assert!(is_match_with("x(a)", "?(a)", &options));
+        assert!(is_match_with("ab.js", "*(a)b.js", &MatchOptions::new()));
+        assert!(!is_match_with("ab.js", "*(a)b.js", &options));
+        assert!(is_match_with("a.js", "{a,b}.js", &options));
+        // !( は否定の演算子として読まれ、残りはリテラルになる
+        assert!(is_match_with("a", "!(a)", &options));
+        assert!(!is_match_with("(a)", "!(a)", &options));
+
+        let options = MatchOptions::new().nonegate(true);
+        assert!(is_match_with("!a.js", "!*.js", &options));
+        assert!(!is_match_with("b.md", "!*.js", &options));
+        assert!(is_match_with("!!a", "!!a", &options));
+        assert!(!Pattern::with_options("!*.js", &options).is_negated());
+        assert!(is_match_with("b", "!(a)", &options));
+        assert!(!is_match_with("a", "!(a)", &options));
+        assert_eq!(Pattern::with_options("!src/*.js", &options).literal_prefix(), "!src/");
+
+        let options = MatchOptions::new().nobrace(true).noext(true).nonegate(true);
+        assert!(is_match_with("!(a)", "!(a)", &options));
+        assert!(Pattern::try_with_options("a{b", &options).unwrap().is_match("a{b"));
+    }
+
+    // 34. 正規表現風の省略クラスのテスト
+    #[test]
+    fn test_extended_classes() {
+        let options = MatchOptions::new().extended_classes(true);
+        assert!(is_match_with("img-042.png", "img-\\d\\d\\d.png", &options));
+        assert!(!is_match_with("img-04a.png", "img-\\d\\d\\d.png", &options));
+        assert!(is_match_with("my_file", "my\\wfile", &options));
+        assert!(!is_match_with("my-file", "my\\wfile", &options));
+        assert!(is_match_with("a b", "a\\sb", &options));
+        assert!(is_match_with("a-b", "a\\Sb", &options));
+        assert!(!is_match_with("a1b", "a\\Db", &options));
+        assert!(is_match_with("v1.2.3", "v[\\d.]*", &options));
+        assert!(is_match_with("x_1", "[\\w][\\w][\\w]", &options));
+        assert!(!is_match_with("1", "[^\\d]", &options));
+        // 区切り文字にはどの省略形も一致しない
+        assert!(!is_match_with("a/b", "a\\Wb", &options));
+        assert!(is_match_with("a/b", "a\\Wb", &options.clone().separator('.')));
+        assert!(!is_match_with("a.b", "a\\Wb", &options.clone().separator('.')));
+        // 省略形以外のエスケープはそのまま
+        assert!(is_match_with("a*", "a\\*", &options));
+        assert!(is_match_with("a\\d", "a\\\\d", &options));
+        assert!(is_match_with("a\\7", "a\\\\\\d", &options));
+        assert_eq!(Pattern::with_options("\\d\\d", &options).literal_prefix(), "");
+
+        // 無効のときは従来どおりエスケープされた文字
+        assert!(is_match("d", "\\d"));
+        assert!(!is_match("1", "\\d"));
+        assert!(is_match_with("d", "[\\d]", &MatchOptions::new()));
+    }
+
+    // 35. 書記素クラスタ単位の ? のテスト
+    #[test]
+    fn test_graphemes() {
+        let options = MatchOptions::new().graphemes(true);
+        // 分解された é、肌の色つきの絵文字、家族の絵文字（ZWJ 連結）、国旗
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        for name in ["e\u{301}", "\u{1f44d}\u{1f3fd}", family, "\u{1f1ef}\u{1f1f5}"] {
+            assert!(is_match_with(&format!("{name}.txt"), "?.txt", &options), "{name}");
+            assert!(!is_match_with(&format!("{name}.txt"), "?.txt", &MatchOptions::new()), "{name}");
+        }
+        assert!(is_match_with("cafe\u{301}", "caf?", &options));
+        assert!(!is_match_with("cafe\u{301}", "caf??", &options));
+        assert!(is_match_with("a\u{301}b\u{301}/c", "??/?", &options));
+        assert!(is_match_with("a\u{301}b\u{301}/c", "*/?", &options));
+        // 改行 CRLF も1つのクラスタ
+        assert!(is_match_with("a\r\nb", "a?b", &options));
+        // ? の後ろに残った結合文字は文字クラスや * が受け持つ
+        assert!(is_match_with("e\u{301}", "e?", &MatchOptions::new()));
+        assert!(is_match_with("e\u{301}", "[e]*", &options));
+        assert!(!is_match_with("e\u{301}", "[e]", &options));
+        // 区切り文字は越えない
+        assert!(!is_match_with("a/b", "a?b", &options));
+        assert!(is_match_with("\u{600}/x", "?/x", &options));
+
+        let pattern = Pattern::with_options("caf?.txt", &options);
+        assert_eq!(pattern.captures("cafe\u{301}.txt"), Some(vec!["e\u{301}".to_string()]));
+        assert!(pattern.is_match("caf\u{e9}.txt"));
+    }
+
+    // 36. Unicode 正規化のテスト
+    #[test]
+    fn test_normalize_unicode() {
+        let options = MatchOptions::new().normalize_unicode(true);
+        let (nfc, nfd) = ("r\u{e9}sum\u{e9}", "re\u{301}sume\u{301}");
+        assert!(is_match_with(&format!("docs/{nfd}.pdf"), &format!("docs/{nfc}.*"), &options));
+        assert!(is_match_with(&format!("docs/{nfc}.pdf"), &format!("docs/{nfd}.*"), &options));
+        assert!(!is_match_with(&format!("docs/{nfd}.pdf"), &format!("docs/{nfc}.*"), &MatchOptions::new()));
+        // 合成された文字は ? や文字クラスの1文字になる
+        assert!(is_match_with(nfd, "r?sum?", &options));
+        assert!(is_match_with(nfd, "r[\u{e9}]sum[\u{e8}-\u{ea}]", &options));
+        assert!(is_match_with(nfd, &format!("{{{nfc},cv}}"), &options));
+        assert!(is_match_with(&format!("a/{nfd}"), &format!("!a/{nfd}x"), &options));
+
+        let pattern = Pattern::with_options(&format!("{nfd}/*"), &options);
+        assert_eq!(pattern.literal_prefix(), "");
+        assert_eq!(Pattern::with_options(nfd, &options).literal(), None);
+        assert_eq!(pattern.captures(&format!("{nfc}/caf\u{65}\u{301}")), Some(vec!["caf\u{e9}".to_string()]));
+        let set = GlobSetBuilder::new().add(Pattern::with_options(&format!("**/{nfc}*"), &options)).build();
+        assert!(set.is_match(&format!("a/{nfd}.pdf")));
+    }
+
+    // 37. filter_map_keys のテスト
+    #[test]
+    fn test_filter_map_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let lock = HashMap::from([
+            ("node_modules/a/index.js".to_string(), 1),
+            ("node_modules/a/README.md".to_string(), 2),
+            ("node_modules/b/index.js".to_string(), 3),
+            ("src/index.js".to_string(), 4),
+        ]);
+        let scripts = filter_map_keys(lock.clone(), &["**/*.js", "!src/**"]);
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(scripts["node_modules/a/index.js"], 1);
+        assert_eq!(scripts["node_modules/b/index.js"], 3);
+
+        // 否定パターンだけなら除外されなかったキーが残る
+        let docs = filter_map_keys(lock.clone(), &["!**/*.js"]);
+        assert_eq!(docs.into_keys().collect::<Vec<_>>(), ["node_modules/a/README.md"]);
+
+        // パターンが空なら何も残らない
+        assert!(filter_map_keys::<_, _, _, &str>(lock, &[]).is_empty());
+
+        // BTreeMap は順序を保ち、参照のキーも受け付ける
+        let sizes = BTreeMap::from([("b.rs", 2), ("a.rs", 1), ("c.md", 3)]);
+        let sources = filter_map_keys(sizes, &["*.rs"]);
+        assert_eq!(sources.into_iter().collect::<Vec<_>>(), [("a.rs", 1), ("b.rs", 2)]);
+    }
+
+    // 38. some / every のテスト
+    #[test]
+    fn test_some_every() {
+        let paths = ["a.js", "b.ts", "lib/c.js"];
+        assert!(some(&paths, &["*.ts"]));
+        assert!(some(&paths, &["*.md", "lib/*"]));
+        assert!(!some(&paths, &["*.md"]));
+        assert!(every(&paths, &["**/*.{js,ts}"]));
+        assert!(every(&paths, &["*.ts", "**/*.js"]));
+        assert!(!every(&paths, &["**/*.js"]));
+
+        // 否定パターンは match_list と同じく順番に適用される
+        assert!(!some(&paths, &["**/*.js", "!**/*.js"]));
+        assert!(every(&paths, &["!*.md"]));
+        assert!(!every(&paths, &["!lib/**"]));
+
+        // 空のリストとパターン
+        assert!(!some::<&str, _>(&[], &["*"]));
+        assert!(every::<&str, _>(&[], &["*"]));
+        assert!(!some::<_, &str>(&paths, &[]));
+        assert!(!every::<_, &str>(&paths, &[]));
+
+        // String のスライスも受け付ける
+        let owned = vec![String::from("a.rs"), String::from("b.md")];
+        assert!(some(&owned, &[String::from("*.rs")]));
+        assert!(!every(&owned, &[String::from("*.rs")]));
     }
 
     // パフォーマンステスト
@@ -1012,4 +3533,52 @@ mod tests {
         println!("Performance test completed in {:?}", duration);
         println!("Average per match: {:?}", duration / 4000);
     }
+
+    #[test]
+    fn test_performance_pathological_stars() {
+        use std::time::Instant;
+
+        // 単純なバックトラックでは入力長に対して指数時間かかるパターン
+        let input = "a".repeat(10_000);
+        let start = Instant::now();
+        assert!(!is_match(&input, "*a*a*a*a*a*a*a*a*a*a*b"));
+        assert!(!is_match(&input, "**a**a**a**a**a**b"));
+        assert!(!is_match(&format!("{input}/{input}"), "**/a*a*a*a*/**/*b"));
+        assert!(is_match(&input, "a*a*a*a*a*a*"));
+        let duration = start.elapsed();
+
+        assert!(duration.as_secs() < 1, "Performance test took too long: {:?}", duration);
+    }
+
+    #[test]
+    fn test_performance_unclosed_syntax() {
+        use std::time::Instant;
+
+        // 開き記号ごとに閉じ記号を探し直すと、パターン長の2乗や3乗の時間がかかるパターン
+        let start = Instant::now();
+        for unit in ["[", "[:", "!", "{", "@(", "{[", "@([", "{a,b}"] {
+            let pattern = unit.repeat(60_000 / unit.len());
+            assert!(!Pattern::new(&pattern).is_match("a"), "{unit}");
+        }
+        let duration = start.elapsed();
+
+        assert!(duration.as_secs() < 1, "Performance test took too long: {:?}", duration);
+    }
+
+    #[test]
+    fn test_performance_extglob_repetition() {
+        use std::time::Instant;
+
+        // 繰り返しの分け方を1つずつ試すと、入力長の3乗の時間がかかるパターン
+        let input = "a".repeat(10_000) + "b";
+        let start = Instant::now();
+        assert!(!is_match(&input, "+(a)"));
+        assert!(!is_match(&input, "+(a|aa)"));
+        assert!(!is_match(&input, "*(a|aa)"));
+        assert!(!is_match(&input, "x+(a|b)y"));
+        assert!(is_match(&input, "+(a|aa)b"));
+        let duration = start.elapsed();
+
+        assert!(duration.as_secs() < 1, "Performance test took too long: {:?}", duration);
+    }
 }
@@ -0,0 +1,117 @@
+//! Ordered pattern lists, such as the contents of a pattern file.
+
+use std::path::Path;
+
+use crate::{list_keeps_with, MatchOptions, Pattern};
+
+/// An ordered list of patterns applied with [`match_list`](crate::match_list)'s
+/// rules: a path matches once a positive pattern matches it, a later `!`
+/// pattern drops it again, and a later positive pattern brings it back.
+/// A list of only `!` patterns matches every path none of them exclude.
+///
+/// ```
+/// use satch::Matcher;
+///
+/// let matcher = Matcher::from_lines("
+///     # sources
+///     src/**/*.rs
+///     !src/generated/**
+/// ");
+/// assert!(matcher.is_match("src/lib/mod.rs"));
+/// assert!(!matcher.is_match("src/generated/api.rs"));
+/// assert!(!matcher.is_match("README.md"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    patterns: Vec<Pattern>,
+    all_negated: bool,
+}
+
+impl Matcher {
+    /// Compiles `patterns` in order with the default options.
+    pub fn new<P: AsRef<str>>(patterns: &[P]) -> Self {
+        Self::with_options(patterns, &MatchOptions::default())
+    }
+
+    /// Compiles `patterns` in order with the given options.
+    pub fn with_options<P: AsRef<str>>(patterns: &[P], options: &MatchOptions) -> Self {
+        Self::from_patterns(
+            patterns
+                .iter()
+                .map(|pattern| Pattern::with_options(pattern.as_ref(), options))
+                .collect(),
+        )
+    }
+
+    /// Reads one pattern per line of `contents`, skipping blank lines and
+    /// lines starting with `#`. Surrounding whitespace is trimmed; write
+    /// `\#` for a pattern starting with a literal `#`.
+    pub fn from_lines(contents: &str) -> Self {
+        Self::from_lines_with_options(contents, &MatchOptions::default())
+    }
+
+    /// Like [`Matcher::from_lines`], with the given options.
+    pub fn from_lines_with_options(contents: &str, options: &MatchOptions) -> Self {
+        let lines: Vec<&str> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        Self::with_options(&lines, options)
+    }
+
+    fn from_patterns(patterns: Vec<Pattern>) -> Self {
+        let all_negated = !patterns.is_empty() && patterns.iter().all(Pattern::is_negated);
+        Self { patterns, all_negated }
+    }
+
+    /// Returns the patterns in order.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// Returns `true` if `input` survives the patterns.
+    pub fn is_match(&self, input: &str) -> bool {
+        list_keeps_with(&self.patterns, self.all_negated, |pattern| pattern.is_match(input))
+    }
+
+    /// Like [`Matcher::is_match`], for a filesystem path normalized as in
+    /// [`Pattern::matches_path`].
+    pub fn matches_path(&self, path: &Path) -> bool {
+        list_keeps_with(&self.patterns, self.all_negated, |pattern| pattern.matches_path(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_lines_skips_comments_and_blanks() {
+        let matcher = Matcher::from_lines("# comment\n\n  *.rs  \n\\#notes\n");
+        assert_eq!(matcher.patterns().len(), 2);
+        assert!(matcher.is_match("main.rs"));
+        assert!(matcher.is_match("#notes"));
+        assert!(!matcher.is_match("main.js"));
+    }
+
+    #[test]
+    fn test_order_and_negation() {
+        let matcher = Matcher::from_lines("**/*.js\n!vendor/**\nvendor/keep.js\n");
+        assert!(matcher.is_match("src/a.js"));
+        assert!(!matcher.is_match("vendor/a.js"));
+        assert!(matcher.is_match("vendor/keep.js"));
+        // 否定パターンだけのリストは除外されないものすべてにマッチする
+        let matcher = Matcher::from_lines("!*.md");
+        assert!(matcher.is_match("a.js"));
+        assert!(!matcher.is_match("a.md"));
+        assert!(!Matcher::from_lines("# nothing\n").is_match("a.js"));
+    }
+
+    #[test]
+    fn test_options_and_paths() {
+        let matcher = Matcher::from_lines_with_options("*.MD", &MatchOptions::new().nocase(true));
+        assert!(matcher.is_match("readme.md"));
+        assert!(Matcher::new(&["src/*.rs"]).matches_path(Path::new("./src/lib.rs")));
+    }
+}
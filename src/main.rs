@@ -13,9 +13,23 @@
 //!
 //! # List matching files
 //! satch --list --recursive --basename "*.js"     # Find all .js files
+//! satch --list --recursive "**/node_modules/"    # Find directories (trailing /)
+//!
+//! # Machine-readable results
+//! satch --list -r --output json "**/*.rs"         # [{"path": ..., "matched": true, ...}]
 //!
 //! # Test specific paths
 //! satch "*.rs" src/main.rs lib.rs test.js        # Test multiple files
+//!
+//! # Rename matching files (preview first, then --apply)
+//! satch rename "src/**/*.js" "{dir}/{name}.mjs"
+//!
+//! # Copy or move matching files into directories named by captures
+//! satch cp --dry-run "assets/**/*.png" "public/img/{1}"
+//!
+//! # Print an equivalent regex or ignore-file rules
+//! satch convert --to regex "**/*.{js,ts}"
+//! satch convert --to gitignore "{dist,out}/"
 //! ```
 //!
 //! ## Features
@@ -25,19 +39,24 @@
 //! - **Recursive search**: Search through directory trees
 //! - **Basename matching**: Match against filename only, ignoring path
 //! - **Multiple modes**: stdin input, file listing, or direct path testing
+//! - **Bulk renaming**: Compute new names from captures with `satch rename`
+//! - **Bulk copies and moves**: Lay out matching files with `satch cp` and `satch mv`
+//! - **Conversion**: Port patterns to regex or `.gitignore` rules with `satch convert`
 //!
 //! ## Pattern Support
 //!
 //! - Basic wildcards: `*`, `?`
 //! - Globstars: `**` for recursive directory matching
-//! - Character classes: `[abc]`, `[a-z]`, `[^abc]`
+//! - Character classes: `[abc]`, `[a-z]`, `[^abc]`, `[!abc]`, `[[:alpha:]]`
 //! - Complex patterns: `**/test/**/*.js`
 
-use clap::{Arg, Command};
-use satch::is_match;
+use clap::{Arg, ArgMatches, Command};
+use satch::{expand_template, gitignore, to_regex, MatchOptions, Pattern, WalkBuilder};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::process;
 
 /// Main entry point for the satch CLI tool.
 ///
@@ -45,11 +64,32 @@ use std::path::Path;
 /// - List mode: Find and list files matching the pattern
 /// - Path testing: Test specific paths against the pattern  
 /// - Stdin mode: Read paths from stdin and test each one
+/// - `rename`, `cp`, `mv`: Rename, copy or move matching files after a template
+/// - `convert`: Print the pattern as a regex or as ignore-file rules
 fn main() {
     let matches = Command::new("satch")
         .version("0.1.0")
         .author("ushironoko")
         .about("glob pattern matching CLI tool")
+        // サブコマンドの名前はパターンより優先する
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(transfer_command("rename", Transfer::Rename))
+        .subcommand(transfer_command("cp", Transfer::Copy))
+        .subcommand(transfer_command("mv", Transfer::Move))
+        .subcommand(
+            Command::new("convert")
+                .about("Print a regex or .gitignore rules matching the same paths as a pattern")
+                .arg(Arg::new("pattern").help("Glob pattern to convert").required(true))
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("FORMAT")
+                        .help("Output format")
+                        .value_parser(["regex", "gitignore"])
+                        .default_value("regex"),
+                ),
+        )
         .arg(
             Arg::new("pattern")
                 .help("Glob pattern to match against")
@@ -77,6 +117,27 @@ fn main() {
                 .help("Search recursively in directories")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .value_name("N")
+                .help("Descend at most N directory levels when listing recursively")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("min-depth")
+                .long("min-depth")
+                .value_name("N")
+                .help("List only files at least N directory levels deep (1 is the current directory)")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("follow")
+                .short('L')
+                .long("follow")
+                .help("Follow symbolic links when listing (loops are detected and skipped)")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("verbose")
                 .short('v')
@@ -84,6 +145,12 @@ fn main() {
                 .help("Show verbose output")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("posix-slashes")
+                .long("posix-slashes")
+                .help("Treat \\ in paths as / (always on for Windows)")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("basename")
                 .short('b')
@@ -91,23 +158,132 @@ fn main() {
                 .help("Match against basename only (ignore directory path)")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("Print results as text lines or as one JSON array")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
         .get_matches();
 
-    let pattern = matches.get_one::<String>("pattern").unwrap();
+    // Windowsでは \ 区切りのパスもそのままマッチさせる
+    let options = MatchOptions::new()
+        .windows(cfg!(windows))
+        .posix_slashes(cfg!(windows) || matches.get_flag("posix-slashes"));
+    let transfer = match matches.subcommand() {
+        Some(("rename", matches)) => Some((matches, Transfer::Rename)),
+        Some(("cp", matches)) => Some((matches, Transfer::Copy)),
+        Some(("mv", matches)) => Some((matches, Transfer::Move)),
+        _ => None,
+    };
+    if let Some((matches, transfer)) = transfer {
+        transfer_files(matches, &options, transfer);
+        return;
+    }
+    if let Some(("convert", matches)) = matches.subcommand() {
+        convert_pattern(matches, &options);
+        return;
+    }
+    let pattern = &Pattern::with_options(matches.get_one::<String>("pattern").unwrap(), &options);
     let list_mode = matches.get_flag("list");
     let recursive = matches.get_flag("recursive");
-    let verbose = matches.get_flag("verbose");
+    let follow = matches.get_flag("follow");
+    let min_depth = matches.get_one::<usize>("min-depth").copied();
+    let max_depth = matches.get_one::<usize>("max-depth").copied();
     let basename_mode = matches.get_flag("basename");
+    let mut output = Output {
+        verbose: matches.get_flag("verbose"),
+        records: (matches.get_one::<String>("output").map(String::as_str) == Some("json")).then(Vec::new),
+    };
 
     if list_mode {
-        list_matching_files(pattern, recursive, verbose, basename_mode);
+        list_matching_files(pattern, recursive, min_depth, max_depth, follow, &mut output, basename_mode);
     } else if let Some(paths) = matches.get_many::<String>("paths") {
         for path in paths {
-            check_path_match(pattern, path, verbose, basename_mode);
+            check_path_match(pattern, path, &mut output, basename_mode);
         }
     } else {
-        read_from_stdin(pattern, verbose, basename_mode);
+        read_from_stdin(pattern, &mut output, basename_mode);
+    }
+    output.finish();
+}
+
+/// How results are reported: as text lines while they are found, or, for
+/// `--output json`, collected and printed as one JSON array at the end.
+struct Output {
+    verbose: bool,
+    /// `Some` in JSON mode.
+    records: Option<Vec<MatchRecord>>,
+}
+
+/// One element of the JSON output.
+struct MatchRecord {
+    path: String,
+    matched: bool,
+    pattern: String,
+    basename_used: bool,
+}
+
+impl Output {
+    /// Records a result in JSON mode, returning `false` in text mode.
+    fn record(&mut self, path: &str, matched: bool, pattern: &Pattern, basename_used: bool) -> bool {
+        let Some(records) = &mut self.records else {
+            return false;
+        };
+        records.push(MatchRecord {
+            path: path.to_string(),
+            matched,
+            pattern: pattern.as_str().to_string(),
+            basename_used,
+        });
+        true
+    }
+
+    /// Prints the JSON array, one object per line. Does nothing in text mode.
+    fn finish(self) {
+        let Some(records) = self.records else {
+            return;
+        };
+        let objects: Vec<String> = records
+            .iter()
+            .map(|record| {
+                format!(
+                    "  {{\"path\": {}, \"matched\": {}, \"pattern\": {}, \"basename_used\": {}}}",
+                    json_string(&record.path),
+                    record.matched,
+                    json_string(&record.pattern),
+                    record.basename_used
+                )
+            })
+            .collect();
+        if objects.is_empty() {
+            println!("[]");
+        } else {
+            println!("[\n{}\n]", objects.join(",\n"));
+        }
+    }
+}
+
+/// Quotes `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            // その他の制御文字は \u でエスケープする
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
     }
+    json.push('"');
+    json
 }
 
 /// Lists files matching the given pattern.
@@ -115,90 +291,102 @@ fn main() {
 /// # Arguments
 /// * `pattern` - Glob pattern to match against
 /// * `recursive` - If true, search recursively through directories
-/// * `verbose` - If true, show verbose output including non-matches
+/// * `min_depth` - If set, skip files shallower than this (1 is the current directory)
+/// * `max_depth` - If set, descend at most this many levels when recursive
+/// * `follow` - If true, descend into symbolic links to directories
+/// * `output` - Where results go; when verbose, non-matches are reported too
 /// * `basename_mode` - If true, match against filename only (ignore directory path)
-fn list_matching_files(pattern: &str, recursive: bool, verbose: bool, basename_mode: bool) {
+fn list_matching_files(
+    pattern: &Pattern,
+    recursive: bool,
+    min_depth: Option<usize>,
+    max_depth: Option<usize>,
+    follow: bool,
+    output: &mut Output,
+    basename_mode: bool,
+) {
+    // 従来どおり隠しファイルもignoreファイルの対象も一覧に含める
+    let mut walk = WalkBuilder::new(".");
+    walk.hidden(false).ignore_files(false).follow_links(follow).min_depth(min_depth);
     if recursive {
-        list_files_recursive(".", pattern, verbose, basename_mode);
+        // パターンのリテラル前置部分の外は走査しない。verboseでは不一致もすべて報告する
+        if !basename_mode && !output.verbose {
+            walk.pattern(pattern.clone());
+        }
+        list_files_recursive(walk.max_depth(max_depth), pattern, output, basename_mode);
     } else {
-        list_files_in_directory(".", pattern, verbose, basename_mode);
+        list_files_in_directory(walk.max_depth(Some(1)), pattern, output);
     }
 }
 
-fn list_files_in_directory(dir: &str, pattern: &str, verbose: bool, basename_mode: bool) {
-    match fs::read_dir(dir) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(file_name) = entry.file_name().to_str() {
-                            let test_path = if basename_mode {
-                                file_name
-                            } else {
-                                file_name
-                            };
-                            
-                            if is_match(test_path, pattern) {
-                                println!("{}", file_name);
-                            } else if verbose {
-                                eprintln!("No match: {}", file_name);
-                            }
-                        }
-                    } else if verbose {
-                        if let Some(dir_name) = entry.file_name().to_str() {
-                            eprintln!("Skipping directory: {}", dir_name);
-                        }
-                    }
-                }
+fn list_files_in_directory(walk: &WalkBuilder, pattern: &Pattern, output: &mut Output) {
+    for entry in walk.build() {
+        let path = match entry {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Error reading directory: {}", e);
+                continue;
             }
+        };
+        // 単一ディレクトリではファイル名がそのままbasenameになる
+        // UTF-8 でない名前も読み飛ばさず、不正な並びを U+FFFD に置き換えて照合する
+        let Some(file_name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            continue;
+        };
+        // / で終わるパターンではファイルの代わりにディレクトリを一覧にする
+        let is_dir = path.is_dir();
+        if is_dir && !pattern.is_dir_only() {
+            if output.verbose {
+                eprintln!("Skipping directory: {}", file_name);
+            }
+            continue;
         }
-        Err(e) => {
-            eprintln!("Error reading directory {}: {}", dir, e);
+        let listed = format!("{}{}", file_name, if is_dir { "/" } else { "" });
+        let matched = pattern.is_match_entry(&file_name, is_dir);
+        if (matched || output.verbose) && output.record(&listed, matched, pattern, false) {
+            continue;
+        }
+        if matched {
+            println!("{}", listed);
+        } else if output.verbose {
+            eprintln!("No match: {}", file_name);
         }
     }
 }
 
-fn list_files_recursive(dir: &str, pattern: &str, verbose: bool, basename_mode: bool) {
-    if let Err(e) = visit_dir(Path::new(dir), pattern, verbose, basename_mode) {
-        eprintln!("Error walking directory tree: {}", e);
-    }
-}
-
-fn visit_dir(dir: &Path, pattern: &str, verbose: bool, basename_mode: bool) -> io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_dir() {
-                visit_dir(&path, pattern, verbose, basename_mode)?;
-            } else {
-                if let Some(path_str) = path.to_str() {
-                    let relative_path = if path_str.starts_with("./") {
-                        &path_str[2..]
-                    } else {
-                        path_str
-                    };
-                    
-                    let test_path = if basename_mode {
-                        path.file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or(relative_path)
-                    } else {
-                        relative_path
-                    };
-                    
-                    if is_match(test_path, pattern) {
-                        println!("{}", relative_path);
-                    } else if verbose {
-                        eprintln!("No match: {}", relative_path);
-                    }
-                }
+fn list_files_recursive(walk: &WalkBuilder, pattern: &Pattern, output: &mut Output, basename_mode: bool) {
+    for entry in walk.build() {
+        let path = match entry {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Error walking directory tree: {}", e);
+                continue;
             }
+        };
+        let is_dir = path.is_dir();
+        if is_dir && !pattern.is_dir_only() {
+            continue;
+        }
+        let path_str = path.to_string_lossy();
+        let relative_path = path_str.strip_prefix("./").unwrap_or(&path_str);
+
+        let file_name = path.file_name().map(|n| n.to_string_lossy());
+        let test_path = match &file_name {
+            Some(file_name) if basename_mode => file_name,
+            _ => relative_path,
+        };
+
+        let listed = format!("{}{}", relative_path, if is_dir { "/" } else { "" });
+        let matched = pattern.is_match_entry(test_path, is_dir);
+        if (matched || output.verbose) && output.record(&listed, matched, pattern, basename_mode) {
+            continue;
+        }
+        if matched {
+            println!("{}", listed);
+        } else if output.verbose {
+            eprintln!("No match: {}", relative_path);
         }
     }
-    Ok(())
 }
 
 /// Tests a single path against the given pattern and prints the result.
@@ -206,9 +394,9 @@ fn visit_dir(dir: &Path, pattern: &str, verbose: bool, basename_mode: bool) -> i
 /// # Arguments
 /// * `pattern` - Glob pattern to match against
 /// * `path` - File path to test
-/// * `verbose` - If true, show detailed matching information
+/// * `output` - Where the result goes; when verbose, text output shows detailed matching information
 /// * `basename_mode` - If true, match against filename only (ignore directory path)
-fn check_path_match(pattern: &str, path: &str, verbose: bool, basename_mode: bool) {
+fn check_path_match(pattern: &Pattern, path: &str, output: &mut Output, basename_mode: bool) {
     let test_path = if basename_mode {
         Path::new(path)
             .file_name()
@@ -218,7 +406,10 @@ fn check_path_match(pattern: &str, path: &str, verbose: bool, basename_mode: boo
         path
     };
     
-    let matches = is_match(test_path, pattern);
+    let matches = pattern.is_match(test_path);
+    if output.record(path, matches, pattern, basename_mode) {
+        return;
+    }
     
     if matches {
         println!("{}: MATCH", path);
@@ -226,8 +417,8 @@ fn check_path_match(pattern: &str, path: &str, verbose: bool, basename_mode: boo
         println!("{}: NO MATCH", path);
     }
     
-    if verbose {
-        println!("  Pattern: {}", pattern);
+    if output.verbose {
+        println!("  Pattern: {}", pattern.as_str());
         println!("  Path: {}", path);
         println!("  Test path: {}", test_path);
     }
@@ -237,9 +428,9 @@ fn check_path_match(pattern: &str, path: &str, verbose: bool, basename_mode: boo
 ///
 /// # Arguments
 /// * `pattern` - Glob pattern to match against
-/// * `verbose` - If true, show detailed matching information
+/// * `output` - Where the results go
 /// * `basename_mode` - If true, match against filename only (ignore directory path)
-fn read_from_stdin(pattern: &str, verbose: bool, basename_mode: bool) {
+fn read_from_stdin(pattern: &Pattern, output: &mut Output, basename_mode: bool) {
     let stdin = io::stdin();
     let reader = BufReader::new(stdin);
     
@@ -248,7 +439,7 @@ fn read_from_stdin(pattern: &str, verbose: bool, basename_mode: bool) {
             Ok(path) => {
                 let path = path.trim();
                 if !path.is_empty() {
-                    check_path_match(pattern, path, verbose, basename_mode);
+                    check_path_match(pattern, path, output, basename_mode);
                 }
             }
             Err(e) => {
@@ -257,4 +448,346 @@ fn read_from_stdin(pattern: &str, verbose: bool, basename_mode: bool) {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Prints the pattern converted to the format given with `--to`.
+///
+/// Exits with an error for patterns the format cannot express, such as a
+/// negated pattern as `.gitignore` rules.
+fn convert_pattern(matches: &ArgMatches, options: &MatchOptions) {
+    let pattern = matches.get_one::<String>("pattern").unwrap();
+    match matches.get_one::<String>("to").map(String::as_str) {
+        Some("gitignore") => match gitignore::from_glob(pattern) {
+            Some(lines) => {
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+            None => {
+                eprintln!("{}: negated patterns and extglobs have no .gitignore equivalent", pattern);
+                process::exit(1);
+            }
+        },
+        _ => println!("{}", to_regex(pattern, options)),
+    }
+}
+
+/// The file operations driven by a pattern and a path template.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    /// `satch rename`: the template is the new path, and nothing happens without `--apply`.
+    Rename,
+    /// `satch cp`: the template is the directory each file is copied into.
+    Copy,
+    /// `satch mv`: the template is the directory each file is moved into.
+    Move,
+}
+
+impl Transfer {
+    fn verb(self) -> &'static str {
+        match self {
+            Transfer::Rename => "rename",
+            Transfer::Copy => "copy",
+            Transfer::Move => "move",
+        }
+    }
+}
+
+/// Builds the `rename`, `cp` or `mv` subcommand.
+fn transfer_command(name: &'static str, transfer: Transfer) -> Command {
+    let (about, template_help, flag) = match transfer {
+        Transfer::Rename => (
+            "Rename files matching a pattern after a template (dry run unless --apply)",
+            "New path: {1}, {2}, ... for captures, {dir}, {name} and {ext} for the old path",
+            Arg::new("apply").long("apply").help("Rename the files instead of only showing the plan"),
+        ),
+        Transfer::Copy | Transfer::Move => (
+            if transfer == Transfer::Copy {
+                "Copy files matching a pattern into the directories a template names"
+            } else {
+                "Move files matching a pattern into the directories a template names"
+            },
+            "Target directory: {1}, {2}, ... for captures, {dir}, {name} and {ext} for the old path",
+            Arg::new("dry-run").long("dry-run").help("Only show the plan"),
+        ),
+    };
+    Command::new(name)
+        .about(about)
+        .arg(Arg::new("pattern").help("Glob pattern selecting the files").required(true))
+        .arg(Arg::new("template").help(template_help).required(true))
+        .arg(flag.action(clap::ArgAction::SetTrue))
+}
+
+/// Renames, copies or moves the files under the current directory that
+/// match the pattern, or only prints the plan on a dry run.
+///
+/// Nothing is done when a matching path is not valid UTF-8, two files
+/// would get the same path or a new path is already taken.
+fn transfer_files(matches: &ArgMatches, options: &MatchOptions, transfer: Transfer) {
+    let pattern = Pattern::with_options(matches.get_one::<String>("pattern").unwrap(), options);
+    let template = matches.get_one::<String>("template").unwrap();
+    let dry_run = match transfer {
+        Transfer::Rename => !matches.get_flag("apply"),
+        Transfer::Copy | Transfer::Move => matches.get_flag("dry-run"),
+    };
+
+    let mut walk = WalkBuilder::new(".");
+    walk.hidden(false).ignore_files(false).pattern(pattern.clone());
+    let mut files = Vec::new();
+    for entry in walk.build() {
+        match entry {
+            Ok(path) if !path.is_dir() => files.push(path.strip_prefix(".").unwrap_or(&path).to_path_buf()),
+            Ok(_) => {}
+            Err(e) => eprintln!("Error walking directory tree: {}", e),
+        }
+    }
+    let plan = match plan_transfers(files, &pattern, template, transfer) {
+        Ok(plan) => plan,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            process::exit(1);
+        }
+    };
+
+    if let Err(conflicts) = check_targets(&plan) {
+        for conflict in conflicts {
+            eprintln!("{}", conflict);
+        }
+        process::exit(1);
+    }
+    for (from, to) in &plan {
+        println!("{} -> {}", from.display(), to.display());
+        if dry_run {
+            continue;
+        }
+        if let Err(e) = transfer_file(from, to, transfer) {
+            eprintln!("Error trying to {} {}: {}", transfer.verb(), from.display(), e);
+            process::exit(1);
+        }
+    }
+    if dry_run && !plan.is_empty() {
+        match transfer {
+            Transfer::Rename => eprintln!("Dry run: pass --apply to rename {} file(s)", plan.len()),
+            Transfer::Copy => eprintln!("Dry run: {} file(s) would be copied", plan.len()),
+            Transfer::Move => eprintln!("Dry run: {} file(s) would be moved", plan.len()),
+        }
+    }
+}
+
+/// Pairs each of `files` the pattern matches with its new path.
+///
+/// Files whose path is not valid UTF-8 are errors rather than renamed
+/// after a lossy copy of their name, which would lose the original bytes
+/// and could give two files the same new path. Any error leaves the plan
+/// empty, so nothing is done.
+fn plan_transfers(
+    files: Vec<PathBuf>,
+    pattern: &Pattern,
+    template: &str,
+    transfer: Transfer,
+) -> Result<Vec<(PathBuf, PathBuf)>, Vec<String>> {
+    let mut plan = Vec::new();
+    let mut errors = Vec::new();
+    for relative in files {
+        let Some(captures) = pattern.captures(&relative.to_string_lossy().replace('\\', "/")) else {
+            continue;
+        };
+        // 置換文字を含む名前にすると元のバイト列が失われる
+        let Some(path_str) = utf8_path(&relative) else {
+            errors.push(format!("{}: the path is not valid UTF-8, so its new path cannot be built", relative.display()));
+            continue;
+        };
+        let Some(target) = target_path(template, &captures, &path_str, transfer) else {
+            errors.push(format!("{}: the template refers to a capture the pattern does not have", path_str));
+            continue;
+        };
+        // 移動先が元と同じファイルは何もしない（コピーは上書きとして弾く）
+        if transfer == Transfer::Copy || normalize_path(&target) != normalize_path(&relative) {
+            plan.push((relative, target));
+        }
+    }
+    if errors.is_empty() {
+        Ok(plan)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns `path` with `/` separators, or `None` if it is not valid UTF-8.
+fn utf8_path(path: &Path) -> Option<String> {
+    path.to_str().map(|path| path.replace('\\', "/"))
+}
+
+/// Returns the new path of the file at `path` under `template`, or `None`
+/// when the template refers to a capture the pattern does not have.
+///
+/// Copies and moves keep the file name inside the directory the template
+/// names. `.` segments, like the one `{dir}` leaves for top-level files,
+/// are dropped so the new path reads like the paths the walk yields.
+fn target_path(template: &str, captures: &[String], path: &str, transfer: Transfer) -> Option<PathBuf> {
+    let target = expand_template(captures, &fill_path_placeholders(template, path))?;
+    let mut target = normalize_path(Path::new(&target));
+    if transfer != Transfer::Rename {
+        target.push(path.rsplit('/').next().unwrap_or(path));
+    }
+    Some(target)
+}
+
+/// Renames, copies or moves one file, creating the directories `to`
+/// needs.
+fn transfer_file(from: &Path, to: &Path, transfer: Transfer) -> io::Result<()> {
+    if let Some(parent) = to.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    match transfer {
+        Transfer::Copy => fs::copy(from, to).map(|_| ()),
+        Transfer::Rename | Transfer::Move => match fs::rename(from, to) {
+            // 別のファイルシステムへはコピーしてから消す
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                fs::copy(from, to)?;
+                fs::remove_file(from)
+            }
+            result => result,
+        },
+    }
+}
+
+/// Returns a message for each new path that two files share or that
+/// already exists.
+fn check_targets(plan: &[(PathBuf, PathBuf)]) -> Result<(), Vec<String>> {
+    let mut conflicts = Vec::new();
+    let mut sources: HashMap<PathBuf, &Path> = HashMap::new();
+    for (from, to) in plan {
+        // ./a.js と a.js は同じファイルとして数える
+        let key = normalize_path(to);
+        if let Some(other) = sources.get(&key) {
+            conflicts.push(format!("{} and {} would both become {}", other.display(), from.display(), to.display()));
+            continue;
+        }
+        sources.insert(key, from);
+        if to.exists() {
+            conflicts.push(format!("{} would replace the existing {}", from.display(), to.display()));
+        }
+    }
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Returns `path` without its `.` segments, so that paths naming the same
+/// file compare equal.
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components().filter(|component| *component != Component::CurDir).collect()
+}
+
+/// Fills in the `{dir}`, `{name}` and `{ext}` placeholders of a template
+/// from `path`, leaving capture numbers and escaped braces to
+/// [`expand_template`].
+///
+/// `{dir}` is the directory of `path` (`.` for none), `{name}` its file
+/// name without the extension and `{ext}` the extension without the dot.
+fn fill_path_placeholders(template: &str, path: &str) -> String {
+    let (dir, file_name) = path.rsplit_once('/').unwrap_or((".", path));
+    // .bashrc のような先頭のドットは拡張子の区切りではない
+    let (name, ext) = match file_name.rfind('.') {
+        Some(dot) if dot > 0 => (&file_name[..dot], &file_name[dot + 1..]),
+        _ => (file_name, ""),
+    };
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(brace) = rest.find('{') {
+        filled.push_str(&rest[..brace]);
+        rest = &rest[brace..];
+        let value = ["dir", "name", "ext"]
+            .into_iter()
+            .zip([dir, name, ext])
+            .find_map(|(key, value)| rest[1..].strip_prefix(key)?.strip_prefix('}').map(|after| (value, after)));
+        match value {
+            Some((value, after)) => {
+                // 値の波括弧が番号として読まれないようエスケープする
+                filled.push_str(&value.replace('{', "{{").replace('}', "}}"));
+                rest = after;
+            }
+            None => {
+                // {{ はまとめて送り、エスケープの中身を名前として読まない
+                let len = if rest.starts_with("{{") { 2 } else { 1 };
+                filled.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(template: &str, pattern: &str, path: &str, transfer: Transfer) -> String {
+        let captures = Pattern::new(pattern).captures(path).unwrap();
+        target_path(template, &captures, path, transfer).unwrap().display().to_string()
+    }
+
+    #[test]
+    fn test_target_path_top_level_file() {
+        // {dir} はトップレベルで . になるが、新しいパスには残さない
+        assert_eq!(target("{dir}/{name}.js", "**/*.js", "a.js", Transfer::Rename), "a.js");
+        assert_eq!(target("{dir}/{name}.ts", "**/*.js", "a.js", Transfer::Rename), "a.ts");
+        assert_eq!(target("out/{dir}", "**/*.js", "a.js", Transfer::Copy), "out/a.js");
+        assert_eq!(target("{dir}/{name}.ts", "**/*.js", "src/a.js", Transfer::Rename), "src/a.ts");
+        assert_eq!(target("out/{dir}", "**/*.js", "src/a.js", Transfer::Copy), "out/src/a.js");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_utf8_path_rejects_invalid_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(utf8_path(Path::new("src/café.js")), Some("src/café.js".to_string()));
+        assert_eq!(utf8_path(Path::new(OsStr::from_bytes(b"caf\xE9.js"))), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plan_transfers_rejects_invalid_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // 置換文字にすると2つの名前が同じになるが、どちらも移動しない
+        let files = vec![
+            PathBuf::from("ok.js"),
+            PathBuf::from(OsStr::from_bytes(b"a\xE9.js")),
+            PathBuf::from(OsStr::from_bytes(b"a\xE8.js")),
+        ];
+        let pattern = Pattern::new("*.js");
+        for transfer in [Transfer::Rename, Transfer::Copy, Transfer::Move] {
+            let errors = plan_transfers(files.clone(), &pattern, "out", transfer).unwrap_err();
+            assert_eq!(errors.len(), 2);
+            assert!(errors[0].ends_with("the path is not valid UTF-8, so its new path cannot be built"));
+        }
+        let plan = plan_transfers(vec![PathBuf::from("src/a.js")], &Pattern::new("**/*.js"), "out/{dir}", Transfer::Move);
+        assert_eq!(plan.unwrap(), [(PathBuf::from("src/a.js"), PathBuf::from("out/src/a.js"))]);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("./a.js")).display().to_string(), "a.js");
+        assert_eq!(normalize_path(Path::new("./src/./a.js")).display().to_string(), "src/a.js");
+        assert_eq!(normalize_path(Path::new("src/a.js")).display().to_string(), "src/a.js");
+        // mv "**/*.js" "{dir}" はトップレベルのファイルをその場に残す
+        assert_eq!(normalize_path(&Path::new(".").join("a.js")), normalize_path(Path::new("a.js")));
+    }
+
+    #[test]
+    fn test_check_targets_normalizes_paths() {
+        let plan = [(PathBuf::from("x/a.js"), PathBuf::from("./a.js")), (PathBuf::from("y/a.js"), PathBuf::from("a.js"))];
+        let conflicts = check_targets(&plan).unwrap_err();
+        assert_eq!(conflicts, ["x/a.js and y/a.js would both become a.js"]);
+    }
+}
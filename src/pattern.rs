@@ -0,0 +1,2322 @@
+//! Compiled glob patterns.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Component, Path, Prefix};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::automaton::{find_input, Automaton};
+use crate::brace::expand_braces;
+use crate::casefold::{fold_pattern, FoldedInput};
+use crate::delimiters::Delimiters;
+use crate::compiled::{from_tokens, to_tokens, Compiled, CompiledAlternative, Token};
+use crate::complexity::{estimate, Complexity};
+use crate::error::{check_length, validate, whole_pattern_error};
+use crate::extglob::ExtglobKind;
+use crate::scan::scan;
+#[cfg(feature = "stats")]
+use crate::stats::{self, MatchStats, StatsSlot};
+use crate::{
+    class_matches_slash, collapse_globstars, find_expanded_pattern, match_expanded_pattern, match_expanded_pattern_with, parse_glob_segments,
+    parse_glob_segments_within, replace_named_placeholders, rewrite_syntax, split_negation, strip_dot_slash,
+    swap_separator, to_nfc, trace_expanded_pattern, trim_globstar_spans, AlternativeTrace, Candidate, CaptureSpans,
+    GlobSegment, MatchOptions, MatchTrace, Mismatch, PatternError, SegmentSpan, StepLimitExceeded, TraceOutcome,
+};
+
+/// A glob pattern prepared once and matched against many inputs.
+///
+/// Negation and brace expansion are resolved at construction time, so
+/// repeated matching skips that work. The compiled form is shared between
+/// clones, so a pattern is cheap to clone into worker threads or caches.
+///
+/// Patterns are equal, hash and order by their source and options, so
+/// they can key maps and sets. The source is compared after the rewrites
+/// of [`Pattern::normalized`] that keep the matched inputs unchanged, so
+/// `./a` and `a` are the same key, but `a/./b` and `a/b` are not.
+///
+/// ```
+/// use satch::Pattern;
+///
+/// let pattern = Pattern::new("src/**/*.{js,ts}");
+/// assert!(pattern.is_match("src/lib/app.ts"));
+/// assert!(!pattern.is_match("test/app.ts"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: Arc<str>,
+    options: MatchOptions,
+    negated: bool,
+    alternatives: Arc<[Alternative]>,
+    /// 比較とハッシュに使う正規化した文字列。一致する入力が変わる . の除去はしない
+    key: Arc<str>,
+    /// 直近の照合の統計。クローン間で共有する
+    #[cfg(feature = "stats")]
+    stats: StatsSlot,
+}
+
+// 比較とハッシュは正規化したパターンの文字列と設定だけで決まり、コンパイル結果は見ない
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.options == other.options
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Hash for Pattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.options.hash(state);
+    }
+}
+
+impl PartialOrd for Pattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders by source, compared as for equality, then by options.
+impl Ord for Pattern {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.key, &self.options).cmp(&(&other.key, &other.options))
+    }
+}
+
+/// Writes the pattern source.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Compiles with [`Pattern::try_new`], so malformed syntax is an error.
+///
+/// ```
+/// use satch::Pattern;
+///
+/// let pattern: Pattern = "**/*.rs".parse().unwrap();
+/// assert_eq!(pattern.to_string(), "**/*.rs");
+/// assert!("src/[abc".parse::<Pattern>().is_err());
+/// ```
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Self::try_new(pattern)
+    }
+}
+
+/// What is known about the paths directly inside one directory, worked
+/// out once and shared between them.
+pub(crate) struct DirFrontier {
+    /// alternatives と同じ順に並ぶ
+    states: Vec<DirState>,
+    /// ファイル名だけを照合するときの設定。ドットファイルの規則はパスの先頭にだけ適用される
+    name_options: MatchOptions,
+}
+
+enum DirState {
+    /// No path in the directory matches the alternative.
+    Rejected,
+    /// The directories match, leaving the file name to match these segments.
+    Name(Vec<GlobSegment>),
+    /// The whole path has to be matched.
+    Full,
+}
+
+/// One brace expansion of a pattern, kept both as written and parsed.
+#[derive(Debug, Clone)]
+struct Alternative {
+    chars: Vec<char>,
+    segments: Vec<GlobSegment>,
+    /// Literal text every match starts with, checked before running the matcher.
+    prefix: String,
+    /// Literal text every match ends with, after the prefix.
+    suffix: String,
+    /// Ends in `/`, so only directories match when the input says whether
+    /// it is one.
+    dir_only: bool,
+    /// The names of `{name}` placeholders, with the index of the wildcard
+    /// each became among the alternative's wildcards.
+    names: Vec<(usize, String)>,
+}
+
+impl Alternative {
+    fn new(chars: Vec<char>, segments: Vec<GlobSegment>, nocase: bool) -> Self {
+        let literal = |segment: Option<&GlobSegment>| match segment {
+            // nocase では入力を畳み込むまで比較できないので前置・後置の絞り込みをしない
+            Some(GlobSegment::Literal(literal)) if !nocase => literal.iter().collect(),
+            _ => String::new(),
+        };
+        let prefix = literal(segments.first());
+        // リテラルだけのパターンでは同じセグメントを二重に数えない
+        let suffix = if segments.len() > 1 { literal(segments.last()) } else { String::new() };
+        let dir_only = chars.last() == Some(&'/');
+        Self {
+            chars,
+            segments,
+            prefix,
+            suffix,
+            dir_only,
+            names: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if `subject` cannot match because it lacks the
+    /// literal prefix or suffix.
+    fn rejects(&self, subject: &str) -> bool {
+        subject.len() < self.prefix.len() + self.suffix.len()
+            || !subject.starts_with(&self.prefix)
+            || !subject.ends_with(&self.suffix)
+    }
+}
+
+impl Pattern {
+    /// Compiles `pattern` with the default options.
+    pub fn new(pattern: &str) -> Self {
+        Self::with_options(pattern, &MatchOptions::default())
+    }
+
+    /// Compiles `pattern` with the given options.
+    ///
+    /// A pattern exceeding the [`max_pattern_len`](MatchOptions::max_pattern_len)
+    /// or [`max_segments`](MatchOptions::max_segments) limits, or nesting
+    /// extglobs more than 32 levels deep, matches nothing.
+    pub fn with_options(pattern: &str, options: &MatchOptions) -> Self {
+        Self::compile(pattern, options).unwrap_or_else(|_| Self {
+            source: pattern.into(),
+            options: options.clone(),
+            negated: false,
+            alternatives: Arc::new([]),
+            key: pattern.into(),
+            #[cfg(feature = "stats")]
+            stats: StatsSlot::default(),
+        })
+    }
+
+    /// Compiles `pattern`, failing only when it exceeds the limits in `options`.
+    fn compile(pattern: &str, options: &MatchOptions) -> Result<Self, PatternError> {
+        let (negated, alternatives) = Self::compile_alternatives(pattern, options)?;
+        Ok(Self {
+            source: pattern.into(),
+            options: options.clone(),
+            negated,
+            key: normalize_pattern(pattern, options, &alternatives, false).into(),
+            alternatives,
+            #[cfg(feature = "stats")]
+            stats: StatsSlot::default(),
+        })
+    }
+
+    /// Resolves the negation and compiles each brace alternative of `pattern`.
+    fn compile_alternatives(pattern: &str, options: &MatchOptions) -> Result<(bool, Arc<[Alternative]>), PatternError> {
+        check_length(pattern, options.max_pattern_len)?;
+        let rewritten = rewrite_syntax(pattern, options);
+        let (negated, body) = split_negation(&rewritten);
+        // 区切り文字を / と入れ替えて、/ 区切りのパターンとして扱う
+        let body = swap_separator(body, options.separator);
+        let alternatives = expand_braces(&body)
+            .iter()
+            .map(|expanded| {
+                let expanded = if options.strips_dot_slash() { strip_dot_slash(expanded) } else { expanded };
+                let mut chars: Vec<char> = expanded.chars().collect();
+                let mut names = Vec::new();
+                if options.named_captures {
+                    let (replaced, placeholders) = replace_named_placeholders(&chars);
+                    // 手前のワイルドカードを数えて、何番目のキャプチャになるかを求める
+                    names = placeholders
+                        .into_iter()
+                        .map(|(position, name)| {
+                            let before = parse_glob_segments(&replaced[..position]);
+                            (before.iter().filter(|segment| !matches!(segment, GlobSegment::Literal(_))).count(), name)
+                        })
+                        .collect();
+                    chars = replaced;
+                }
+                if options.noglobstar || options.strict_globstar {
+                    chars = collapse_globstars(&chars, options.noglobstar);
+                }
+                if options.nocase {
+                    chars = fold_pattern(&chars);
+                }
+                let segments = parse_glob_segments_within(&chars, options.max_segments)
+                    .map_err(|kind| whole_pattern_error(kind, pattern))?;
+                Ok(Alternative {
+                    names,
+                    ..Alternative::new(chars, segments, options.nocase)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((negated, alternatives))
+    }
+
+    /// Compiles `pattern` with the default options, rejecting malformed
+    /// syntax such as an unclosed `[` instead of treating it as literal text,
+    /// and patterns over the default limits.
+    pub fn try_new(pattern: &str) -> Result<Self, PatternError> {
+        Self::try_with_options(pattern, &MatchOptions::default())
+    }
+
+    /// Like [`Pattern::try_new`], with the given options.
+    pub fn try_with_options(pattern: &str, options: &MatchOptions) -> Result<Self, PatternError> {
+        check_length(pattern, options.max_pattern_len)?;
+        validate(pattern, options)?;
+        Self::compile(pattern, options)
+    }
+
+    /// Rebuilds a pattern compiled from `pattern` with `options` from its
+    /// compiled form, without parsing it again. Used by `satch-macros`.
+    #[doc(hidden)]
+    pub fn __from_compiled(pattern: &str, options: &MatchOptions, compiled: &Compiled<'_>) -> Self {
+        let alternatives = compiled
+            .alternatives
+            .iter()
+            .map(|alternative| Alternative {
+                names: alternative.names.iter().map(|&(index, name)| (index, name.to_string())).collect(),
+                ..Alternative::new(alternative.chars.to_vec(), from_tokens(alternative.tokens), options.nocase)
+            })
+            .collect();
+        Self {
+            source: pattern.into(),
+            options: options.clone(),
+            negated: compiled.negated,
+            alternatives,
+            key: compiled.key.into(),
+            #[cfg(feature = "stats")]
+            stats: StatsSlot::default(),
+        }
+    }
+
+    /// Calls `f` with the compiled form of the pattern, which
+    /// [`Pattern::__from_compiled`] turns back into the pattern. Used by
+    /// `satch-macros`.
+    #[doc(hidden)]
+    pub fn __with_compiled<R>(&self, f: impl FnOnce(&Compiled<'_>) -> R) -> R {
+        let tokens: Vec<Vec<Token<'_>>> = self.alternatives.iter().map(|alternative| to_tokens(&alternative.segments)).collect();
+        let names: Vec<Vec<(usize, &str)>> = self
+            .alternatives
+            .iter()
+            .map(|alternative| alternative.names.iter().map(|(index, name)| (*index, name.as_str())).collect())
+            .collect();
+        let alternatives: Vec<CompiledAlternative<'_>> = self
+            .alternatives
+            .iter()
+            .zip(&tokens)
+            .zip(&names)
+            .map(|((alternative, tokens), names)| CompiledAlternative {
+                chars: &alternative.chars,
+                tokens,
+                names,
+            })
+            .collect();
+        f(&Compiled {
+            negated: self.negated,
+            key: &self.key,
+            alternatives: &alternatives,
+        })
+    }
+
+    /// Returns the pattern source this matcher was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the source with redundancies removed, for deduplicating
+    /// pattern lists or keying caches.
+    ///
+    /// `.` path segments are dropped as [`Pattern::matches_path`] drops
+    /// them from paths, so `a/./b` becomes `a/b`. The other rewrites keep
+    /// the matched paths unchanged: repeated `**` segments become one
+    /// (`**/**/a` becomes `**/a`) unless [`noglobstar`](MatchOptions::noglobstar)
+    /// is set, and runs of three or more `*` inside a segment become `**`.
+    /// Escapes, classes, braces and extglobs are kept as written. Patterns
+    /// with a custom [`separator`](MatchOptions::separator) are returned
+    /// unchanged.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// assert_eq!(Pattern::new("./src/**/**/./a***.rs").normalized(), "src/**/a**.rs");
+    /// assert_eq!(Pattern::new("!{a/./b,c}/./*").normalized(), "!{a/./b,c}/*");
+    /// ```
+    pub fn normalized(&self) -> String {
+        normalize_pattern(&self.source, &self.options, &self.alternatives, true)
+    }
+
+    /// Returns `true` if the pattern starts with a `!` negation.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Returns the one input this pattern matches when it has no glob
+    /// syntax, with escapes removed, or `None` otherwise.
+    ///
+    /// Options that let a literal match other inputs, such as `nocase`,
+    /// `match_base`, `windows`, `posix_slashes` and `normalize_unicode`,
+    /// make every pattern non-literal.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// assert_eq!(Pattern::new("src/main\\*.rs").literal(), Some("src/main*.rs".to_string()));
+    /// assert_eq!(Pattern::new("src/*.rs").literal(), None);
+    /// ```
+    pub fn literal(&self) -> Option<String> {
+        let options = &self.options;
+        if self.negated || options.nocase || options.normalize_unicode || options.converts_backslashes() {
+            return None;
+        }
+        let [alternative] = &self.alternatives[..] else {
+            return None;
+        };
+        let literal: String = match &alternative.segments[..] {
+            [] => String::new(),
+            [GlobSegment::Literal(literal)] => literal.iter().collect(),
+            _ => return None,
+        };
+        // match_base ではファイル名だけのパターンがどのディレクトリの同名ファイルにもマッチする
+        // strict_slashes では // を含む入力が弾かれるので、そのパターンは何にもマッチしない
+        if (options.match_base && !literal.contains('/')) || (options.strict_slashes && literal.contains("//")) {
+            return None;
+        }
+        Some(self.unswap(literal))
+    }
+
+    /// Returns `true` if the pattern needs the matcher, that is, it has no
+    /// [`literal`](Pattern::literal) text to compare inputs with.
+    pub fn has_magic(&self) -> bool {
+        self.literal().is_none()
+    }
+
+    /// Returns the options this pattern was compiled with.
+    pub fn options(&self) -> &MatchOptions {
+        &self.options
+    }
+
+    /// Returns `true` if this pattern matches every input `other` matches,
+    /// making `other` redundant next to it.
+    ///
+    /// The answer is conservative: `false` means the containment could not
+    /// be shown. Patterns with extglobs, POSIX classes, or the `nocase`,
+    /// `match_base` or `max_steps` options are only compared for equality,
+    /// as are patterns with different `windows` or `posix_slashes` settings.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// assert!(Pattern::new("**/*.js").is_superset_of(&Pattern::new("src/**/*.js")));
+    /// assert!(Pattern::new("src/*.{js,ts}").is_superset_of(&Pattern::new("src/[a-m]*.ts")));
+    /// assert!(!Pattern::new("*.js").is_superset_of(&Pattern::new("src/*.js")));
+    /// ```
+    pub fn is_superset_of(&self, other: &Pattern) -> bool {
+        if self == other {
+            return true;
+        }
+        let (Some(this), Some(other_automaton)) = (self.automaton(), other.automaton()) else {
+            return false;
+        };
+        if !self.same_slashes(other) {
+            return false;
+        }
+        find_input(&this, &other_automaton, |this_matches, other_matches| other_matches && !this_matches) == Some(false)
+    }
+
+    /// Returns `true` if some input could match both this pattern and
+    /// `other`, such as an include and an exclude rule that conflict.
+    ///
+    /// The answer is conservative in the other direction: `false` means
+    /// no input matches both. Patterns that [`Pattern::is_superset_of`]
+    /// cannot compare are assumed to overlap.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// assert!(Pattern::new("src/**/*.js").overlaps(&Pattern::new("**/test/*")));
+    /// assert!(!Pattern::new("*.js").overlaps(&Pattern::new("*.ts")));
+    /// assert!(!Pattern::new("src/*").overlaps(&Pattern::new("!src/**")));
+    /// ```
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        let (Some(this), Some(other_automaton)) = (self.automaton(), other.automaton()) else {
+            return true;
+        };
+        if !self.same_slashes(other) {
+            return true;
+        }
+        find_input(&this, &other_automaton, |this_matches, other_matches| this_matches && other_matches) != Some(false)
+    }
+
+    /// Returns the longest `/`-terminated literal prefix every match starts
+    /// with, or an empty string when there is none. Escapes are removed, so
+    /// the prefix can be used as a directory to start a walk from.
+    ///
+    /// Negated and [`nocase`](MatchOptions::nocase) patterns, and those
+    /// with a custom [`separator`](MatchOptions::separator) or
+    /// [`normalize_unicode`](MatchOptions::normalize_unicode), have no
+    /// literal prefix. With [`named_captures`](MatchOptions::named_captures),
+    /// a `{name}` placeholder ends the prefix like a wildcard.
+    ///
+    /// ```
+    /// use satch::{MatchOptions, Pattern};
+    ///
+    /// assert_eq!(Pattern::new("src/components/**/*.tsx").literal_prefix(), "src/components/");
+    /// assert_eq!(Pattern::new("src/main.rs").literal_prefix(), "src/");
+    /// assert_eq!(Pattern::new("*.rs").literal_prefix(), "");
+    ///
+    /// let options = MatchOptions::new().named_captures(true);
+    /// assert_eq!(Pattern::with_options("src/{version}/*.rs", &options).literal_prefix(), "src/");
+    /// ```
+    pub fn literal_prefix(&self) -> String {
+        if self.negated || self.options.nocase || self.options.normalize_unicode || self.options.separator != '/' {
+            return String::new();
+        }
+        let rewritten = rewrite_syntax(&self.source, &self.options);
+        // {name} のプレースホルダは * と同じくどのディレクトリにもマッチするので、前置部分を終わらせる
+        let result = if self.options.named_captures {
+            let chars: Vec<char> = rewritten.chars().collect();
+            scan(&replace_named_placeholders(&chars).0.into_iter().collect::<String>())
+        } else {
+            scan(&rewritten)
+        };
+        // グロブを含まないパターンは最後の / までが前置部分になる
+        let base = if result.is_glob {
+            result.base
+        } else {
+            result.base.rfind('/').map_or(String::new(), |slash| result.base[..slash].to_string())
+        };
+
+        let mut prefix = String::with_capacity(base.len() + 1);
+        let mut chars = base.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => prefix.extend(chars.next()),
+                _ => prefix.push(c),
+            }
+        }
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        prefix
+    }
+
+    /// Returns `true` if both patterns treat `\\` in inputs alike, which
+    /// comparing their automata needs.
+    fn same_slashes(&self, other: &Pattern) -> bool {
+        self.options.windows == other.options.windows
+            && self.options.converts_backslashes() == other.options.converts_backslashes()
+    }
+
+    fn automaton(&self) -> Option<Automaton<'_>> {
+        Automaton::new(
+            self.alternatives.iter().map(|alternative| alternative.segments.as_slice()),
+            self.negated,
+            &self.options,
+        )
+    }
+
+    /// Estimates how expensive this pattern can be to match: how many
+    /// alternatives its braces expand to, its segment and `**` counts, and
+    /// how matching time grows with the input.
+    ///
+    /// A pattern that exceeded its limits when compiled matches nothing and
+    /// reports zero alternatives.
+    ///
+    /// ```
+    /// use satch::{Pattern, WorstCase};
+    ///
+    /// let complexity = Pattern::new("src/**/*.{js,ts}").complexity();
+    /// assert_eq!(complexity.alternatives, 2);
+    /// assert_eq!(complexity.globstars, 2);
+    /// assert_eq!(complexity.worst_case, WorstCase::Backtracking);
+    ///
+    /// // Nested extglobs grow quickly with the input length
+    /// let complexity = Pattern::new("+(a|*(b|c))").complexity();
+    /// assert_eq!(complexity.extglob_depth, 2);
+    /// assert!(complexity.worst_case > WorstCase::Backtracking);
+    /// ```
+    pub fn complexity(&self) -> Complexity {
+        estimate(self.alternatives.iter().map(|alternative| alternative.segments.as_slice()))
+    }
+
+    /// Returns `true` if `input` matches this pattern.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.is_match_normalized(&self.normalize_input(input), self.options.windows, None)
+    }
+
+    /// Like [`Pattern::is_match`], telling the pattern whether `input`
+    /// names a directory.
+    ///
+    /// As in gitignore, a brace alternative ending in `/` matches only
+    /// directories, which need not be written with the trailing `/`. Other
+    /// alternatives match as in [`Pattern::is_match`].
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("**/build/");
+    /// assert!(pattern.is_match_entry("web/build", true));
+    /// assert!(!pattern.is_match_entry("web/build", false));
+    ///
+    /// // Without the hint, only the trailing `/` tells directories apart
+    /// assert!(pattern.is_match("web/build/"));
+    /// assert!(!pattern.is_match("web/build"));
+    /// ```
+    pub fn is_match_entry(&self, input: &str, is_dir: bool) -> bool {
+        self.is_match_candidate(&Candidate::new(input).dir(is_dir))
+    }
+
+    /// Returns `true` if every brace alternative ends in `/`, so the
+    /// pattern matches nothing but directories.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// assert!(Pattern::new("{build,dist}/").is_dir_only());
+    /// assert!(!Pattern::new("{build/,*.log}").is_dir_only());
+    /// assert!(!Pattern::new("!build/").is_dir_only());
+    /// ```
+    pub fn is_dir_only(&self) -> bool {
+        !self.negated && !self.alternatives.is_empty() && self.alternatives.iter().all(|alternative| alternative.dir_only)
+    }
+
+    /// Like [`Pattern::is_match`], but fails instead of answering when
+    /// matching runs out of [`max_steps`](MatchOptions::max_steps), so an
+    /// input too expensive to match is not mistaken for a mismatch.
+    ///
+    /// The limit applies to each brace alternative separately, and the
+    /// first alternative to exceed it ends the match.
+    ///
+    /// ```
+    /// use satch::{MatchOptions, Pattern};
+    ///
+    /// let pattern = Pattern::with_options("+(a|aa)", &MatchOptions::new().max_steps(100));
+    /// assert_eq!(pattern.try_is_match("aaa"), Ok(true));
+    /// assert!(pattern.try_is_match(&"a".repeat(1000)).is_err());
+    /// ```
+    pub fn try_is_match(&self, input: &str) -> Result<bool, StepLimitExceeded> {
+        self.try_match_normalized(&self.normalize_input(input), self.options.windows, None, true)
+    }
+
+    /// Returns the text matched by each wildcard (`*`, `**`, `?`, character
+    /// classes and extglobs) in pattern order, or `None` if `input` does not
+    /// match.
+    ///
+    /// A `**` followed by `/` captures the directories it spans without the
+    /// trailing slash. Negated patterns never produce captures. With the
+    /// [`windows`](MatchOptions::windows) option, captured text uses `/`
+    /// separators.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("src/**/*.js");
+    /// assert_eq!(pattern.captures("src/lib/util/a.js"), Some(vec!["lib/util".into(), "a".into()]));
+    /// assert_eq!(pattern.captures("src/a.ts"), None);
+    /// ```
+    pub fn captures(&self, input: &str) -> Option<Vec<String>> {
+        let input = self.normalize_input(input);
+        let (_, spans) = self.capture_spans(&input)?;
+        Some(spans.into_iter().map(|span| self.unswap(input[span].to_string())).collect())
+    }
+
+    /// Returns the text matched by each `{name}` placeholder, keyed by
+    /// name, or `None` if `input` does not match. Placeholders need the
+    /// [`named_captures`](MatchOptions::named_captures) option; without
+    /// any, a match yields an empty map. A name used twice keeps the text
+    /// of its last placeholder.
+    ///
+    /// ```
+    /// use satch::{MatchOptions, Pattern};
+    ///
+    /// let options = MatchOptions::new().named_captures(true);
+    /// let pattern = Pattern::with_options("releases/{version}/notes-*.md", &options);
+    /// let captures = pattern.named_captures("releases/1.4.0/notes-en.md").unwrap();
+    /// assert_eq!(captures["version"], "1.4.0");
+    /// assert_eq!(pattern.named_captures("releases/1.4.0/a/notes-en.md"), None);
+    /// ```
+    pub fn named_captures(&self, input: &str) -> Option<HashMap<String, String>> {
+        let input = self.normalize_input(input);
+        let (alternative, spans) = self.capture_spans(&input)?;
+        Some(
+            alternative
+                .names
+                .iter()
+                .map(|(index, name)| (name.clone(), self.unswap(input[spans[*index].clone()].to_string())))
+                .collect(),
+        )
+    }
+
+    /// Returns the byte range of `input` matched by each wildcard, in the
+    /// same order and under the same rules as [`Pattern::captures`], or
+    /// `None` if `input` does not match. Editors can use the ranges to
+    /// highlight what each wildcard matched.
+    ///
+    /// The ranges index `input` as given, including a leading `./` or a
+    /// Windows verbatim marker the matcher skips. With
+    /// [`normalize_unicode`](MatchOptions::normalize_unicode), an input not
+    /// in NFC gets ranges into its NFC form, which is what was matched.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("src/**/*.js");
+    /// assert_eq!(pattern.match_spans("./src/lib/util/a.js"), Some(vec![6..14, 15..16]));
+    /// assert_eq!(pattern.match_spans("src/a.ts"), None);
+    /// ```
+    pub fn match_spans(&self, input: &str) -> Option<Vec<Range<usize>>> {
+        // 取り除いた先頭の分だけずらして、元の入力の位置に戻す
+        let (normalized, offset) = self.normalize_input_with_offset(input);
+        let (_, spans) = self.capture_spans(&normalized)?;
+        Some(spans.into_iter().map(|span| span.start + offset..span.end + offset).collect())
+    }
+
+    /// Returns the first alternative matching the normalized `input`, with
+    /// the byte ranges matched by each of its wildcards.
+    fn capture_spans(&self, input: &str) -> Option<(&Alternative, Vec<Range<usize>>)> {
+        if self.negated || self.rejects_input(input, self.options.windows) {
+            return None;
+        }
+
+        self.alternatives.iter().find_map(|alternative| {
+            let subject = self.subject(alternative, input);
+            if alternative.rejects(subject) {
+                return None;
+            }
+            let folded = self.options.nocase.then(|| FoldedInput::new(subject));
+            let target = folded.as_ref().map_or(subject, |folded| folded.text.as_str());
+
+            let mut spans = CaptureSpans::new();
+            if !match_expanded_pattern_with(target, &alternative.segments, &self.options, Some(&mut spans)) {
+                return None;
+            }
+            // キャプチャはマッチ成功時に後ろから積まれる
+            spans.reverse();
+            // キャプチャには**直後の/を含めない
+            trim_globstar_spans(&mut spans, &alternative.segments, &target.chars().collect::<Vec<_>>());
+            // match_base で照合するファイル名は入力の末尾にある
+            let subject_offset = input.len() - subject.len();
+            let char_offsets: Vec<usize> = subject
+                .char_indices()
+                .map(|(offset, _)| subject_offset + offset)
+                .chain(std::iter::once(input.len()))
+                .collect();
+            let spans = spans
+                .iter()
+                .map(|&(start, end)| {
+                    // 大文字小文字の折りたたみで文字数が変わるため、元の入力の範囲に戻す
+                    let (start, end) = folded
+                        .as_ref()
+                        .map_or((start, end), |folded| folded.original_span(start, end));
+                    char_offsets[start]..char_offsets[end]
+                })
+                .collect();
+            Some((alternative, spans))
+        })
+    }
+
+    /// Explains how `input` matches this pattern, or where each brace
+    /// alternative stopped matching, for debugging a pattern that does not
+    /// behave as expected.
+    ///
+    /// ```
+    /// use satch::{Pattern, TraceOutcome};
+    ///
+    /// let trace = Pattern::new("src/*/*.rs").explain("src/lib/util/mod.rs");
+    /// assert!(!trace.matched);
+    /// let TraceOutcome::Failed { offset, segment } = &trace.alternatives[0].outcome else { panic!() };
+    /// assert_eq!((*offset, segment.as_deref()), (12, Some(".rs")));
+    ///
+    /// let trace = Pattern::new("src/**/*.rs").explain("src/lib/mod.rs");
+    /// println!("{trace}");
+    /// // "src/lib/mod.rs": match
+    /// //   src/**/*.rs: src/ = "src/", **/ = "lib/", * = "mod", .rs = ".rs"
+    /// ```
+    pub fn explain(&self, input: &str) -> MatchTrace {
+        let input = self.normalize_input(input).into_owned();
+        if self.rejects_input(&input, self.options.windows) {
+            return MatchTrace {
+                input: self.unswap(input),
+                matched: false,
+                rejected: true,
+                alternatives: Vec::new(),
+            };
+        }
+
+        let mut alternatives = Vec::new();
+        let mut matched = false;
+        for alternative in self.alternatives.iter() {
+            let trace = self.explain_alternative(alternative, &input);
+            matched = matches!(trace.outcome, TraceOutcome::Matched(_));
+            alternatives.push(trace);
+            if matched {
+                break;
+            }
+        }
+        MatchTrace {
+            input: self.unswap(input),
+            matched: matched != self.negated,
+            rejected: false,
+            alternatives,
+        }
+    }
+
+    /// Puts back the separator swapped with `/` when the pattern or input
+    /// was compiled or normalized.
+    fn unswap(&self, text: String) -> String {
+        match swap_separator(&text, self.options.separator) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(swapped) => swapped,
+        }
+    }
+
+    fn explain_alternative(&self, alternative: &Alternative, input: &str) -> AlternativeTrace {
+        let subject = self.subject(alternative, input);
+        // match_base で照合するファイル名は入力の末尾にある
+        let subject_offset = input.len() - subject.len();
+        let folded = self.options.nocase.then(|| FoldedInput::new(subject));
+        let target = folded.as_ref().map_or(subject, |folded| folded.text.as_str());
+        // 照合した文字の範囲を入力のバイト位置に戻す
+        let char_offsets: Vec<usize> = subject
+            .char_indices()
+            .map(|(offset, _)| subject_offset + offset)
+            .chain(std::iter::once(input.len()))
+            .collect();
+        let byte_span = |start: usize, end: usize| {
+            let (start, end) = folded.as_ref().map_or((start, end), |folded| folded.original_span(start, end));
+            char_offsets[start]..char_offsets[end]
+        };
+
+        let mut spans = CaptureSpans::new();
+        let outcome = match trace_expanded_pattern(target, &alternative.segments, &self.options, Some(&mut spans)) {
+            Ok(()) => {
+                spans.reverse();
+                let mut wildcard_spans = spans.into_iter();
+                let mut position = 0;
+                let segment_spans = alternative
+                    .segments
+                    .iter()
+                    .map(|segment| {
+                        let (start, end) = match segment {
+                            GlobSegment::Literal(literal) => (position, position + literal.len()),
+                            _ => wildcard_spans.next().expect("the matcher records a span for each wildcard"),
+                        };
+                        position = end;
+                        SegmentSpan {
+                            segment: self.unswap(segment.to_string()),
+                            span: byte_span(start, end),
+                        }
+                    })
+                    .collect();
+                TraceOutcome::Matched(segment_spans)
+            }
+            Err(Mismatch::DotFile) => TraceOutcome::DotFile,
+            Err(Mismatch::Failed(input_idx, segment_idx)) => TraceOutcome::Failed {
+                offset: byte_span(input_idx, input_idx).start,
+                segment: alternative.segments.get(segment_idx).map(|segment| self.unswap(segment.to_string())),
+            },
+            Err(Mismatch::StepLimit) => TraceOutcome::StepLimitExceeded,
+        };
+        AlternativeTrace {
+            pattern: self.unswap(alternative.chars.iter().collect()),
+            outcome,
+        }
+    }
+
+    /// Returns what the matcher did during the most recent
+    /// [`is_match`](Pattern::is_match), [`matches_path`](Pattern::matches_path)
+    /// or [`is_match_candidate`](Pattern::is_match_candidate) call, or `None`
+    /// before the first. Clones of a pattern share these statistics, so
+    /// with several threads matching it is whichever call finished last.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("*a*a*a*b");
+    /// assert!(!pattern.is_match("aaaaaaaaaaaa/aab"));
+    /// let stats = pattern.last_stats().unwrap();
+    /// assert!(stats.backtracks > 0);
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn last_stats(&self) -> Option<MatchStats> {
+        self.stats.get()
+    }
+
+    /// Returns `true` if this pattern matches some part of `input` rather
+    /// than all of it, like micromatch's `contains`.
+    ///
+    /// Wildcards keep their meaning inside the matched part, so `*b` is
+    /// found in `aa/bb/cc` but `a*c` cannot span a `/`. A negated pattern
+    /// is true when no part of `input` matches the rest of the pattern.
+    ///
+    /// All parts are searched at once, so this takes time proportional to
+    /// the input length times the pattern length, like [`Pattern::is_match`].
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("*b");
+    /// assert!(pattern.contains("aa/bb/cc"));
+    /// assert!(!pattern.contains("aa/cc"));
+    /// ```
+    pub fn contains(&self, input: &str) -> bool {
+        let input = self.normalize_input(input);
+        if self.rejects_input(&input, self.options.windows) {
+            return false;
+        }
+
+        let found = self.alternatives.iter().any(|alternative| {
+            // match_base でもファイル名の部分は入力の部分でもあるので、入力全体から探す
+            if self.options.nocase {
+                find_expanded_pattern(&FoldedInput::new(&input).text, &alternative.segments, &self.options)
+            } else {
+                find_expanded_pattern(&input, &alternative.segments, &self.options)
+            }
+        });
+        found != self.negated
+    }
+
+    /// Returns the length in bytes of the longest prefix of `input` this
+    /// pattern matches in full, or `None` if no prefix matches.
+    ///
+    /// Prefixes are tried from the longest down, so this takes up to one
+    /// match per character of `input`. As in [`Pattern::match_spans`], the
+    /// length counts a leading `./` or Windows verbatim marker the matcher
+    /// skips.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("api/v[0-9]");
+    /// assert_eq!(pattern.match_prefix_len("api/v2/users/42"), Some(6));
+    /// assert_eq!(Pattern::new("api/*/").match_prefix_len("api/v2/users/42"), Some(7));
+    /// assert_eq!(pattern.match_prefix_len("app/v2"), None);
+    /// ```
+    pub fn match_prefix_len(&self, input: &str) -> Option<usize> {
+        let (normalized, offset) = self.normalize_input_with_offset(input);
+        let mut ends: Vec<usize> = normalized.char_indices().map(|(i, _)| i).chain([normalized.len()]).collect();
+        ends.reverse();
+        ends.into_iter()
+            .find(|&end| self.is_match_normalized(&normalized[..end], self.options.windows, None))
+            .map(|end| offset + end)
+    }
+
+    /// Returns whether each of `paths` matches this pattern, like calling
+    /// [`Pattern::is_match`] on each.
+    ///
+    /// Paths in the same directory share the work of matching it: the
+    /// directory is checked once, after which its paths are rejected
+    /// outright or only their file names are matched, where the pattern
+    /// allows it.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("src/*/*.rs");
+    /// let paths = ["src/a/lib.rs", "src/a/mod.rs", "docs/a/lib.rs", "src/a/b/c.rs"];
+    /// assert_eq!(pattern.match_paths(&paths), vec![true, true, false, false]);
+    /// ```
+    pub fn match_paths<S: AsRef<str>>(&self, paths: &[S]) -> Vec<bool> {
+        let mut frontiers: HashMap<String, DirFrontier> = HashMap::new();
+        paths
+            .iter()
+            .map(|path| {
+                let path = self.normalize_input(path.as_ref());
+                match split_dir(&path) {
+                    Some((dir, name)) => {
+                        if !frontiers.contains_key(dir) {
+                            frontiers.insert(dir.to_string(), self.dir_frontier(dir));
+                        }
+                        self.matches_in_dir(&frontiers[dir], &path, name)
+                    }
+                    None => self.is_match_normalized(&path, self.options.windows, None),
+                }
+            })
+            .collect()
+    }
+
+    /// Like [`Pattern::match_paths`], for `paths` sorted so that the paths
+    /// of a directory are next to each other, as in a sorted listing.
+    ///
+    /// Only the directory of the previous path is remembered, so consecutive
+    /// siblings just match their file names against it. Unsorted input
+    /// gives the same results, only without the speedup.
+    pub fn match_sorted<S: AsRef<str>>(&self, paths: &[S]) -> Vec<bool> {
+        let mut last: Option<(String, DirFrontier)> = None;
+        paths
+            .iter()
+            .map(|path| {
+                let path = self.normalize_input(path.as_ref());
+                let Some((dir, name)) = split_dir(&path) else {
+                    return self.is_match_normalized(&path, self.options.windows, None);
+                };
+                let (_, frontier) = match &mut last {
+                    Some(entry) if entry.0 == dir => entry,
+                    slot => slot.insert((dir.to_string(), self.dir_frontier(dir))),
+                };
+                self.matches_in_dir(frontier, &path, name)
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the filesystem `path` matches this pattern.
+    ///
+    /// The path is normalized first: components are joined with `/`
+    /// regardless of platform, `.` components are dropped, and non-UTF-8
+    /// components are converted lossily (invalid sequences become
+    /// `U+FFFD`, which only wildcards can match). Windows prefixes become
+    /// `C:` for drives and `//server/share` for UNC shares, with any `\\?\`
+    /// verbatim marker removed.
+    pub fn matches_path(&self, path: &Path) -> bool {
+        self.is_match_candidate(&Candidate::from_path(path))
+    }
+
+    /// Returns `true` if the raw bytes `input` match this pattern.
+    ///
+    /// Valid UTF-8 is matched as [`Pattern::is_match`] would. Each invalid
+    /// sequence, as split by [`String::from_utf8_lossy`], counts as a
+    /// single character for `?` and is matched only by wildcards and
+    /// negated classes. It is never equal to a character written in the
+    /// pattern, not even `U+FFFD`.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("caf?.txt");
+    /// assert!(pattern.is_match_bytes(b"caf\xe9.txt"));
+    /// assert!(!Pattern::new("cafe.txt").is_match_bytes(b"caf\xe9.txt"));
+    /// assert!(!Pattern::new("caf\u{FFFD}.txt").is_match_bytes(b"caf\xe9.txt"));
+    /// ```
+    pub fn is_match_bytes(&self, input: &[u8]) -> bool {
+        match std::str::from_utf8(input) {
+            Ok(input) => self.is_match(input),
+            Err(_) => self.is_match(&self.decode_invalid_utf8(input)),
+        }
+    }
+
+    /// Returns `true` if the platform string `input` matches this pattern.
+    ///
+    /// Unlike [`Pattern::matches_path`], the string is matched as it is,
+    /// without normalizing components. On Unix its bytes are matched as in
+    /// [`Pattern::is_match_bytes`]; on Windows an unpaired surrogate is
+    /// read as `U+FFFD`.
+    pub fn is_match_os_str(&self, input: &OsStr) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            self.is_match_bytes(input.as_bytes())
+        }
+        #[cfg(not(unix))]
+        {
+            self.is_match(&input.to_string_lossy())
+        }
+    }
+
+    /// Decodes `input` as [`String::from_utf8_lossy`] does, but writes each
+    /// invalid sequence as a character no literal or class of the pattern
+    /// names, so that only wildcards and negated classes match it.
+    fn decode_invalid_utf8(&self, input: &[u8]) -> String {
+        // U+FFFD か非文字のうち、パターンのどこにも書かれていないものを使う。
+        // 書かれていない文字は入力に元からあっても同じようにしかマッチしない
+        let stand_in = std::iter::once('\u{FFFD}')
+            .chain('\u{FDD0}'..='\u{FDEF}')
+            .find(|&c| {
+                !self.alternatives.iter().any(|alternative| {
+                    alternative.segments.iter().any(|segment| segment.mentions(c, &self.options))
+                })
+            })
+            .unwrap_or('\u{FFFD}');
+        let mut decoded = String::with_capacity(input.len());
+        for chunk in input.utf8_chunks() {
+            decoded.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                decoded.push(stand_in);
+            }
+        }
+        decoded
+    }
+
+    /// Returns `true` if the prepared `candidate` matches this pattern.
+    ///
+    /// A candidate marked with [`Candidate::dir`] is matched as in
+    /// [`Pattern::is_match_entry`].
+    pub fn is_match_candidate(&self, candidate: &Candidate<'_>) -> bool {
+        // UNCプレフィックス由来の先頭の // は常に許可する
+        let unc_root = self.options.windows || candidate.is_path();
+        self.is_match_normalized(&self.normalize_input(candidate.as_str()), unc_root, candidate.is_dir())
+    }
+
+    /// Returns, for each brace alternative, a literal that every input it
+    /// matches contains, or `None` when the pattern cannot be filtered
+    /// this way. A pattern that matches nothing has no literals.
+    pub(crate) fn required_literals(&self) -> Option<Vec<String>> {
+        // 否定・nocase・Unicode 正規化・\ の変換・独自の区切り文字ではパターンのリテラルが入力にそのまま現れるとは限らない
+        let options = &self.options;
+        if self.negated
+            || options.nocase
+            || options.normalize_unicode
+            || options.converts_backslashes()
+            || options.separator != '/'
+        {
+            return None;
+        }
+        self.alternatives
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .segments
+                    .iter()
+                    .filter_map(|segment| match segment {
+                        GlobSegment::Literal(literal) => Some(literal),
+                        _ => None,
+                    })
+                    .max_by_key(|literal| literal.len())
+                    .map(|literal| literal.iter().collect())
+            })
+            .collect()
+    }
+
+    /// Returns `false` only if no path below the directory `dir` can match
+    /// this pattern, so a walk can skip the whole subtree.
+    ///
+    /// The answer is conservative: `true` means a descendant might match,
+    /// not that one does. Negated patterns and patterns whose wildcards can
+    /// span several directories, such as `**`, give `true` once the
+    /// directories before them match.
+    ///
+    /// ```
+    /// use satch::Pattern;
+    ///
+    /// let pattern = Pattern::new("src/*/test/*.rs");
+    /// assert!(pattern.could_match_dir("src"));
+    /// assert!(pattern.could_match_dir("src/lib/test"));
+    /// assert!(!pattern.could_match_dir("docs"));
+    /// assert!(!pattern.could_match_dir("src/lib/bench"));
+    /// ```
+    pub fn could_match_dir(&self, dir: &str) -> bool {
+        let dir = self.normalize_input(dir);
+        let dir = dir.trim_end_matches('/');
+        if self.negated || dir.is_empty() {
+            return true;
+        }
+        let folded = self.options.nocase.then(|| FoldedInput::new(dir).text);
+        let components: Vec<&str> = folded.as_deref().unwrap_or(dir).split('/').collect();
+        self.alternatives
+            .iter()
+            .any(|alternative| self.alternative_could_match_dir(&alternative.chars, &components))
+    }
+
+    fn alternative_could_match_dir(&self, alternative: &[char], components: &[&str]) -> bool {
+        if self.options.match_base && !alternative.contains(&'/') {
+            return true;
+        }
+        let Some(segments) = split_path_segments(alternative, &self.options) else {
+            return true;
+        };
+        for (i, component) in components.iter().enumerate() {
+            let Some(segment) = segments.get(i) else {
+                return false;
+            };
+            // ** は複数のディレクトリにまたがるので以降は判定できない
+            if segment.windows(2).any(|pair| pair == ['*', '*']) {
+                return true;
+            }
+            // ドットファイルの規則はパスの先頭にだけ適用される
+            let options = if i == 0 {
+                self.options.clone()
+            } else {
+                self.options.clone().dot(true)
+            };
+            if !match_expanded_pattern(component, &parse_glob_segments(segment), &options) {
+                return false;
+            }
+        }
+        // 子孫にはディレクトリの後にもう一つ以上の要素がある
+        segments.len() > components.len()
+    }
+
+    /// Works out which alternatives the paths directly inside `dir` can
+    /// match. `dir` is normalized and split off with [`split_dir`].
+    pub(crate) fn dir_frontier(&self, dir: &str) -> DirFrontier {
+        let folded = self.options.nocase.then(|| FoldedInput::new(dir).text);
+        let components: Vec<&str> = folded.as_deref().unwrap_or(dir).split('/').collect();
+        let states = self
+            .alternatives
+            .iter()
+            .map(|alternative| {
+                // 否定パターンではディレクトリで絞り込めない
+                if self.negated {
+                    return DirState::Full;
+                }
+                if !self.alternative_could_match_dir(&alternative.chars, &components) {
+                    return DirState::Rejected;
+                }
+                if self.options.match_base && !alternative.chars.contains(&'/') {
+                    return DirState::Full;
+                }
+                match split_path_segments(&alternative.chars, &self.options) {
+                    // ** を含まなければ、各ディレクトリはパターンの同じ位置のセグメントにマッチしている
+                    Some(segments)
+                        if segments.len() == components.len() + 1
+                            && !segments.iter().any(|segment| segment.windows(2).any(|pair| pair == ['*', '*'])) =>
+                    {
+                        DirState::Name(parse_glob_segments(segments[components.len()]))
+                    }
+                    _ => DirState::Full,
+                }
+            })
+            .collect();
+        DirFrontier {
+            states,
+            name_options: self.options.clone().dot(true),
+        }
+    }
+
+    /// Matches a normalized `path` whose directory has `frontier` and
+    /// whose final component is `name`.
+    pub(crate) fn matches_in_dir(&self, frontier: &DirFrontier, path: &str, name: &str) -> bool {
+        if self.rejects_input(path, self.options.windows) {
+            return false;
+        }
+        let folded = self.options.nocase.then(|| FoldedInput::new(name).text);
+        let name = folded.as_deref().unwrap_or(name);
+        let matched = self
+            .alternatives
+            .iter()
+            .zip(&frontier.states)
+            .any(|(alternative, state)| match state {
+                DirState::Rejected => false,
+                DirState::Name(segments) => match_expanded_pattern(name, segments, &frontier.name_options),
+                DirState::Full => self.matches_alternative(alternative, path),
+            });
+        matched != self.negated
+    }
+
+    fn is_match_normalized(&self, input: &str, unc_root: bool, is_dir: Option<bool>) -> bool {
+        self.try_match_normalized(input, unc_root, is_dir, false) == Ok(true)
+    }
+
+    /// Matches a normalized input, which names a directory if `is_dir`
+    /// says so. An alternative that runs out of `max_steps` fails the whole
+    /// match when `stop_at_limit` is set, and does not match otherwise.
+    fn try_match_normalized(
+        &self,
+        input: &str,
+        unc_root: bool,
+        is_dir: Option<bool>,
+        stop_at_limit: bool,
+    ) -> Result<bool, StepLimitExceeded> {
+        #[cfg(feature = "stats")]
+        let _recorder = stats::Recorder::new(&self.stats);
+        if self.rejects_input(input, unc_root) {
+            return Ok(false);
+        }
+        // / で終わる候補はディレクトリの入力に / を補って照合する
+        let dir_input = match is_dir {
+            Some(true) if !input.ends_with('/') => Cow::Owned(format!("{input}/")),
+            _ => Cow::Borrowed(input),
+        };
+
+        // ブレース展開した各パターンのいずれかにマッチすれば一致
+        let mut matched = false;
+        for alternative in self.alternatives.iter() {
+            let input = match is_dir {
+                Some(false) if alternative.dir_only => continue,
+                Some(true) if alternative.dir_only => &dir_input,
+                _ => input,
+            };
+            match self.try_match_alternative(alternative, input) {
+                Ok(true) => {
+                    matched = true;
+                    break;
+                }
+                Err(error) if stop_at_limit => return Err(error),
+                Ok(false) | Err(_) => {}
+            }
+        }
+        Ok(matched != self.negated)
+    }
+
+    fn matches_alternative(&self, alternative: &Alternative, input: &str) -> bool {
+        self.try_match_alternative(alternative, input) == Ok(true)
+    }
+
+    fn try_match_alternative(&self, alternative: &Alternative, input: &str) -> Result<bool, StepLimitExceeded> {
+        let subject = self.subject(alternative, input);
+        if alternative.rejects(subject) {
+            return Ok(false);
+        }
+        let result = if self.options.nocase {
+            trace_expanded_pattern(&FoldedInput::new(subject).text, &alternative.segments, &self.options, None)
+        } else {
+            trace_expanded_pattern(subject, &alternative.segments, &self.options, None)
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(Mismatch::StepLimit) => Err(StepLimitExceeded::new(self.options.max_steps.unwrap_or_default())),
+            Err(Mismatch::DotFile | Mismatch::Failed(..)) => Ok(false),
+        }
+    }
+
+    /// Returns the part of `input` that `alternative` is matched against:
+    /// the final path component under `match_base` when the alternative
+    /// has no `/`, and the whole input otherwise.
+    fn subject<'a>(&self, alternative: &Alternative, input: &'a str) -> &'a str {
+        if self.options.match_base && !alternative.chars.contains(&'/') {
+            input.rsplit('/').next().unwrap_or(input)
+        } else {
+            input
+        }
+    }
+
+    /// Prepares `input` for the matcher: [`Pattern::normalize_separators`],
+    /// then NFC with `normalize_unicode`.
+    fn normalize_input<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        self.normalize_input_with_offset(input).0
+    }
+
+    /// Like [`Pattern::normalize_input`], also returning the number of
+    /// bytes removed from the start of `input`. Positions in the result
+    /// plus that offset are positions in `input`, unless NFC changed it.
+    fn normalize_input_with_offset<'a>(&self, input: &'a str) -> (Cow<'a, str>, usize) {
+        let separated = self.normalize_separators(input);
+        let offset = input.len() - separated.len();
+        if !self.options.normalize_unicode {
+            return (separated, offset);
+        }
+        let normalized = match separated {
+            Cow::Borrowed(separated) => to_nfc(separated),
+            Cow::Owned(separated) => Cow::Owned(to_nfc(&separated).into_owned()),
+        };
+        (normalized, offset)
+    }
+
+    /// In windows mode, strips verbatim (`\\?\`) and device (`\\.\`)
+    /// markers. In windows and `posix_slashes` modes, replaces `\\` with
+    /// `/`. A custom separator is swapped with `/`, and a leading `./` is
+    /// stripped. Only the start of `input` changes length, so the result
+    /// lines up with the end of `input`.
+    fn normalize_separators<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        let input = if self.options.windows {
+            strip_verbatim_prefix(input)
+        } else {
+            Cow::Borrowed(input)
+        };
+        let input = if self.options.converts_backslashes() && input.contains('\\') {
+            Cow::Owned(input.replace('\\', "/"))
+        } else {
+            input
+        };
+        let input = match input {
+            Cow::Borrowed(input) => swap_separator(input, self.options.separator),
+            Cow::Owned(input) => Cow::Owned(swap_separator(&input, self.options.separator).into_owned()),
+        };
+        if !self.options.strips_dot_slash() {
+            return input;
+        }
+        // 区切り文字をそろえてから ./ を取り除く
+        match input {
+            Cow::Borrowed(input) => Cow::Borrowed(strip_dot_slash(input)),
+            Cow::Owned(input) => Cow::Owned(strip_dot_slash(&input).to_string()),
+        }
+    }
+
+    /// With `unc_root`, a leading `//` is the root of a UNC path rather
+    /// than an empty segment.
+    fn rejects_input(&self, input: &str, unc_root: bool) -> bool {
+        let checked = if unc_root {
+            input.strip_prefix('/').unwrap_or(input)
+        } else {
+            input
+        };
+        // 連続スラッシュを含む入力は無効とする
+        self.options.strict_slashes && checked.contains("//")
+    }
+}
+
+/// Computes [`Pattern::normalized`] for `pattern`, compiled to `alternatives`.
+/// Without `drop_dots`, only the leading `./` that compiling strips is
+/// removed, so the result matches the same inputs as `pattern`.
+fn normalize_pattern(pattern: &str, options: &MatchOptions, alternatives: &[Alternative], drop_dots: bool) -> String {
+    if options.separator != '/' {
+        return pattern.to_string();
+    }
+    let (_, body) = split_negation(pattern);
+    let negation = &pattern[..pattern.len() - body.len()];
+    let stripped = if !drop_dots && options.strips_dot_slash() { strip_dot_slash(body) } else { body };
+    // noglobstar では ** が * と同じなので、繰り返しをまとめると一致する入力が変わる
+    let normalized = normalize_source(stripped, !options.noglobstar, drop_dots);
+    if normalized == body {
+        return pattern.to_string();
+    }
+    let normalized = format!("{negation}{normalized}");
+    // ** が1つになると src/**/*.js のように中間ディレクトリが必要になるので、その場合はまとめない
+    let requires_directory = |alternatives: &[Alternative]| {
+        alternatives.iter().any(|alternative| {
+            alternative
+                .segments
+                .iter()
+                .any(|segment| matches!(segment, GlobSegment::Globstar { requires_directory: true, .. }))
+        })
+    };
+    let recompiled = Pattern::compile_alternatives(&normalized, options);
+    if recompiled.is_ok_and(|(_, normalized)| requires_directory(&normalized)) && !requires_directory(alternatives) {
+        return format!("{negation}{}", normalize_source(stripped, false, drop_dots));
+    }
+    normalized
+}
+
+/// Removes, with `drop_dots`, `.` segments and, with `collapse_globstars`,
+/// repeated `**` segments from a pattern without its negation, and
+/// shortens runs of stars within segments.
+fn normalize_source(body: &str, collapse_globstars: bool, drop_dots: bool) -> String {
+    let chars: Vec<char> = body.chars().collect();
+    let segments = split_top_level_segments(&chars);
+    let has_other = |index: usize| segments.iter().enumerate().any(|(i, segment)| i != index && !segment.is_empty());
+    let mut normalized: Vec<String> = Vec::with_capacity(segments.len());
+    for (index, segment) in segments.iter().enumerate() {
+        if drop_dots && *segment == ['.'] && has_other(index) {
+            continue;
+        }
+        // 末尾の **/** は空文字列にマッチしないので ** にしない
+        let is_last = index + 1 == segments.len();
+        if collapse_globstars
+            && !is_last
+            && *segment == ['*', '*']
+            && normalized.last().is_some_and(|last| last == "**")
+        {
+            continue;
+        }
+        normalized.push(collapse_star_runs(segment));
+    }
+    normalized.join("/")
+}
+
+/// Splits a pattern at the `/` outside classes, braces and extglobs.
+fn split_top_level_segments(pattern: &[char]) -> Vec<&[char]> {
+    let delimiters = Delimiters::new(pattern);
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            '[' => {
+                if let Some(class_end) = delimiters.class_end(i) {
+                    i = class_end;
+                }
+            }
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth = depth.saturating_sub(1),
+            '/' if depth == 0 => {
+                segments.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    segments.push(&pattern[start..]);
+    segments
+}
+
+/// Shortens runs of three or more `*` inside a segment to `**`. Runs at
+/// either end of a segment, or next to a brace or extglob, are kept, as
+/// `***/a` needs a `/` where `**/a` does not.
+fn collapse_star_runs(segment: &[char]) -> String {
+    let delimiters = Delimiters::new(segment);
+    let mut collapsed = String::with_capacity(segment.len());
+    let mut i = 0;
+    while i < segment.len() {
+        let end = match segment[i] {
+            '\\' => (i + 2).min(segment.len()),
+            '[' => delimiters.class_end(i).map_or(i + 1, |class_end| class_end + 1),
+            '*' => {
+                let end = i + segment[i..].iter().take_while(|&&c| c == '*').count();
+                // 要素の端の ** は / を含む区切りとして扱われ、ブレースや extglob に接すると展開後に端になりうる
+                let inner = i > 0
+                    && end < segment.len()
+                    && !matches!(segment[i - 1], '}' | ')' | '|' | ',')
+                    && !matches!(segment[end], '{' | '(' | '|' | ',')
+                    && delimiters.extglob(end).is_none();
+                if end - i >= 3 && inner {
+                    collapsed.push_str("**");
+                    i = end;
+                    continue;
+                }
+                end
+            }
+            _ => i + 1,
+        };
+        collapsed.extend(&segment[i..end]);
+        i = end;
+    }
+    collapsed
+}
+
+/// Splits a normalized path into its directory and final component, or
+/// returns `None` when the path has no directory that can be matched on
+/// its own.
+pub(crate) fn split_dir(path: &str) -> Option<(&str, &str)> {
+    // 連続スラッシュはディレクトリの要素に分けられない
+    path.rsplit_once('/')
+        .filter(|(dir, _)| !dir.is_empty() && !dir.contains("//") && !dir.ends_with('/'))
+}
+
+/// Splits a brace-free pattern at the `/` separating path segments, or
+/// returns `None` when an extglob or character class could match a `/`
+/// itself.
+fn split_path_segments<'a>(pattern: &'a [char], options: &MatchOptions) -> Option<Vec<&'a [char]>> {
+    let delimiters = Delimiters::new(pattern);
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => {
+                // エスケープされた / も区切りとして扱う
+                if pattern.get(i + 1) == Some(&'/') {
+                    segments.push(&pattern[start..i]);
+                    start = i + 2;
+                }
+                i += 2;
+                continue;
+            }
+            '[' => {
+                if let Some(class_end) = delimiters.class_end(i) {
+                    // [^a] のような文字クラスは / 自体にマッチする
+                    if class_matches_slash(&pattern[i + 1..class_end], options) {
+                        return None;
+                    }
+                    i = class_end + 1;
+                    continue;
+                }
+            }
+            '/' => {
+                segments.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {
+                if let Some((kind, close)) = delimiters.extglob(i) {
+                    if kind == ExtglobKind::Negated || pattern[i..close].contains(&'/') {
+                        return None;
+                    }
+                    i = close + 1;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    segments.push(&pattern[start..]);
+    Some(segments)
+}
+
+/// Removes a `\\?\` or `\\.\` marker, turning `\\?\UNC\server` into
+/// `\\server`.
+fn strip_verbatim_prefix(input: &str) -> Cow<'_, str> {
+    let is_separator = |b: u8| b == b'\\' || b == b'/';
+    let bytes = input.as_bytes();
+    if bytes.len() < 4 || !is_separator(bytes[0]) || !is_separator(bytes[1]) || !is_separator(bytes[3]) {
+        return Cow::Borrowed(input);
+    }
+    if bytes[2] != b'?' && bytes[2] != b'.' {
+        return Cow::Borrowed(input);
+    }
+
+    let rest = &input[4..];
+    match rest.get(..4) {
+        Some(unc) if unc.as_bytes()[..3].eq_ignore_ascii_case(b"UNC") && is_separator(unc.as_bytes()[3]) => {
+            Cow::Owned(format!("//{}", &rest[4..]))
+        }
+        _ => Cow::Borrowed(rest),
+    }
+}
+
+/// Converts `path` to the `/`-separated form the matcher expects.
+pub(crate) fn normalize_path(path: &Path) -> String {
+    let mut normalized = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => {
+                    normalized.push(char::from(drive));
+                    normalized.push(':');
+                }
+                Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                    normalized.push_str("//");
+                    normalized.push_str(&server.to_string_lossy());
+                    normalized.push('/');
+                    normalized.push_str(&share.to_string_lossy());
+                }
+                Prefix::Verbatim(name) | Prefix::DeviceNS(name) => {
+                    normalized.push_str(&name.to_string_lossy());
+                }
+            },
+            Component::RootDir => normalized.push('/'),
+            Component::CurDir => {}
+            Component::ParentDir => push_component(&mut normalized, ".."),
+            Component::Normal(name) => push_component(&mut normalized, &name.to_string_lossy()),
+        }
+    }
+    normalized
+}
+
+fn push_component(normalized: &mut String, name: &str) {
+    if !normalized.is_empty() && !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+    normalized.push_str(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PatternErrorKind, WorstCase};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_pattern_matches_like_is_match() {
+        let pattern = Pattern::new("src/*.{js,ts}");
+        assert!(pattern.is_match("src/a.js"));
+        assert!(pattern.is_match("src/a.ts"));
+        assert!(!pattern.is_match("src/a.rs"));
+        assert_eq!(pattern.as_str(), "src/*.{js,ts}");
+    }
+
+    #[test]
+    fn test_clones_share_compiled_pattern() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Pattern>();
+
+        let pattern = Pattern::new("src/**/*.{js,ts}");
+        let clone = pattern.clone();
+        assert!(Arc::ptr_eq(&pattern.alternatives, &clone.alternatives));
+        assert!(Arc::ptr_eq(&pattern.source, &clone.source));
+        let handle = std::thread::spawn(move || clone.is_match("src/a/b.ts"));
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_equality_and_ordering() {
+        use std::collections::{BTreeSet, HashSet};
+
+        assert_eq!(Pattern::new("*.rs"), Pattern::new("*.rs"));
+        assert_ne!(Pattern::new("*.rs"), Pattern::with_options("*.rs", &MatchOptions::new().dot(true)));
+        assert!(Pattern::new("a") < Pattern::new("b"));
+
+        // 正規化すると同じになるパターンは等しい
+        for (a, b) in [("./a", "a"), ("**/**/a", "**/a"), ("a***b", "a**b"), ("!./a", "!a")] {
+            assert_eq!(Pattern::new(a), Pattern::new(b));
+            assert_eq!(Pattern::new(a).cmp(&Pattern::new(b)), Ordering::Equal);
+            let set: HashSet<Pattern> = [a, b].into_iter().map(Pattern::new).collect();
+            assert_eq!(set.len(), 1);
+        }
+        // . を除くと一致する入力が変わるので等しくない
+        assert_ne!(Pattern::new("a/./b"), Pattern::new("a/b"));
+        assert_ne!(Pattern::new("a/."), Pattern::new("a"));
+        assert_ne!(Pattern::new("src/**/**/*.js"), Pattern::new("src/**/*.js"));
+        assert_ne!(
+            Pattern::with_options("./a", &MatchOptions::new().strip_dot_slash(false)),
+            Pattern::with_options("a", &MatchOptions::new().strip_dot_slash(false))
+        );
+
+        // 等しいパターンは同じ入力にマッチする
+        let patterns = [
+            "a", "./a", "a/./b", "a/b", "./a/./b", "a/.", "**/**/a", "**/a", "**/./**/a", "a***b", "a**b",
+            "src/**/**/*.js", "src/**/*.js", "!./a", "!a",
+        ];
+        let inputs = ["a", "a/b", "a/./b", "./a", "a/.", "x/a", "x/y/a", "./x/a", "axb", "src/main.js", "src/x/m.js"];
+        for a in patterns {
+            for b in patterns {
+                let (a, b) = (Pattern::new(a), Pattern::new(b));
+                if a == b {
+                    for input in inputs {
+                        assert_eq!(a.is_match(input), b.is_match(input), "{a} {b} {input}");
+                    }
+                }
+            }
+        }
+
+        let set: HashSet<Pattern> = ["*.rs", "*.md", "*.rs"].into_iter().map(Pattern::new).collect();
+        assert_eq!(set.len(), 2);
+        let sorted: Vec<String> = ["b/*", "a/*", "c"]
+            .into_iter()
+            .map(Pattern::new)
+            .collect::<BTreeSet<_>>()
+            .iter()
+            .map(|pattern| pattern.as_str().to_string())
+            .collect();
+        assert_eq!(sorted, ["a/*", "b/*", "c"]);
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let pattern: Pattern = "!src/{a,b}/*.rs".parse().unwrap();
+        assert_eq!(pattern.to_string(), "!src/{a,b}/*.rs");
+        assert!(pattern.is_negated());
+        assert_eq!(format!("{}", Pattern::new("[abc")), "[abc");
+        let error = "a\\".parse::<Pattern>().unwrap_err();
+        assert_eq!(error.kind(), &PatternErrorKind::DanglingEscape);
+    }
+
+    #[test]
+    fn test_normalized() {
+        let normalized = |pattern: &str| Pattern::new(pattern).normalized();
+        assert_eq!(normalized("a/./b"), "a/b");
+        assert_eq!(normalized("./a/."), "a");
+        assert_eq!(normalized("."), ".");
+        assert_eq!(normalized("/./a"), "/a");
+        assert_eq!(normalized("**/**/a/**/**/**"), "**/a/**/**");
+        assert_eq!(normalized("a***b/***/x****y"), "a**b/***/x**y");
+        assert_eq!(normalized("x****/a***{b,c}"), "x****/a***{b,c}");
+        assert_eq!(normalized("a***(b)"), "a***(b)");
+        assert_eq!(normalized("a\\***b/[*]***c"), "a\\***b/[*]**c");
+        assert_eq!(normalized("@(a/./b|c)/./d"), "@(a/./b|c)/d");
+        assert_eq!(normalized("!!./a"), "!!a");
+        // ** を1つにすると src/main.js にマッチしなくなる
+        assert_eq!(normalized("src/**/**/*.js"), "src/**/**/*.js");
+        assert_eq!(normalized("**/**/*.js"), "**/*.js");
+
+        for pattern in ["src/**/**/*.js", "a***b", "**/**/x/**/**", "**/**/a", "x****"] {
+            let original = Pattern::new(pattern);
+            let normalized = Pattern::new(&original.normalized());
+            for path in ["src/main.js", "src/a/b.js", "a/b", "axb", "ax/yb", "x", "p/x/q", "a", "q/a", "x/y"] {
+                assert_eq!(original.is_match(path), normalized.is_match(path), "{pattern} {path}");
+            }
+        }
+
+        let options = MatchOptions::new().noglobstar(true);
+        let pattern = Pattern::with_options("**/**/a", &options);
+        assert_eq!(pattern.normalized(), "**/**/a");
+        assert!(pattern.is_match("x/y/a"));
+        assert!(!pattern.is_match("x/a"));
+    }
+
+    #[test]
+    fn test_literal() {
+        let literal = |pattern: &str| Pattern::new(pattern).literal();
+        assert_eq!(literal("src/main.rs"), Some("src/main.rs".to_string()));
+        assert_eq!(literal("a\\[b\\]\\*"), Some("a[b]*".to_string()));
+        assert_eq!(literal("a{b}"), Some("a{b}".to_string()));
+        assert_eq!(literal(""), Some(String::new()));
+        assert_eq!(literal("a{b,c}"), None);
+        assert_eq!(literal("!a"), None);
+        assert_eq!(literal("a//b"), None);
+        assert_eq!(Pattern::with_options("A.txt", &MatchOptions::new().nocase(true)).literal(), None);
+        assert_eq!(Pattern::with_options("a.txt", &MatchOptions::new().match_base(true)).literal(), None);
+        assert!(!Pattern::new("a.txt").has_magic());
+        assert!(Pattern::new("*.txt").has_magic());
+
+        for pattern in ["src/main.rs", "a\\[b\\]\\*", "a{b}", "./a", "a/"] {
+            let compiled = Pattern::new(pattern);
+            let literal = compiled.literal().unwrap();
+            assert!(compiled.is_match(&literal), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_is_superset_of() {
+        let superset = |a: &str, b: &str| Pattern::new(a).is_superset_of(&Pattern::new(b));
+        assert!(superset("**/*.js", "src/**/*.js"));
+        assert!(superset("**", "src/*.js"));
+        assert!(superset("src/*", "src/[a-z].{js,ts}"));
+        assert!(superset("*.[a-z]s", "*.{js,ts}"));
+        assert!(superset("a/**/b", "a/x/b"));
+        assert!(superset("!*.md", "!*.{md,txt}"));
+        assert!(superset("!*.md", "*.js"));
+        assert!(!superset("*.js", "src/*.js"));
+        assert!(!superset("src/*.js", "**/*.js"));
+        assert!(!superset("*.js", "*.js?"));
+        // ドットファイルの規則: * は .eslintrc.js にマッチしない
+        assert!(!superset("*.js", ".eslintrc.js"));
+        assert!(superset("*.js", "a.js"));
+        // src/**/*.js は src/main.js にマッチしないので src/**/**/*.js より狭い
+        assert!(superset("src/**/**/*.js", "src/**/*.js"));
+        assert!(!superset("src/**/*.js", "src/*.js"));
+
+        // extglob は同じパターン同士でしか比べない
+        assert!(superset("@(a|b)", "@(a|b)"));
+        assert!(!superset("@(a|b|c)", "@(a|b)"));
+        let dot = MatchOptions::new().dot(true);
+        assert!(Pattern::with_options("*.js", &dot).is_superset_of(&Pattern::new(".eslintrc.js")));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let overlaps = |a: &str, b: &str| Pattern::new(a).overlaps(&Pattern::new(b));
+        assert!(overlaps("src/**/*.js", "**/test/*"));
+        assert!(overlaps("*.{js,ts}", "[a-z]*.ts"));
+        assert!(overlaps("!*.js", "!*.ts"));
+        assert!(!overlaps("*.js", "*.ts"));
+        assert!(!overlaps("src/*", "lib/*"));
+        assert!(!overlaps("src/*", "!src/**"));
+        assert!(!overlaps("*", "*/*"));
+        // ドットファイルの規則があるので * と .* は重ならない
+        assert!(!overlaps("*", ".*"));
+        assert!(overlaps("a*", "[!b]*"));
+        // strict_slashes では // を含む入力はどちらにもマッチしない
+        assert!(!overlaps("a/*/b", "a//b"));
+        let loose = MatchOptions::new().strict_slashes(false);
+        assert!(Pattern::with_options("a/*/b", &loose).overlaps(&Pattern::with_options("a//b", &loose)));
+
+        // 比較できないパターンは重なりうるものとして扱う
+        assert!(overlaps("@(a|b)", "c"));
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        let prefix = |pattern: &str| Pattern::new(pattern).literal_prefix();
+        assert_eq!(prefix("src/components/**/*.tsx"), "src/components/");
+        assert_eq!(prefix("src/lib/*.rs"), "src/lib/");
+        assert_eq!(prefix("src/main.rs"), "src/");
+        assert_eq!(prefix("main.rs"), "");
+        assert_eq!(prefix("**/*.rs"), "");
+        assert_eq!(prefix("/usr/*.h"), "/usr/");
+        assert_eq!(prefix("/*.h"), "/");
+        assert_eq!(prefix("a/b{c,d}/e"), "a/");
+        assert_eq!(prefix("a/b\\*/*.rs"), "a/b*/");
+        assert_eq!(prefix("!src/*.rs"), "");
+        assert_eq!(Pattern::with_options("src/*.rs", &MatchOptions::new().nocase(true)).literal_prefix(), "");
+    }
+
+    #[test]
+    fn test_literal_prefix_and_suffix_filter() {
+        let alternative = |pattern: &str| {
+            let pattern = Pattern::new(pattern);
+            let alternative = &pattern.alternatives[0];
+            (alternative.prefix.clone(), alternative.suffix.clone())
+        };
+        assert_eq!(alternative("src/**/*.js"), ("src/".into(), ".js".into()));
+        assert_eq!(alternative("**/*.j\\*"), ("".into(), ".j*".into()));
+        assert_eq!(alternative("main.rs"), ("main.rs".into(), "".into()));
+        assert_eq!(alternative("a*"), ("a".into(), "".into()));
+
+        let pattern = Pattern::new("ab*ba");
+        assert!(pattern.is_match("abba"));
+        // 前置部分と後置部分は重ならない
+        assert!(!pattern.is_match("aba") && !pattern.is_match("ab"));
+        assert!(!pattern.is_match("abab"));
+        assert_eq!(pattern.captures("abxba"), Some(vec!["x".into()]));
+        assert!(Pattern::with_options("*.JS", &MatchOptions::new().nocase(true)).is_match("a.js"));
+    }
+
+    #[test]
+    fn test_match_paths_shares_directories() {
+        let pattern = Pattern::new("src/{*,lib/*}/*.rs");
+        let paths = ["src/a/x.rs", "src/a/y.js", "src/lib/m/z.rs", "docs/a/x.rs", "main.rs", "src//x.rs"];
+        let expected: Vec<bool> = paths.iter().map(|path| pattern.is_match(path)).collect();
+        assert_eq!(pattern.match_paths(&paths), expected);
+        assert_eq!(expected, vec![true, false, true, false, false, false]);
+
+        let frontier = pattern.dir_frontier("src/a");
+        assert!(matches!(frontier.states[..], [DirState::Name(_), DirState::Rejected]));
+        assert!(matches!(pattern.dir_frontier("docs").states[..], [DirState::Rejected, DirState::Rejected]));
+        assert!(matches!(Pattern::new("src/**/*.rs").dir_frontier("src/a").states[..], [DirState::Full]));
+        assert_eq!(split_dir("a/b/c"), Some(("a/b", "c")));
+        assert_eq!(split_dir("a//c"), None);
+
+        let pattern = Pattern::with_options("SRC/*.RS", &MatchOptions::new().nocase(true));
+        assert_eq!(pattern.match_paths(&["src/Lib.rs", "src/lib.js"]), vec![true, false]);
+        assert_eq!(Pattern::new("!src/*.rs").match_paths(&["src/a.rs", "src/a.js"]), vec![false, true]);
+    }
+
+    #[test]
+    fn test_match_sorted() {
+        let pattern = Pattern::new("src/*/*.rs");
+        let mut paths = vec!["src/b/x.rs", "src/a/y.rs", "src/a/b/z.rs", "src/a/x.js", "src/a/w.rs", "a.rs"];
+        let expected: Vec<bool> = paths.iter().map(|path| pattern.is_match(path)).collect();
+        // 並んでいなくても結果は変わらない
+        assert_eq!(pattern.match_sorted(&paths), expected);
+        paths.sort();
+        let expected: Vec<bool> = paths.iter().map(|path| pattern.is_match(path)).collect();
+        assert_eq!(pattern.match_sorted(&paths), expected);
+        assert_eq!(expected, vec![false, false, true, false, true, true]);
+    }
+
+    #[test]
+    fn test_could_match_dir() {
+        let could_match = |pattern: &str, dir: &str| Pattern::new(pattern).could_match_dir(dir);
+        assert!(could_match("src/*.rs", "src"));
+        assert!(!could_match("src/*.rs", "src/lib"));
+        assert!(!could_match("src/*.rs", "lib"));
+        assert!(could_match("src/**/*.rs", "src/a/b/c"));
+        assert!(!could_match("src/**/*.rs", "lib/a"));
+        assert!(could_match("*/x", "a"));
+        assert!(!could_match("*/x", ".git"));
+        assert!(could_match("a/*/x", "a/.git"));
+        assert!(could_match("{src,lib}/*.rs", "lib/"));
+        assert!(could_match("a/@(b|c)/d", "a/c"));
+        // [^a] は / にもマッチするので x/y/z.rs にマッチしうる
+        assert!(could_match("x[^a]y/*.rs", "x"));
+        assert!(!could_match("a/@(b|c)/d", "a/e"));
+        assert!(could_match("a/@(b/c|d)/e", "a/b"));
+        assert!(could_match("a/[/]/b", "a"));
+        assert!(could_match("!src/*.rs", "lib/a"));
+        assert!(could_match("a/b\\/c/*", "a/b/c"));
+        assert!(could_match("*.rs", ""));
+        assert!(Pattern::with_options("SRC/*.rs", &MatchOptions::new().nocase(true)).could_match_dir("src"));
+        assert!(Pattern::with_options("*.rs", &MatchOptions::new().match_base(true)).could_match_dir("src/a"));
+    }
+
+    #[test]
+    fn test_could_match_dir_for_every_parent() {
+        let cases = [
+            ("a*?(a)/b", "a/b"),
+            ("x*@(y|)/*/z", "x/q/z"),
+            ("src/**/*.rs", "src/a/b/c.rs"),
+            ("*/@(b|c)/d", "a/c/d"),
+            ("a/*!(x)/b", "a/y/b"),
+            ("{a,b*?(.d)}/c", "b/c"),
+        ];
+        for (glob, path) in cases {
+            let pattern = Pattern::new(glob);
+            assert!(pattern.is_match(path), "{} ~ {}", glob, path);
+            // 一致するパスの親ディレクトリはどれも枝刈りされない
+            for (slash, _) in path.match_indices('/') {
+                assert!(pattern.could_match_dir(&path[..slash]), "{} under {}", glob, &path[..slash]);
+            }
+            assert_eq!(pattern.match_paths(&[path]), [true], "{}", glob);
+            assert_eq!(pattern.match_sorted(&[path]), [true], "{}", glob);
+        }
+    }
+
+    #[test]
+    fn test_dir_only_alternatives() {
+        let pattern = Pattern::new("{build,dist/*}/");
+        assert!(pattern.is_dir_only());
+        assert!(pattern.is_match_entry("build", true));
+        assert!(pattern.is_match_entry("build/", true));
+        assert!(pattern.is_match_entry("dist/web", true));
+        assert!(!pattern.is_match_entry("build", false));
+        assert!(!pattern.is_match_entry("build/", false));
+        assert!(!pattern.is_match_entry("dist", true));
+
+        // ヒントのない照合では従来どおり末尾の / で区別する
+        assert!(pattern.is_match("build/"));
+        assert!(!pattern.is_match("build"));
+
+        // / で終わらない候補はヒントに関係なくマッチする
+        let pattern = Pattern::new("{logs/,*.log}");
+        assert!(!pattern.is_dir_only());
+        assert!(pattern.is_match_entry("logs", true));
+        assert!(!pattern.is_match_entry("logs", false));
+        assert!(pattern.is_match_entry("a.log", false));
+        assert!(pattern.is_match_entry("a.log", true));
+
+        // 否定はディレクトリの判定の後に適用する
+        let pattern = Pattern::new("!build/");
+        assert!(!pattern.is_dir_only());
+        assert!(!pattern.is_match_entry("build", true));
+        assert!(pattern.is_match_entry("build", false));
+        assert!(pattern.is_match_entry("src", true));
+
+        let pattern = Pattern::with_options("src/*/", &MatchOptions::new().windows(true));
+        assert!(pattern.is_match_entry("src\\lib", true));
+        assert!(!pattern.is_match_entry("src\\lib.rs", false));
+    }
+
+    #[test]
+    fn test_negated_pattern() {
+        let pattern = Pattern::new("!*.js");
+        assert!(pattern.is_match("main.rs"));
+        assert!(!pattern.is_match("main.js"));
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(Pattern::try_new("src/*.{js,ts}").unwrap().is_match("src/a.ts"));
+        assert_eq!(Pattern::try_new("src/*.{js,ts").unwrap_err().kind(), &PatternErrorKind::UnclosedBrace);
+        // 寛容なコンストラクタは同じパターンをリテラルとして扱う
+        assert!(Pattern::new("src/*.{js,ts").is_match("src/a.{js,ts"));
+    }
+
+    #[test]
+    fn test_windows_separators() {
+        let options = MatchOptions::new().windows(true);
+        let pattern = Pattern::with_options("src/**/*.rs", &options);
+        assert!(pattern.is_match("src\\lib\\mod.rs"));
+        assert!(pattern.is_match("src/lib\\mod.rs"));
+        assert!(!Pattern::new("src/**/*.rs").is_match("src\\lib\\mod.rs"));
+        assert_eq!(pattern.captures("src\\a\\b\\c.rs"), Some(vec!["a/b".into(), "c".into()]));
+        // 連続した区切り文字は \ を含めて判定する
+        assert!(!Pattern::with_options("a/**/b", &options).is_match("a\\\\b"));
+    }
+
+    #[test]
+    fn test_windows_drive_and_unc_prefixes() {
+        let options = MatchOptions::new().windows(true);
+        let matches = |pattern: &str, input: &str| Pattern::with_options(pattern, &options).is_match(input);
+        assert!(matches("**/*.rs", "C:\\src\\main.rs"));
+        assert!(matches("C:/src/*.rs", "C:\\src\\main.rs"));
+        assert!(matches("C:/src/*.rs", "\\\\?\\C:\\src\\main.rs"));
+        assert!(matches("**/*.txt", "\\\\server\\share\\file.txt"));
+        assert!(matches("//server/share/*.txt", "\\\\server\\share\\file.txt"));
+        assert!(matches("//server/share/*.txt", "\\\\?\\UNC\\server\\share\\file.txt"));
+        assert!(matches("COM1", "\\\\.\\COM1"));
+        // 先頭以外の連続した区切り文字は従来どおり拒否する
+        assert!(!matches("**/*.txt", "\\\\\\server\\file.txt"));
+        assert!(!matches("**/*.txt", "C:\\\\file.txt"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_normalize_windows_path_prefixes() {
+        assert_eq!(normalize_path(Path::new(r"C:\src\main.rs")), "C:/src/main.rs");
+        assert_eq!(normalize_path(Path::new(r"\\?\C:\src\main.rs")), "C:/src/main.rs");
+        assert_eq!(normalize_path(Path::new(r"\\server\share\file.txt")), "//server/share/file.txt");
+        assert_eq!(normalize_path(Path::new(r"\\?\UNC\server\share\file.txt")), "//server/share/file.txt");
+        assert!(Pattern::new("**/*.txt").matches_path(Path::new(r"\\server\share\file.txt")));
+    }
+
+    #[test]
+    fn test_match_base() {
+        let options = MatchOptions::new().match_base(true);
+        let pattern = Pattern::with_options("*.rs", &options);
+        assert!(pattern.is_match("src/lib/mod.rs"));
+        assert!(pattern.is_match("mod.rs"));
+        assert!(!pattern.is_match("src/mod.rs/x"));
+        assert_eq!(pattern.captures("src/lib/mod.rs"), Some(vec!["mod".into()]));
+        // / を含むパターンはパス全体に対してマッチする
+        assert!(!Pattern::with_options("lib/*.rs", &options).is_match("src/lib/mod.rs"));
+        // ブレース展開後の代替パターンごとに判定する
+        let pattern = Pattern::with_options("{*.md,docs/*.txt}", &options);
+        assert!(pattern.is_match("a/b/README.md"));
+        assert!(!pattern.is_match("a/docs/x.txt"));
+        assert!(pattern.is_match("docs/x.txt"));
+        // ドットファイルの規則はbasenameに適用される
+        assert!(!pattern.is_match("a/.hidden.md"));
+    }
+
+    #[test]
+    fn test_nocase_unicode_case_folding() {
+        let options = MatchOptions::new().nocase(true);
+        let matches = |pattern: &str, input: &str| Pattern::with_options(pattern, &options).is_match(input);
+        assert!(matches("STRASSE.TXT", "straße.txt"));
+        assert!(matches("straße.*", "STRASSE.TXT"));
+        assert!(matches("ΣΟΦΙΑ/*.md", "σοφια/README.md"));
+        assert!(matches("σοφιας.txt", "ΣΟΦΙΑΣ.TXT"));
+        assert!(matches("Москва/**", "МОСКВА/a/b"));
+        assert!(!matches("STRASSE.TXT", "strase.txt"));
+        // キャプチャは元の入力の表記のまま返す
+        let pattern = Pattern::with_options("*/STRASSE-*.txt", &options);
+        assert_eq!(pattern.captures("Weg/Straße-Ä.TXT"), Some(vec!["Weg".into(), "Ä".into()]));
+    }
+
+    fn captures(pattern: &str, input: &str) -> Option<Vec<String>> {
+        Pattern::new(pattern).captures(input)
+    }
+
+    #[test]
+    fn test_captures_wildcards() {
+        assert_eq!(captures("test/*.js", "test/foo.js"), Some(vec!["foo".into()]));
+        assert_eq!(captures("*.*", "a.b"), Some(vec!["a".into(), "b".into()]));
+        assert_eq!(captures("file?.[a-z]s", "file1.js"), Some(vec!["1".into(), "j".into()]));
+        assert_eq!(captures("x*", "x"), Some(vec!["".into()]));
+        assert_eq!(captures("test/*.js", "test/foo.ts"), None);
+    }
+
+    #[test]
+    fn test_captures_globstars() {
+        assert_eq!(captures("**/*.rs", "src/lib/mod.rs"), Some(vec!["src/lib".into(), "mod".into()]));
+        assert_eq!(captures("**/*.rs", "mod.rs"), Some(vec!["".into(), "mod".into()]));
+        assert_eq!(captures("src/**", "src/a/b"), Some(vec!["a/b".into()]));
+        assert_eq!(
+            captures("**/test/**/*.js", "a/b/test/c/d.js"),
+            Some(vec!["a/b".into(), "c".into(), "d".into()])
+        );
+    }
+
+    #[test]
+    fn test_captures_extglobs_and_braces() {
+        assert_eq!(captures("@(foo|bar).*", "bar.js"), Some(vec!["bar".into(), "js".into()]));
+        assert_eq!(captures("*.{js,ts}", "app.ts"), Some(vec!["app".into()]));
+    }
+
+    #[test]
+    fn test_negated_pattern_has_no_captures() {
+        assert_eq!(captures("!*.js", "main.rs"), None);
+    }
+
+    #[test]
+    fn test_named_captures() {
+        let options = MatchOptions::new().named_captures(true);
+        let named = |pattern: &str, input: &str| {
+            let captures = Pattern::with_options(pattern, &options).named_captures(input)?;
+            let mut captures: Vec<(String, String)> = captures.into_iter().collect();
+            captures.sort();
+            Some(captures)
+        };
+        let pair = |name: &str, text: &str| (name.to_string(), text.to_string());
+        assert_eq!(
+            named("releases/{version}/notes-*.md", "releases/2.0/notes-ja.md"),
+            Some(vec![pair("version", "2.0")])
+        );
+        assert_eq!(
+            named("{owner}/{repo}/**/*.rs", "rust-lang/cargo/src/lib.rs"),
+            Some(vec![pair("owner", "rust-lang"), pair("repo", "cargo")])
+        );
+        // `*` と同じく1つのセグメントだけを取る
+        assert_eq!(named("a/{x}/b", "a/y/z/b"), None);
+        // ブレース展開の選択肢ごとにワイルドカードの位置が違っても名前を保つ
+        assert_eq!(named("{*.txt,*/{page}}", "docs/intro"), Some(vec![pair("page", "intro")]));
+        assert_eq!(named("{*.txt,*/{page}}", "a.txt"), Some(vec![]));
+        // セグメント全体でない、または名前でない波括弧はそのまま
+        assert_eq!(named("v{x}/{1a}/{}", "v{x}/{1a}/{}"), Some(vec![]));
+        assert_eq!(named("\\{x}/[{]x}", "{x}/{x}"), Some(vec![]));
+        // 位置によるキャプチャにも含まれる
+        let pattern = Pattern::with_options("src/{module}/*.rs", &options);
+        assert_eq!(pattern.captures("src/net/tcp.rs"), Some(vec!["net".into(), "tcp".into()]));
+        // オプションなしではリテラル
+        assert!(Pattern::new("a/{x}").is_match("a/{x}"));
+        assert_eq!(Pattern::new("a/{x}").named_captures("a/{x}"), Some(HashMap::new()));
+        let separated = MatchOptions::new().named_captures(true).separator(':');
+        let pattern = Pattern::with_options("user:{id}:name", &separated);
+        assert_eq!(pattern.named_captures("user:a/b:name").unwrap()["id"], "a/b");
+    }
+
+    #[test]
+    fn test_match_prefix_len() {
+        let pattern = Pattern::new("src/*");
+        assert_eq!(pattern.match_prefix_len("src/lib/mod.rs"), Some(7));
+        assert_eq!(pattern.match_prefix_len("src/main.rs"), Some(11));
+        assert_eq!(pattern.match_prefix_len("src"), None);
+        assert_eq!(pattern.match_prefix_len("./src/lib"), Some(9));
+        // 最長の前置部分を返す
+        assert_eq!(Pattern::new("a*").match_prefix_len("aaa/b"), Some(3));
+        assert_eq!(Pattern::new("**").match_prefix_len("a/b/c"), Some(5));
+        assert_eq!(Pattern::new("*/").match_prefix_len("a/b/c"), Some(2));
+        assert_eq!(Pattern::new("\u{e9}?").match_prefix_len("\u{e9}\u{e9}x"), Some(4));
+        assert_eq!(Pattern::new("x").match_prefix_len(""), None);
+        assert_eq!(Pattern::new("").match_prefix_len("abc"), Some(0));
+        // 否定パターンは元のパターンに一致しない最長の前置部分
+        assert_eq!(Pattern::new("!*.rs").match_prefix_len("main.rs"), Some(6));
+    }
+
+    #[test]
+    fn test_match_spans() {
+        let spans = |pattern: &str, input: &str, options: &MatchOptions| {
+            // 範囲は比べやすいよう (start, end) の組にする
+            let spans = Pattern::with_options(pattern, options).match_spans(input)?;
+            Some(spans.into_iter().map(|span| (span.start, span.end)).collect::<Vec<_>>())
+        };
+        let default = MatchOptions::new();
+        assert_eq!(spans("file?.[a-z]s", "file1.js", &default), Some(vec![(4, 5), (6, 7)]));
+        assert_eq!(spans("**/*.rs", "mod.rs", &default), Some(vec![(0, 0), (0, 3)]));
+        assert_eq!(spans("@(foo|bar).*", "bar.js", &default), Some(vec![(0, 3), (4, 6)]));
+        assert_eq!(spans("!*.js", "main.rs", &default), None);
+        // 位置は文字数ではなくバイト数
+        assert_eq!(spans("*/*.txt", "\u{65e5}\u{672c}/\u{e9}.txt", &default), Some(vec![(0, 6), (7, 9)]));
+        // 取り除かれる先頭部分も入力の位置に含める
+        assert_eq!(spans("*.rs", "././main.rs", &default), Some(vec![(4, 8)]));
+        let windows = MatchOptions::new().windows(true);
+        assert_eq!(spans("C:/*/*.rs", "\\\\?\\C:\\src\\main.rs", &windows), Some(vec![(7, 10), (11, 15)]));
+        assert_eq!(spans("*.rs", "src/main.rs", &MatchOptions::new().match_base(true)), Some(vec![(4, 8)]));
+        // 大文字小文字を無視しても元の入力の位置を返す
+        assert_eq!(spans("stra*e", "STRASSE", &MatchOptions::new().nocase(true)), Some(vec![(4, 6)]));
+        assert_eq!(spans("a.*", "a.b:c", &MatchOptions::new().separator('.')), Some(vec![(2, 5)]));
+    }
+
+    fn explained_spans(pattern: &Pattern, input: &str) -> Vec<(String, String)> {
+        let trace = pattern.explain(input);
+        let Some(AlternativeTrace {
+            outcome: TraceOutcome::Matched(spans),
+            ..
+        }) = trace.alternatives.last()
+        else {
+            panic!("{input} does not match {pattern}");
+        };
+        spans
+            .iter()
+            .map(|span| (span.segment.clone(), trace.input[span.span.clone()].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_explain_matched_spans() {
+        let spans = |pattern: &str, input: &str| explained_spans(&Pattern::new(pattern), input);
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs.iter().map(|&(a, b)| (a.to_string(), b.to_string())).collect()
+        };
+        assert_eq!(
+            spans("src/**/*.rs", "src/lib/mod.rs"),
+            pairs(&[("src/", "src/"), ("**/", "lib/"), ("*", "mod"), (".rs", ".rs")])
+        );
+        assert_eq!(spans("**/*.js", "a.js"), pairs(&[("**/", ""), ("*", "a"), (".js", ".js")]));
+        assert_eq!(spans("a/**/", "a/b/c"), pairs(&[("a/", "a/"), ("**/", "b/c")]));
+        assert_eq!(
+            spans("[a-c]?@(x|y)\\*", "bzy*"),
+            pairs(&[("[a-c]", "b"), ("?", "z"), ("@(x|y)", "y"), ("\\*", "*")])
+        );
+        assert_eq!(spans("*.{js,ts}", "é.ts"), pairs(&[("*", "é"), (".ts", ".ts")]));
+
+        let base = Pattern::with_options("*.rs", &MatchOptions::new().match_base(true));
+        assert_eq!(explained_spans(&base, "src/main.rs"), pairs(&[("*", "main"), (".rs", ".rs")]));
+        let nocase = Pattern::with_options("STRASSE/*", &MatchOptions::new().nocase(true));
+        assert_eq!(explained_spans(&nocase, "Straße/x"), pairs(&[("strasse/", "Straße/"), ("*", "x")]));
+
+        let trace = Pattern::new("*.{md,txt}").explain("notes.txt");
+        assert!(trace.matched);
+        assert_eq!(trace.alternatives.len(), 2);
+        assert!(matches!(trace.alternatives[0].outcome, TraceOutcome::Failed { .. }));
+    }
+
+    #[test]
+    fn test_explain_failures() {
+        let outcome = |pattern: &str, input: &str| {
+            let trace = Pattern::new(pattern).explain(input);
+            assert!(!trace.matched);
+            trace.alternatives[0].outcome.clone()
+        };
+        let failed = |offset: usize, segment: Option<&str>| TraceOutcome::Failed {
+            offset,
+            segment: segment.map(str::to_string),
+        };
+        assert_eq!(outcome("src/*/*.rs", "src/lib/util/mod.rs"), failed(12, Some(".rs")));
+        assert_eq!(outcome("src/*.rs", "src/main.rs.bak"), failed(11, None));
+        assert_eq!(outcome("src/*.rs", "lib/main.rs"), failed(0, Some("src/")));
+        assert_eq!(outcome("*.rs", ".build.rs"), TraceOutcome::DotFile);
+
+        let trace = Pattern::new("a/*").explain("a//b");
+        assert!(trace.rejected && trace.alternatives.is_empty());
+
+        let pattern = Pattern::with_options("+(a|aa)b", &MatchOptions::new().max_steps(100));
+        let trace = pattern.explain(&"a".repeat(100));
+        assert_eq!(trace.alternatives[0].outcome, TraceOutcome::StepLimitExceeded);
+
+        // 否定パターンは内側のパターンの照合結果を示す
+        let trace = Pattern::new("!*.rs").explain("main.rs");
+        assert!(!trace.matched);
+        assert!(matches!(trace.alternatives[0].outcome, TraceOutcome::Matched(_)));
+
+        let text = Pattern::new("src/*.rs").explain("lib/main.rs").to_string();
+        assert_eq!(text, "\"lib/main.rs\": no match\n  src/*.rs: src/ does not match \"lib/main.rs\" at byte 0");
+    }
+
+    #[test]
+    fn test_complexity() {
+        let complexity = Pattern::new("src/main.rs").complexity();
+        assert_eq!(complexity.alternatives, 1);
+        assert_eq!(complexity.segments, 1);
+        assert_eq!(complexity.worst_case, WorstCase::Linear);
+        assert_eq!(Pattern::new("[a-z]?.rs").complexity().worst_case, WorstCase::Linear);
+
+        let complexity = Pattern::new("{a,b}/**/{c,d,e}/**").complexity();
+        assert_eq!(complexity.alternatives, 6);
+        assert_eq!(complexity.globstars, 12);
+        assert_eq!(complexity.segments, 4);
+        assert_eq!(complexity.extglob_depth, 0);
+        assert_eq!(complexity.worst_case, WorstCase::Backtracking);
+
+        // extglob 内のセグメントも数える
+        let complexity = Pattern::new("a/@(b*|c)").complexity();
+        assert_eq!(complexity.segments, 5);
+        assert_eq!(complexity.extglob_depth, 1);
+        assert_eq!(complexity.worst_case, WorstCase::Extglob);
+
+        // 制限を超えたパターンは何にもマッチしないので照合の負担もない
+        let options = MatchOptions::new().max_segments(2);
+        assert_eq!(Pattern::with_options("a/*/b/*", &options).complexity(), Complexity::default());
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_last_stats() {
+        let pattern = Pattern::new("*a*a*a*b");
+        assert_eq!(pattern.last_stats(), None);
+
+        assert!(!pattern.is_match("aaaaaaaaaaaa/aab"));
+        let slow = pattern.last_stats().unwrap();
+        assert!(slow.steps > 0 && slow.backtracks > 0);
+        assert!(slow.memo_hits > 0 && slow.peak_memo_size > 0);
+        assert_eq!(slow.branches, slow.memo_hits + slow.memo_misses);
+
+        // 他のパターンの照合は数えず、クローンとは統計を共有する
+        assert!(!Pattern::new("src/*.rs").is_match("lib/a.rs"));
+        assert!(pattern.clone().is_match("aaab"));
+        let fast = pattern.last_stats().unwrap();
+        assert!(fast.steps > 0 && fast.steps < slow.steps);
+
+        // リテラルの後置部分で弾かれた入力は探索しない
+        assert!(!pattern.is_match("aaaaaaaaaaaaaaaac"));
+        assert_eq!(pattern.last_stats(), Some(MatchStats::default()));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("./src/main.rs")), "src/main.rs");
+        assert_eq!(normalize_path(Path::new("src//lib/./mod.rs")), "src/lib/mod.rs");
+        assert_eq!(normalize_path(Path::new("/usr/lib")), "/usr/lib");
+        assert_eq!(normalize_path(Path::new("../a")), "../a");
+        assert_eq!(normalize_path(Path::new("dir/")), "dir");
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let pattern = Pattern::new("src/**/*.rs");
+        assert!(pattern.matches_path(Path::new("src/lib/mod.rs")));
+        assert!(pattern.matches_path(&PathBuf::from("./src/lib/mod.rs")));
+        assert!(!pattern.matches_path(Path::new("tests/mod.rs")));
+    }
+
+    #[test]
+    fn test_is_match_bytes() {
+        let pattern = Pattern::new("docs/*.txt");
+        assert!(pattern.is_match_bytes(b"docs/readme.txt"));
+        assert!(pattern.is_match_bytes(b"docs/caf\xe9.txt"));
+        assert!(!pattern.is_match_bytes(b"docs/caf\xe9/a.txt"));
+        // 不正な並びはそれぞれ1文字として ? にマッチする
+        assert!(Pattern::new("a?b").is_match_bytes(b"a\xe3\x81b"));
+        assert!(!Pattern::new("a?b").is_match_bytes(b"a\xff\xfeb"));
+        assert!(Pattern::new("a??b").is_match_bytes(b"a\xff\xfeb"));
+        assert!(Pattern::new("a[!x]b").is_match_bytes(b"a\x80b"));
+        assert!(!Pattern::new("a[a-z]b").is_match_bytes(b"a\x80b"));
+        assert!(Pattern::new("\u{e9}").is_match_bytes(b"\xc3\xa9"));
+        // 不正な並びはパターンに書いた U+FFFD とは別の文字
+        assert!(!Pattern::new("a\u{FFFD}b").is_match_bytes(b"a\xffb"));
+        assert!(Pattern::new("a\u{FFFD}b").is_match_bytes("a\u{FFFD}b".as_bytes()));
+        assert!(!Pattern::new("a[\u{FFFD}]b").is_match_bytes(b"a\xffb"));
+        assert!(Pattern::new("a[!\u{FFFD}]b").is_match_bytes(b"a\xffb"));
+        assert!(!Pattern::new("a[\u{FFF0}-\u{FFFF}\u{FDD0}]b").is_match_bytes(b"a\xffb"));
+        assert!(Pattern::new("\u{FFFD}*").is_match_bytes(b"\xef\xbf\xbd\xff"));
+        assert!(!Pattern::new("*\u{FFFD}").is_match_bytes(b"\xef\xbf\xbd\xff"));
+        assert!(!Pattern::new("@(x|\u{FFFD})").is_match_bytes(b"\xff"));
+        let options = MatchOptions::new().nocase(true);
+        assert!(!Pattern::with_options("a\u{FFFD}b", &options).is_match_bytes(b"A\xffB"));
+        assert!(Pattern::with_options("a?b", &options).is_match_bytes(b"A\xffB"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_match_os_str() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        assert!(Pattern::new("*.txt").is_match_os_str(name));
+        assert!(Pattern::new("caf?.txt").is_match_os_str(name));
+        assert!(!Pattern::new("cafe.txt").is_match_os_str(name));
+        assert!(!Pattern::new("caf\u{FFFD}.txt").is_match_os_str(name));
+        // パスとしての正規化はしない
+        assert!(!Pattern::new("a/b").is_match_os_str(OsStr::new("a//b")));
+        assert!(Pattern::new("a/b").matches_path(Path::new("a//b")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_matches_path_with_non_utf8_component() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        let path = Path::new("docs").join(name);
+        assert!(Pattern::new("docs/*.txt").matches_path(&path));
+        assert!(!Pattern::new("docs/cafe.txt").matches_path(&path));
+    }
+}
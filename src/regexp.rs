@@ -0,0 +1,468 @@
+//! Conversion of glob patterns to regular expressions.
+//!
+//! The generated expression mirrors [`is_match_with`](crate::is_match_with)
+//! rule for rule, including the dotfile check and the rejection of inputs
+//! with consecutive slashes. Those rules, negated patterns and `!(...)`
+//! extglobs are expressed with lookaheads, so the output targets
+//! backtracking engines (JavaScript, PCRE, `fancy-regex`, ...).
+
+use crate::brace::expand_braces;
+use crate::class::{class_items, split_class_negation, ClassItem, PosixClass};
+use crate::extglob::ExtglobKind;
+use crate::{
+    collapse_globstars, parse_glob_segments, replace_named_placeholders, rewrite_syntax, split_negation,
+    star_fills_segment, strip_dot_slash, swap_separator, GlobSegment, MatchOptions,
+};
+
+/// Any single character, including newlines.
+const ANY_CHAR: &str = r"[\s\S]";
+/// A character that never matches.
+const NO_CHAR: &str = r"[^\s\S]";
+
+/// Converts a glob pattern into an equivalent regular expression.
+///
+/// The expression is anchored at both ends and accepts exactly the inputs
+/// [`is_match_with`](crate::is_match_with) accepts with the same `options`,
+/// except that `\\?\` verbatim prefixes are not stripped in windows mode and
+/// `nocase` relies on the regex engine's simple case folding. `?` always
+/// matches one `char`, even with [`graphemes`](MatchOptions::graphemes), and
+/// [`normalize_unicode`](MatchOptions::normalize_unicode) normalizes only the
+/// pattern, so inputs must be normalized before they are matched.
+/// A `!(...)` extglob is translated with a lookahead that cannot always pin
+/// down the text it excludes, so ambiguous or nested negations (`!(a)*`)
+/// may yield a stricter expression than the matcher.
+///
+/// ```
+/// use satch::{to_regex, MatchOptions};
+///
+/// let regex = to_regex("src/*.{js,ts}", &MatchOptions::new().strict_slashes(false));
+/// assert_eq!(regex, r"^(?:\./)*(?!\./)(?:src/(?=[\s\S])[^/]*\.js|src/(?=[\s\S])[^/]*\.ts)$");
+/// ```
+pub fn to_regex(pattern: &str, options: &MatchOptions) -> String {
+    let converter = Converter::new(options);
+    let rewritten = rewrite_syntax(pattern, options);
+    let (negated, body) = split_negation(&rewritten);
+    // 照合と同じく区切り文字を / と入れ替えてから変換する
+    let body = swap_separator(body, options.separator);
+    let alternatives: Vec<String> = expand_braces(&body)
+        .iter()
+        .map(|expanded| {
+            let expanded = if options.strips_dot_slash() { strip_dot_slash(expanded) } else { expanded };
+            let mut chars: Vec<char> = expanded.chars().collect();
+            if options.named_captures {
+                chars = replace_named_placeholders(&chars).0;
+            }
+            if options.noglobstar || options.strict_globstar {
+                converter.alternative_to_regex(&collapse_globstars(&chars, options.noglobstar))
+            } else {
+                converter.alternative_to_regex(&chars)
+            }
+        })
+        .collect();
+    let alternatives = alternatives.join("|");
+
+    // 大文字小文字を無視する場合はフラグを付ける
+    let mut regex = String::from(if options.nocase { "(?i)^" } else { "^" });
+    if options.strips_dot_slash() {
+        // 先頭の ./ はすべて読み飛ばす。後ろの否定先読みで、一部だけ残す解釈を防ぐ
+        regex.push_str(&format!(r"(?:\.{sep})*(?!\.{sep})", sep = converter.separator));
+    }
+    if options.strict_slashes {
+        // 連続スラッシュを含む入力は無効とする（Windowsでは先頭のUNCルートを除く）
+        let quantifier = if options.windows { '+' } else { '*' };
+        regex.push_str(&format!("(?!{}{}{sep}{sep})", ANY_CHAR, quantifier, sep = converter.separator));
+    }
+    if negated {
+        regex.push_str(&format!("(?!(?:{})$){}*", alternatives, ANY_CHAR));
+    } else {
+        regex.push_str(&format!("(?:{})", alternatives));
+    }
+    regex.push('$');
+    regex
+}
+
+/// Compiles a glob pattern into a [`fancy_regex::Regex`] via [`to_regex`].
+///
+/// Requires the `regex` feature.
+#[cfg(feature = "regex")]
+pub fn make_regex(pattern: &str, options: &MatchOptions) -> Result<fancy_regex::Regex, fancy_regex::Error> {
+    fancy_regex::Regex::new(&to_regex(pattern, options))
+}
+
+/// Emits regex fragments for the separators selected by the options.
+///
+/// The pattern it converts has its custom separator swapped with `/`, so
+/// `/` in it stands for the separator and the separator for a literal `/`.
+struct Converter {
+    /// Matches a single path separator.
+    separator: String,
+    /// Matches a single character other than a separator.
+    segment_char: String,
+    /// The separators as class items, for excluding them from a class.
+    separator_items: String,
+    /// The custom separator, which stands for a literal `/` in the pattern.
+    swapped: Option<char>,
+    converts_backslashes: bool,
+    dot: bool,
+    match_base: bool,
+    class_bang: bool,
+    star_matches_empty: bool,
+}
+
+impl Converter {
+    fn new(options: &MatchOptions) -> Self {
+        let swapped = (options.separator != '/').then_some(options.separator);
+        let separator_items = match swapped {
+            Some(separator) => {
+                let mut items = String::new();
+                push_class_char(separator, &mut items);
+                items
+            }
+            // 入力の \ は / として扱われる
+            None if options.converts_backslashes() => r"/\\".to_string(),
+            None => "/".to_string(),
+        };
+        let separator = match swapped {
+            Some(separator) => {
+                let mut escaped = String::new();
+                push_escaped(separator, &mut escaped);
+                escaped
+            }
+            None if options.converts_backslashes() => format!("[{separator_items}]"),
+            None => "/".to_string(),
+        };
+        Self {
+            separator,
+            segment_char: format!("[^{separator_items}]"),
+            separator_items,
+            swapped,
+            converts_backslashes: options.converts_backslashes(),
+            dot: options.dot,
+            match_base: options.match_base,
+            class_bang: options.class_bang,
+            star_matches_empty: options.star_matches_empty,
+        }
+    }
+
+    fn alternative_to_regex(&self, pattern: &[char]) -> String {
+        let mut regex = String::new();
+        if self.match_base && !pattern.contains(&'/') {
+            // 最後の区切り文字までを読み飛ばし、basenameだけにマッチさせる
+            regex.push_str(&format!(
+                "(?:{any}*{sep})?(?={seg}*$)",
+                any = ANY_CHAR,
+                sep = self.separator,
+                seg = self.segment_char
+            ));
+        }
+        // ドットファイルのチェック: パターンが*で始まる場合、入力は.で始まらない
+        if !self.dot && pattern.first() == Some(&'*') {
+            // 区切り文字が . なら、入れ替えたパターンの . は入力の / にあたる
+            regex.push_str("(?!");
+            self.push_literal('.', &mut regex);
+            regex.push(')');
+        }
+
+        self.convert_segments(&parse_glob_segments(pattern), false, (true, true), &mut regex);
+        regex
+    }
+
+    /// Converts parsed segments following the rules of the matcher.
+    ///
+    /// With `within_segment`, no part of the expression may match `/` (used for
+    /// extglob alternatives that cannot cross a separator). `bounds` tells
+    /// whether the start and end of `segments` are path boundaries.
+    fn convert_segments(&self, segments: &[GlobSegment], within_segment: bool, bounds: (bool, bool), regex: &mut String) {
+        let any = if within_segment { self.segment_char.as_str() } else { ANY_CHAR };
+        for (index, segment) in segments.iter().enumerate() {
+            let rest = &segments[index..];
+            if matches!(segment, GlobSegment::Wildcard | GlobSegment::Globstar { .. })
+                && !rest.iter().all(GlobSegment::is_star)
+                && !(segment.is_star() && rest.iter().any(|rest| matches!(rest, GlobSegment::Extglob { .. })))
+            {
+                // 入力が尽きた時点で残りのパターンが * だけでなければマッチしない（extglob が残る場合を除く）
+                regex.push_str(&format!("(?={})", ANY_CHAR));
+            }
+
+            match segment {
+                GlobSegment::Literal(literal) => literal.iter().for_each(|&c| self.push_literal(c, regex)),
+                GlobSegment::AnyChar => regex.push_str(&self.segment_char),
+                GlobSegment::Wildcard => {
+                    // パスの1区間全体を占める * が空文字列にマッチしない場合は1文字以上
+                    let empty = self.star_matches_empty || !star_fills_segment(segments, index, bounds.0, bounds.1);
+                    regex.push_str(&self.segment_char);
+                    regex.push(if empty { '*' } else { '+' });
+                }
+                GlobSegment::Globstar { slash, requires_directory } => {
+                    // パターン末尾の ** は残りの入力全てにマッチする
+                    if index + 1 == segments.len() {
+                        regex.push_str(&format!("{}*", any));
+                        return;
+                    }
+                    if *slash {
+                        // **/ は / を含む範囲を消費する（条件によっては空でもよい）
+                        let spans_directory = format!("{any}*{sep}{any}*", any = any, sep = self.separator);
+                        if *requires_directory {
+                            regex.push_str(&spans_directory);
+                        } else {
+                            regex.push_str(&format!("(?:{})?", spans_directory));
+                        }
+                    } else {
+                        regex.push_str(&format!("{}*", any));
+                    }
+                }
+                GlobSegment::CharClass(content) => self.push_class(content, within_segment, regex),
+                GlobSegment::Extglob { kind, alternatives, crosses_separator } => {
+                    let inner_within_segment = within_segment || !crosses_separator;
+                    let alternatives: Vec<String> = alternatives
+                        .iter()
+                        .map(|alt| {
+                            let mut converted = String::new();
+                            self.convert_segments(alt, inner_within_segment, (false, false), &mut converted);
+                            converted
+                        })
+                        .collect();
+                    let mut rest = String::new();
+                    self.convert_segments(&segments[index + 1..], within_segment, (false, bounds.1), &mut rest);
+                    self.push_extglob(*kind, &alternatives, &rest, inner_within_segment, regex);
+                    regex.push_str(&rest);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn push_extglob(&self, kind: ExtglobKind, alternatives: &[String], rest: &str, within_segment: bool, regex: &mut String) {
+        let group = format!("(?:{})", alternatives.join("|"));
+        match kind {
+            ExtglobKind::ExactlyOne => regex.push_str(&group),
+            ExtglobKind::ZeroOrOne => regex.push_str(&format!("{}?", group)),
+            ExtglobKind::OneOrMore => regex.push_str(&format!("{}+", group)),
+            ExtglobKind::ZeroOrMore => regex.push_str(&format!("{}*", group)),
+            ExtglobKind::Negated => {
+                // 代替パターンのいずれかと残りのパターンで入力を消費できる位置では失敗させる
+                let span = if within_segment { self.segment_char.as_str() } else { ANY_CHAR };
+                regex.push_str(&format!("(?:(?!{}{}$){}*?)", group, rest, span));
+            }
+        }
+    }
+
+    fn push_class(&self, content: &[char], within_segment: bool, regex: &mut String) {
+        let (negated, content) = split_class_negation(content, self.class_bang);
+
+        let mut items = String::new();
+        for item in class_items(content) {
+            match item {
+                // 逆順の範囲はどの文字にもマッチしない
+                ClassItem::Range(start, end) if start <= end => {
+                    push_class_char(start, &mut items);
+                    items.push('-');
+                    push_class_char(end, &mut items);
+                }
+                ClassItem::Range(..) => {}
+                ClassItem::Char(c) => self.push_class_item(c, &mut items),
+                ClassItem::Posix(class) => items.push_str(posix_class_items(class)),
+            }
+        }
+
+        match (negated, items.is_empty()) {
+            (false, true) => regex.push_str(NO_CHAR),
+            (false, false) => regex.push_str(&format!("[{}]", items)),
+            (true, _) if within_segment => regex.push_str(&format!("[^{}{}]", self.separator_items, items)),
+            (true, true) => regex.push_str(ANY_CHAR),
+            (true, false) => regex.push_str(&format!("[^{}]", items)),
+        }
+    }
+
+    fn push_class_item(&self, c: char, items: &mut String) {
+        match c {
+            '/' => items.push_str(&self.separator_items),
+            c if Some(c) == self.swapped => items.push_str(if self.converts_backslashes { r"/\\" } else { "/" }),
+            // 入力の \ は / に置き換わるため、パターン中の \ にはマッチしない
+            '\\' if self.converts_backslashes => {}
+            c => push_class_char(c, items),
+        }
+    }
+
+    fn push_literal(&self, c: char, regex: &mut String) {
+        match c {
+            '/' => regex.push_str(&self.separator),
+            c if Some(c) == self.swapped => regex.push_str(if self.converts_backslashes { r"[/\\]" } else { "/" }),
+            '\\' if self.converts_backslashes => regex.push_str(NO_CHAR),
+            c => push_escaped(c, regex),
+        }
+    }
+}
+
+fn push_class_char(c: char, items: &mut String) {
+    if "\\[]^-&~".contains(c) {
+        items.push('\\');
+    }
+    items.push(c);
+}
+
+/// The regex class items matching the same characters as [`PosixClass::contains`].
+fn posix_class_items(class: PosixClass) -> &'static str {
+    match class {
+        PosixClass::Alpha => r"\p{Alphabetic}",
+        PosixClass::Digit => "0-9",
+        PosixClass::Alnum => r"\p{Alphabetic}\p{N}",
+        PosixClass::Space => r"\s",
+        PosixClass::Upper => r"\p{Uppercase}",
+        PosixClass::Lower => r"\p{Lowercase}",
+        PosixClass::Punct => r"!-/:-@\[-`\{-\~",
+        PosixClass::Xdigit => "0-9A-Fa-f",
+    }
+}
+
+fn push_escaped(c: char, regex: &mut String) {
+    if "\\.+*?()|[]{}^$".contains(c) {
+        regex.push('\\');
+    }
+    regex.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_match_with;
+
+    const CORPUS: &[(&str, &[&str])] = &[
+        ("*.js", &["a.js", ".a.js", "a/b.js", "a.jsx", ".js"]),
+        ("src/*.rs", &["src/main.rs", "src/lib/main.rs", "main.rs"]),
+        ("c?t", &["cat", "ct", "c/t"]),
+        ("src/*", &["src/", "src/a", "src/a/"]),
+        ("./*.rs", &["a.rs", "./a.rs", "././a.rs", "./.a.rs", ".//a.rs"]),
+        (".*/a", &["./a", "./x/a", ".x/a"]),
+        ("a/*/*", &["a/x/", "a/x/y", "a//y"]),
+        ("a/@(*|x)/b", &["a//b", "a/x/b", "a/y/b"]),
+        ("**/*.js", &["a.js", "a/b.js", "a/b/c.js", "a/b/c.ts"]),
+        ("src/**/*.js", &["src/main.js", "src/a/main.js", "src/a/b/main.js"]),
+        ("src/**/main.js", &["src/main.js", "src/a/main.js", "lib/main.js"]),
+        ("src/**", &["src/a", "src/a/b", "src", "lib/a"]),
+        ("**/test/**/*.js", &["a/test/b/c.js", "test/c.js", "a/test/c.js", "a/b/c.js"]),
+        ("**/**/nested/**/*.txt", &["a/nested/b/c.txt", "nested/c.txt", "a/b.txt"]),
+        ("**/?.js", &["a/b/?.js", "a/b/x.js"]),
+        ("[a-c]*.txt", &["apple.txt", "dog.txt", "b.txt"]),
+        ("file[^0-9].log", &["filea.log", "file1.log", "file/.log"]),
+        ("[z-a]", &["a", "z"]),
+        ("[abc", &["[abc", "a"]),
+        ("[-]x", &["-x", "ax"]),
+        ("[]a][^]]", &["]x", "a]", "b]", "]]"]),
+        ("[!a-c][!]]", &["dx", "ax", "d]", "!x"]),
+        ("[\\]\\-][a\\-z]", &["]-", "-z", "]m", "a-"]),
+        ("foo\\*.txt", &["foo*.txt", "foobar.txt"]),
+        ("\\[a\\]\\?{\\,,x}", &["[a]?,", "[a]?x", "a?,", "[a]b,"]),
+        ("**/a\\**/**/b", &["x/a*/y/b", "x/ab/y/b"]),
+        ("[[:alpha:]_][[:alnum:]]", &["a1", "_b", "1a", "é9"]),
+        ("x[^[:digit:][:space:]]", &["xa", "x1", "x ", "x/"]),
+        ("*.{js,ts}", &["a.js", "a.ts", "a.rs"]),
+        ("file{1..3}.txt", &["file2.txt", "file4.txt"]),
+        ("@(foo|bar).js", &["foo.js", "bar.js", "baz.js"]),
+        ("file?(.min).js", &["file.js", "file.min.js", "file.min.min.js"]),
+        ("+([0-9]).txt", &["123.txt", ".txt", "12a.txt"]),
+        ("v*([0-9]).txt", &["v.txt", "v12.txt", "vx.txt"]),
+        ("!(foo).js", &["foo.js", "bar.js", "foobar.js"]),
+        ("!(node_modules)/**/*.js", &["src/a/b.js", "node_modules/a/b.js"]),
+        ("**/@(src|lib)/**/*.js", &["a/src/b/c.js", "a/test/b/c.js"]),
+        ("!*.js", &["a.js", "a.rs", "a//b"]),
+        ("a/**/b", &["a//b", "a/x/b"]),
+        ("", &["", "a"]),
+        ("a**", &["a/x", "x/a/b", "x/ab"]),
+        ("a**b/**/c**", &["a/b/x/c", "axb/y/cz", "ab/c", "a/xb/c"]),
+        ("{*.md,docs/*.txt}", &["a/b/README.md", "a/docs/x.txt", "docs/x.txt", "a/.x.md"]),
+        ("x*?(a)", &["x", "xa", "xba", "x/a"]),
+        ("r/{version}/{a,b}{x}", &["r/1.0/a{x}", "r/{version}/b{x}", "r/.v/a{x}", "r/a{x}"]),
+    ];
+
+    #[test]
+    fn test_regex_agrees_with_matcher() {
+        for options in [
+            MatchOptions::default(),
+            MatchOptions::new().strict_slashes(false),
+            MatchOptions::new().dot(true),
+            MatchOptions::new().match_base(true),
+            MatchOptions::new().class_bang(false),
+            MatchOptions::new().strict_globstar(true),
+            MatchOptions::new().noglobstar(true),
+            MatchOptions::new().nobrace(true).noext(true).nonegate(true),
+            MatchOptions::new().extended_classes(true),
+            MatchOptions::new().named_captures(true),
+            MatchOptions::new().star_matches_empty(false),
+            MatchOptions::new().strip_dot_slash(false),
+            MatchOptions::new().posix_slashes(true),
+            MatchOptions::new().separator('.'),
+            MatchOptions::new().separator(':').strict_slashes(false),
+        ] {
+            for (pattern, inputs) in CORPUS {
+                let source = to_regex(pattern, &options);
+                let regex = fancy_regex::Regex::new(&source)
+                    .unwrap_or_else(|e| panic!("invalid regex for {:?}: {} ({})", pattern, source, e));
+                for input in *inputs {
+                    assert_eq!(
+                        regex.is_match(input).unwrap(),
+                        is_match_with(input, pattern, &options),
+                        "pattern {:?}, input {:?}, regex {}",
+                        pattern,
+                        input,
+                        source
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_agrees_with_matcher_ignoring_case() {
+        let options = MatchOptions::new().nocase(true);
+        let cases: &[(&str, &[&str])] = &[
+            ("[A-C]*.TXT", &["apple.txt", "Bob.TXT", "dog.txt"]),
+            ("src/**/*.rs", &["SRC/A/Main.RS", "lib/main.rs"]),
+        ];
+        for (pattern, inputs) in cases {
+            let source = to_regex(pattern, &options);
+            let regex = fancy_regex::Regex::new(&source).unwrap();
+            for input in *inputs {
+                assert_eq!(regex.is_match(input).unwrap(), is_match_with(input, pattern, &options));
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_agrees_with_matcher_on_windows_paths() {
+        let options = MatchOptions::new().windows(true);
+        let cases: &[(&str, &[&str])] = &[
+            ("src/**/*.rs", &["src\\a\\b.rs", "src/a\\b.rs", "src\\b.rs", "src\\\\b.rs"]),
+            ("src/*.rs", &["src\\main.rs", "src\\a\\main.rs"]),
+            ("a[/]b", &["a\\b", "a/b"]),
+            ("a[^/]b", &["a\\b", "axb"]),
+            ("a\\b", &["a\\b", "a/b"]),
+            ("@(a|b)/c", &["a\\c", "b/c"]),
+            ("**/*.txt", &["\\\\server\\share\\a.txt", "\\\\\\server\\a.txt", "C:\\\\a.txt"]),
+        ];
+        for (pattern, inputs) in cases {
+            let source = to_regex(pattern, &options);
+            let regex = fancy_regex::Regex::new(&source).unwrap();
+            for input in *inputs {
+                assert_eq!(
+                    regex.is_match(input).unwrap(),
+                    is_match_with(input, pattern, &options),
+                    "pattern {:?}, input {:?}, regex {}",
+                    pattern,
+                    input,
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_shape() {
+        let options = MatchOptions::new().strict_slashes(false).strip_dot_slash(false);
+        assert_eq!(to_regex("a.b", &options), r"^(?:a\.b)$");
+        assert_eq!(to_regex("*.rs", &options), r"^(?:(?!\.)(?=[\s\S])[^/]*\.rs)$");
+        assert_eq!(to_regex("!a", &options), r"^(?!(?:a)$)[\s\S]*$");
+        assert_eq!(to_regex("a", &MatchOptions::new().strip_dot_slash(false)), r"^(?![\s\S]*//)(?:a)$");
+        assert_eq!(to_regex("./a", &MatchOptions::default()), r"^(?:\./)*(?!\./)(?![\s\S]*//)(?:a)$");
+    }
+}
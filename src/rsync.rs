@@ -0,0 +1,293 @@
+//! rsync-style include/exclude filter rules.
+//!
+//! Each line of a rule list is `+ PATTERN` (or `include PATTERN`) to
+//! include, `- PATTERN` (or `exclude PATTERN`) to exclude, or a lone `!`
+//! that clears the rules read so far. Blank lines and lines starting with
+//! `#` or `;` are skipped. The first rule matching a path decides, and a
+//! path no rule matches is included.
+//!
+//! Patterns follow rsync: a leading `/` anchors the pattern to the root,
+//! otherwise it matches the end of the path; a trailing `/` matches only
+//! directories; `*` stays within a path component while `**` crosses
+//! them; and `dir/***` matches `dir` and everything inside it.
+//!
+//! ```
+//! use satch::rsync::FilterRules;
+//!
+//! let rules = FilterRules::parse("+ *.rs\n+ */\n- *").unwrap();
+//! assert!(rules.is_included("src/main.rs", false));
+//! assert!(rules.is_included("src", true));
+//! assert!(!rules.is_included("README.md", false));
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use crate::gitignore::escape_literal_syntax;
+use crate::{MatchOptions, Pattern};
+
+/// Whether a [`FilterRule`] includes or excludes what it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterAction {
+    /// A `+` rule.
+    Include,
+    /// A `-` rule.
+    Exclude,
+}
+
+/// One `+` or `-` line of a rule list.
+#[derive(Debug, Clone)]
+pub struct FilterRule {
+    action: FilterAction,
+    source: String,
+    pattern: Pattern,
+    dir_only: bool,
+}
+
+impl FilterRule {
+    /// Parses the pattern of a rule, such as `/build/` or `*.o`.
+    pub fn new(action: FilterAction, source: &str) -> Self {
+        let (dir_only, body) = match source.strip_suffix('/') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, source),
+        };
+        let (anchored, body) = match body.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, body),
+        };
+
+        let mut glob = escape_literal_syntax(body);
+        // dir/*** はディレクトリ自身とその中身すべてにマッチする
+        if let Some(dir) = glob.strip_suffix("/***") {
+            let dir = escape_commas(dir);
+            glob = format!("{{{dir},{dir}/**}}");
+        }
+        if !anchored {
+            glob.insert_str(0, "**/");
+        }
+
+        Self {
+            action,
+            source: source.to_string(),
+            pattern: Pattern::with_options(&glob, &MatchOptions::new().dot(true)),
+            dir_only,
+        }
+    }
+
+    /// Returns whether the rule includes or excludes.
+    pub fn action(&self) -> FilterAction {
+        self.action
+    }
+
+    /// Returns the pattern as written in the rule list.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns `true` if the rule applies to `path`.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && self.pattern.matches_path(path)
+    }
+}
+
+/// Escapes the unescaped `,` in `glob`, so it can be an alternative of a
+/// brace group.
+fn escape_commas(glob: &str) -> String {
+    let mut escaped = String::with_capacity(glob.len());
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                if let Some(next) = chars.next() {
+                    escaped.push(next);
+                }
+            }
+            ',' => escaped.push_str("\\,"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An ordered rule list evaluated with first-match-wins semantics.
+#[derive(Debug, Clone, Default)]
+pub struct FilterRules {
+    rules: Vec<FilterRule>,
+}
+
+/// A line of a rule list that is not a valid rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterRulesError {
+    line: usize,
+    text: String,
+}
+
+impl FilterRulesError {
+    /// Returns the 1-based number of the offending line.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the text of the offending line.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for FilterRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid filter rule on line {}: {}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for FilterRulesError {}
+
+impl FilterRules {
+    /// Creates an empty rule list, which includes every path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a rule list, rejecting lines that are not rules.
+    pub fn parse(contents: &str) -> Result<Self, FilterRulesError> {
+        let mut rules = Self::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line == "!" {
+                rules.rules.clear();
+                continue;
+            }
+            let rule = parse_rule(line).ok_or_else(|| FilterRulesError {
+                line: index + 1,
+                text: line.to_string(),
+            })?;
+            rules.rules.push(rule);
+        }
+        Ok(rules)
+    }
+
+    /// Appends a rule that includes paths matching `pattern`.
+    pub fn include(&mut self, pattern: &str) -> &mut Self {
+        self.rules.push(FilterRule::new(FilterAction::Include, pattern));
+        self
+    }
+
+    /// Appends a rule that excludes paths matching `pattern`.
+    pub fn exclude(&mut self, pattern: &str) -> &mut Self {
+        self.rules.push(FilterRule::new(FilterAction::Exclude, pattern));
+        self
+    }
+
+    /// Returns the rules in order.
+    pub fn rules(&self) -> &[FilterRule] {
+        &self.rules
+    }
+
+    /// Returns the first rule matching `path` itself, if any.
+    pub fn decide(&self, path: impl AsRef<Path>, is_dir: bool) -> Option<&FilterRule> {
+        let path = path.as_ref();
+        self.rules.iter().find(|rule| rule.matches(path, is_dir))
+    }
+
+    /// Returns `true` if `path` is included.
+    ///
+    /// As rsync never descends into an excluded directory, a path inside
+    /// one is excluded too.
+    pub fn is_included(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        let path = path.as_ref();
+        let mut ancestors: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .collect();
+        // rsync と同じく上のディレクトリから順に判定する
+        ancestors.reverse();
+        let excluded = |path: &Path, is_dir| {
+            self.decide(path, is_dir)
+                .is_some_and(|rule| rule.action == FilterAction::Exclude)
+        };
+        !ancestors.into_iter().any(|ancestor| excluded(ancestor, true)) && !excluded(path, is_dir)
+    }
+}
+
+/// Parses a `+`/`-` line or its long form.
+fn parse_rule(line: &str) -> Option<FilterRule> {
+    let (action, pattern) = if let Some(pattern) = line.strip_prefix("+ ") {
+        (FilterAction::Include, pattern)
+    } else if let Some(pattern) = line.strip_prefix("- ") {
+        (FilterAction::Exclude, pattern)
+    } else if let Some(pattern) = line.strip_prefix("include ") {
+        (FilterAction::Include, pattern)
+    } else if let Some(pattern) = line.strip_prefix("exclude ") {
+        (FilterAction::Exclude, pattern)
+    } else {
+        return None;
+    };
+    (!pattern.is_empty()).then(|| FilterRule::new(action, pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = FilterRules::parse("# comment\n; also\n\n+ *.rs\nexclude target/\n").unwrap();
+        assert_eq!(rules.rules().len(), 2);
+        assert_eq!(rules.rules()[1].action(), FilterAction::Exclude);
+        assert_eq!(rules.rules()[1].as_str(), "target/");
+
+        let error = FilterRules::parse("+ a\n* b\n").unwrap_err();
+        assert_eq!(error.line(), 2);
+        assert_eq!(error.to_string(), "invalid filter rule on line 2: * b");
+        // ! はそれまでのルールを消去する
+        assert!(FilterRules::parse("- *\n!\n").unwrap().rules().is_empty());
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let rules = FilterRules::parse("+ keep.log\n- *.log\n").unwrap();
+        assert!(rules.is_included("logs/keep.log", false));
+        assert!(!rules.is_included("logs/a.log", false));
+        assert!(rules.is_included("a.txt", false));
+        assert_eq!(rules.decide("x/a.log", false).unwrap().as_str(), "*.log");
+        assert!(rules.decide("a.txt", false).is_none());
+    }
+
+    #[test]
+    fn test_anchoring_and_directories() {
+        let mut rules = FilterRules::new();
+        rules.exclude("/build/").exclude("cache/tmp").exclude("vendor/***");
+        assert!(!rules.is_included("build", true));
+        assert!(!rules.is_included("build/out.o", false));
+        assert!(rules.is_included("build", false));
+        assert!(rules.is_included("src/build/a", false));
+        assert!(!rules.is_included("a/cache/tmp", false));
+        assert!(!rules.is_included("vendor", true));
+        assert!(!rules.is_included("x/vendor/lib.c", false));
+
+        // ディレクトリ名の , はブレースの区切りにならない
+        let mut rules = FilterRules::new();
+        rules.exclude("a,b/***").exclude("/[,]x/***");
+        assert!(rules.is_included("a", false));
+        assert!(rules.is_included("b", false));
+        assert!(!rules.is_included("a,b", true));
+        assert!(!rules.is_included("src/a,b/c", false));
+        assert!(!rules.is_included(",x/y", false));
+        assert!(rules.is_included("x/y", false));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let rules = FilterRules::parse("- /src/*.o\n- /doc/**.bak\n- {a}\n").unwrap();
+        assert!(!rules.is_included("src/main.o", false));
+        assert!(rules.is_included("src/sub/main.o", false));
+        assert!(!rules.is_included("doc/a/b/c.bak", false));
+        assert!(!rules.is_included("{a}", false));
+        assert!(!rules.is_included(".hidden/{a}", false));
+    }
+}
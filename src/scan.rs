@@ -0,0 +1,221 @@
+//! Static analysis of glob patterns.
+
+use crate::brace::find_brace_group;
+use crate::delimiters::Delimiters;
+use crate::split_negation;
+
+/// What [`scan`] found out about a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanResult {
+    /// The leading directories that contain no glob syntax (`src/lib` in
+    /// `src/lib/**/*.rs`). The whole pattern when it has no glob syntax.
+    pub base: String,
+    /// The remainder of the pattern after `base` and its separator.
+    pub glob: String,
+    /// Whether the pattern starts with a `!` negation.
+    pub negated: bool,
+    /// Whether the pattern contains any glob syntax.
+    pub is_glob: bool,
+    /// Whether the pattern contains a `**` globstar.
+    pub is_globstar: bool,
+    /// Whether the pattern contains an expandable brace group.
+    pub is_brace: bool,
+    /// Whether the pattern contains a character class.
+    pub is_bracket: bool,
+    /// Whether the pattern contains an extglob.
+    pub is_extglob: bool,
+}
+
+/// Splits a pattern into its static base directory and glob portion and
+/// reports which glob features it uses.
+///
+/// A filesystem walk only needs to start at `base`.
+///
+/// ```
+/// use satch::scan;
+///
+/// let result = scan("!src/lib/**/*.{js,ts}");
+/// assert_eq!(result.base, "src/lib");
+/// assert_eq!(result.glob, "**/*.{js,ts}");
+/// assert!(result.negated && result.is_globstar && result.is_brace);
+/// ```
+pub fn scan(pattern: &str) -> ScanResult {
+    let (negated, body) = split_negation(pattern);
+    let chars: Vec<char> = body.chars().collect();
+
+    let brace_start = find_brace_group(&chars).map(|(open, _)| open);
+    let delimiters = Delimiters::new(&chars);
+    let mut first_magic = brace_start;
+    let mut is_bracket = false;
+    let mut is_extglob = false;
+    let mut is_globstar = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let is_magic = match chars[i] {
+            '\\' => {
+                // エスケープされた文字はglobの構文ではない
+                i += 2;
+                continue;
+            }
+            _ if delimiters.extglob(i).is_some() => {
+                is_extglob = true;
+                true
+            }
+            '*' => {
+                is_globstar |= chars.get(i + 1) == Some(&'*');
+                true
+            }
+            '?' => true,
+            '[' if delimiters.class_end(i).is_some() => {
+                is_bracket = true;
+                true
+            }
+            _ => false,
+        };
+        if is_magic && first_magic.is_none_or(|first| i < first) {
+            first_magic = Some(i);
+        }
+        i += 1;
+    }
+
+    let (base, glob) = match first_magic {
+        None => (body.to_string(), String::new()),
+        Some(magic) => match chars[..magic].iter().rposition(|&c| c == '/') {
+            // ルート直下のパターンでは / を基点として残す
+            Some(0) => ("/".to_string(), chars[1..].iter().collect()),
+            Some(slash) => (chars[..slash].iter().collect(), chars[slash + 1..].iter().collect()),
+            None => (String::new(), body.to_string()),
+        },
+    };
+
+    ScanResult {
+        base,
+        glob,
+        negated,
+        is_glob: first_magic.is_some(),
+        is_globstar,
+        is_brace: brace_start.is_some(),
+        is_bracket,
+        is_extglob,
+    }
+}
+
+/// Returns `true` if `pattern` has no glob syntax and no negation, so it
+/// matches only its own text with escapes removed, and a plain string
+/// comparison can replace the matcher.
+///
+/// Characters that only look like glob syntax, such as an unclosed `[` or
+/// a `{a}` without alternatives, are literal text. A pattern containing
+/// `//` is not static, since it matches nothing under the default
+/// [`strict_slashes`](crate::MatchOptions::strict_slashes), and neither is
+/// one starting with `./`, which also matches the input without it under
+/// the default [`strip_dot_slash`](crate::MatchOptions::strip_dot_slash).
+///
+/// ```
+/// use satch::is_static;
+///
+/// assert!(is_static("src/main.rs"));
+/// assert!(is_static("weird\\[file\\].txt"));
+/// assert!(!is_static("src/*.rs"));
+/// assert!(!is_static("!src/main.rs"));
+/// assert!(!is_static("src//main.rs"));
+/// assert!(!is_static("./src/main.rs"));
+/// ```
+pub fn is_static(pattern: &str) -> bool {
+    let result = scan(pattern);
+    !result.negated && !result.is_glob && !has_double_slash(pattern) && !unescaped(pattern).take(2).eq(['.', '/'])
+}
+
+/// Returns `true` if `pattern` contains `//` once escapes are removed.
+fn has_double_slash(pattern: &str) -> bool {
+    let mut previous_slash = false;
+    for c in unescaped(pattern) {
+        if c == '/' && previous_slash {
+            return true;
+        }
+        previous_slash = c == '/';
+    }
+    false
+}
+
+/// Yields the characters of `pattern` with escapes removed.
+fn unescaped(pattern: &str) -> impl Iterator<Item = char> + '_ {
+    let mut chars = pattern.chars();
+    // エスケープされた / も区切りとして比べられる
+    std::iter::from_fn(move || chars.next().map(|c| if c == '\\' { chars.next().unwrap_or(c) } else { c }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(pattern: &str) -> (String, String) {
+        let result = scan(pattern);
+        (result.base, result.glob)
+    }
+
+    #[test]
+    fn test_scan_splits_base_and_glob() {
+        assert_eq!(split("src/lib/*.rs"), ("src/lib".into(), "*.rs".into()));
+        assert_eq!(split("*.rs"), ("".into(), "*.rs".into()));
+        assert_eq!(split("src/main.rs"), ("src/main.rs".into(), "".into()));
+        assert_eq!(split("/usr/*.h"), ("/usr".into(), "*.h".into()));
+        assert_eq!(split("/*.h"), ("/".into(), "*.h".into()));
+        assert_eq!(split("a/b{c,d}/e"), ("a".into(), "b{c,d}/e".into()));
+        assert_eq!(split("a/@(b|c)/d"), ("a".into(), "@(b|c)/d".into()));
+        assert_eq!(split("a/[bc]/d"), ("a".into(), "[bc]/d".into()));
+        assert_eq!(split("a/b\\*/*.rs"), ("a/b\\*".into(), "*.rs".into()));
+    }
+
+    #[test]
+    fn test_scan_literal_syntax_is_not_glob() {
+        let result = scan("a/{b}/[c");
+        assert!(!result.is_glob);
+        assert!(!result.is_brace);
+        assert!(!result.is_bracket);
+        assert_eq!(result.base, "a/{b}/[c");
+    }
+
+    #[test]
+    fn test_scan_flags() {
+        let result = scan("!**/+([0-9]).[jt]s");
+        assert!(result.negated);
+        assert!(result.is_glob);
+        assert!(result.is_globstar);
+        assert!(result.is_extglob);
+        assert!(result.is_bracket);
+        assert!(!result.is_brace);
+        assert_eq!(result.glob, "**/+([0-9]).[jt]s");
+    }
+
+    #[test]
+    fn test_is_static() {
+        assert!(is_static("src/main.rs"));
+        assert!(is_static(""));
+        assert!(is_static("a\\*b"));
+        assert!(is_static("a/{b}/[c"));
+        assert!(!is_static("a?"));
+        assert!(!is_static("a/{b,c}"));
+        assert!(!is_static("a/[bc]"));
+        assert!(!is_static("@(a)"));
+        assert!(!is_static("!a"));
+        assert!(is_static("\\!a"));
+
+        // // を含むパターンは既定の strict_slashes で何にもマッチしない
+        for pattern in ["a//b", "a//", "//a", "a/\\/b"] {
+            assert!(!is_static(pattern), "{}", pattern);
+            assert_eq!(crate::Pattern::new(pattern).literal(), None);
+        }
+        assert!(is_static("a/b/"));
+
+        // 既定の strip_dot_slash では ./a は a にもマッチする
+        for pattern in ["./a", "./", ".\\/a", "././a"] {
+            assert!(!is_static(pattern), "{}", pattern);
+        }
+        assert!(crate::is_match("a", "./a"));
+        assert!(is_static("a/./b"));
+        assert!(!crate::is_match("a/b", "a/./b"));
+        assert!(is_static(".a/b"));
+        assert!(is_static("."));
+    }
+}
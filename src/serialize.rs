@@ -0,0 +1,104 @@
+//! Serde support for patterns and options, behind the `serde` feature.
+//!
+//! A [`Pattern`] serializes as its source and options, and deserializes
+//! from either that form or a bare string compiled with the default
+//! options, so configs can list plain patterns:
+//!
+//! ```
+//! use satch::Pattern;
+//!
+//! let patterns: Vec<Pattern> =
+//!     serde_json::from_str(r#"["src/**/*.rs", {"pattern": "*.MD", "options": {"nocase": true}}]"#).unwrap();
+//! assert!(patterns[0].is_match("src/lib/mod.rs"));
+//! assert!(patterns[1].is_match("readme.md"));
+//! ```
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{is_valid_separator, MatchOptions, Pattern};
+
+#[derive(Serialize)]
+struct PatternRef<'a> {
+    pattern: &'a str,
+    options: &'a MatchOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternRepr {
+    Source(String),
+    Full {
+        pattern: String,
+        #[serde(default)]
+        options: MatchOptions,
+    },
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PatternRef {
+            pattern: self.as_str(),
+            options: self.options(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    /// Compiles the pattern leniently, as [`Pattern::with_options`] does,
+    /// so every serialized pattern reads back.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match PatternRepr::deserialize(deserializer)? {
+            PatternRepr::Source(pattern) => Pattern::new(&pattern),
+            PatternRepr::Full { pattern, options } => Pattern::with_options(&pattern, &options),
+        })
+    }
+}
+
+/// Reads [`MatchOptions::separator`], failing on the characters its builder
+/// method panics on.
+pub(crate) fn deserialize_separator<'de, D: Deserializer<'de>>(deserializer: D) -> Result<char, D::Error> {
+    let separator = char::deserialize(deserializer)?;
+    if !is_valid_separator(separator) {
+        return Err(D::Error::custom(format!("{separator:?} cannot be used as a separator")));
+    }
+    Ok(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_round_trips() {
+        let options = MatchOptions::new().dot(true).max_steps(100);
+        let pattern = Pattern::with_options("src/[abc", &options);
+        let json = serde_json::to_string(&pattern).unwrap();
+        let restored: Pattern = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.as_str(), "src/[abc");
+        assert_eq!(restored.options(), &options);
+        assert!(restored.is_match("src/[abc"));
+    }
+
+    #[test]
+    fn test_options_fill_in_defaults() {
+        let options: MatchOptions = serde_json::from_str(r#"{"windows": true}"#).unwrap();
+        assert_eq!(options, MatchOptions::new().windows(true));
+        let pattern: Pattern = serde_json::from_str(r#"{"pattern": "*.rs"}"#).unwrap();
+        assert_eq!(pattern.options(), &MatchOptions::default());
+        assert!(serde_json::from_str::<Pattern>("42").is_err());
+    }
+
+    #[test]
+    fn test_options_reject_invalid_separators() {
+        let options: MatchOptions = serde_json::from_str(r#"{"separator": "."}"#).unwrap();
+        assert_eq!(options, MatchOptions::new().separator('.'));
+        // 区切り文字のビルダーが受け付けない文字はエラーにする
+        for json in [r#"{"separator": "*"}"#, r#"{"separator": "é"}"#] {
+            let error = serde_json::from_str::<MatchOptions>(json).unwrap_err();
+            assert!(error.to_string().contains("cannot be used as a separator"), "{error}");
+        }
+        assert!(serde_json::from_str::<Pattern>(r#"{"pattern": "a", "options": {"separator": "{"}}"#).is_err());
+    }
+}
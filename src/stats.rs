@@ -0,0 +1,91 @@
+//! Counters describing the work done by the matcher, for diagnosing slow
+//! patterns. Only compiled with the `stats` feature.
+
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// What the matcher did while matching one input.
+///
+/// Counts cover every brace alternative that was run; alternatives ruled
+/// out by their literal prefix or suffix cost nothing and count nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    /// States visited, including those inside extglob alternatives.
+    pub steps: usize,
+    /// Continuations tried at `*`, `**` and extglob branch points.
+    pub branches: usize,
+    /// Branches that were searched and failed, so the next was tried.
+    pub backtracks: usize,
+    /// Branches skipped because the failure memo knew they fail.
+    pub memo_hits: usize,
+    /// Branches the failure memo knew nothing about.
+    pub memo_misses: usize,
+    /// The largest number of memo entries one search allocated.
+    pub peak_memo_size: usize,
+}
+
+impl MatchStats {
+    fn add(&mut self, other: &MatchStats) {
+        self.steps += other.steps;
+        self.branches += other.branches;
+        self.backtracks += other.backtracks;
+        self.memo_hits += other.memo_hits;
+        self.memo_misses += other.memo_misses;
+        self.peak_memo_size = self.peak_memo_size.max(other.peak_memo_size);
+    }
+}
+
+thread_local! {
+    /// このスレッドで記録中の照合の統計
+    static CURRENT: Cell<MatchStats> = Cell::new(MatchStats::default());
+}
+
+/// Adds the counts of one finished search to the match being recorded.
+pub(crate) fn add(stats: &MatchStats) {
+    CURRENT.with(|current| {
+        let mut total = current.get();
+        total.add(stats);
+        current.set(total);
+    });
+}
+
+/// Where a pattern keeps the statistics of its latest match, shared by
+/// its clones.
+///
+/// Listed under `ignore-interior-mutability` in `clippy.toml`: patterns
+/// hash by their normalized source and options, never by these statistics.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct StatsSlot(Arc<Mutex<Option<MatchStats>>>);
+
+impl StatsSlot {
+    pub(crate) fn get(&self) -> Option<MatchStats> {
+        *self.lock()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<MatchStats>> {
+        // 統計は診断用なので、ロックが汚染されていても使い続ける
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Records the statistics of one match into `slot` when dropped.
+pub(crate) struct Recorder<'a> {
+    slot: &'a StatsSlot,
+    /// 入れ子の照合（GlobSet から Pattern を呼ぶなど）で外側の集計を壊さないよう退避する
+    outer: MatchStats,
+}
+
+impl<'a> Recorder<'a> {
+    pub(crate) fn new(slot: &'a StatsSlot) -> Self {
+        let outer = CURRENT.with(|current| current.replace(MatchStats::default()));
+        Self { slot, outer }
+    }
+}
+
+impl Drop for Recorder<'_> {
+    fn drop(&mut self) {
+        let stats = CURRENT.with(|current| current.replace(self.outer));
+        add(&stats);
+        *self.slot.lock() = Some(stats);
+    }
+}
@@ -0,0 +1,76 @@
+//! Splicing captured wildcard text into target paths, for glob-based
+//! renames.
+
+/// Replaces each `{N}` in `template` with the `N`th of `captures`,
+/// counting from 1, or returns `None` if `template` refers to a capture
+/// that is not there.
+///
+/// Write `{{` and `}}` for literal braces. Other braced text, such as
+/// `{a,b}`, is copied unchanged. Together with [`Pattern::captures`](crate::Pattern::captures),
+/// this maps each matching path to a new one.
+///
+/// ```
+/// use satch::{capture, expand_template};
+///
+/// let captures = capture("src/**/*.js", "src/lib/util.js").unwrap();
+/// assert_eq!(expand_template(&captures, "dist/{1}/{2}.min.js"), Some("dist/lib/util.min.js".to_string()));
+/// assert_eq!(expand_template(&captures, "{3}.js"), None);
+/// ```
+pub fn expand_template<S: AsRef<str>>(captures: &[S], template: &str) -> Option<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(brace) = rest.find(['{', '}']) {
+        expanded.push_str(&rest[..brace]);
+        rest = &rest[brace..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            expanded.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        // {数字} だけを置き換え、それ以外の波括弧はそのまま残す
+        let index = rest
+            .strip_prefix('{')
+            .and_then(|inner| inner.split_once('}'))
+            .filter(|(digits, _)| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()));
+        match index {
+            Some((digits, after)) => {
+                let capture = digits.parse::<usize>().ok()?.checked_sub(1).and_then(|n| captures.get(n))?;
+                expanded.push_str(capture.as_ref());
+                rest = after;
+            }
+            None => {
+                expanded.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Some(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        let captures = ["lib/util", "a"];
+        assert_eq!(expand_template(&captures, "dist/{1}/{2}.js").as_deref(), Some("dist/lib/util/a.js"));
+        assert_eq!(expand_template(&captures, "{2}{2}-{1}").as_deref(), Some("aa-lib/util"));
+        assert_eq!(expand_template(&captures, "out.txt").as_deref(), Some("out.txt"));
+        assert_eq!(expand_template(&captures, "{{1}}-{1}").as_deref(), Some("{1}-lib/util"));
+        // 番号でない波括弧はそのまま
+        assert_eq!(expand_template(&captures, "{a,b}/{}/{1").as_deref(), Some("{a,b}/{}/{1"));
+        assert_eq!(expand_template(&captures, "x}").as_deref(), Some("x}"));
+        assert_eq!(expand_template(&["\u{e9}"], "{1}\u{e9}").as_deref(), Some("\u{e9}\u{e9}"));
+    }
+
+    #[test]
+    fn test_missing_capture() {
+        let captures = ["a"];
+        assert_eq!(expand_template(&captures, "{0}"), None);
+        assert_eq!(expand_template(&captures, "{2}"), None);
+        assert_eq!(expand_template(&captures, "{99999999999999999999999}"), None);
+        assert_eq!(expand_template::<&str>(&[], "{1}"), None);
+    }
+}
@@ -0,0 +1,578 @@
+//! Walking the filesystem for paths that match a pattern.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::gitignore::GitignoreMatcher;
+use crate::pattern::normalize_path;
+use crate::{Candidate, Pattern};
+
+/// Ignore files read in each directory when [`WalkBuilder::ignore_files`]
+/// is enabled. Later files take precedence.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Returns the paths under the current directory that match `pattern`,
+/// like the `glob` crate but with satch's matching rules.
+///
+/// Paths are yielded relative to the current directory (`src/main.rs`,
+/// not `./src/main.rs`), depth first with the entries of each directory
+/// sorted by name. Matching directories are yielded as well as files.
+/// Symbolic links are reported but not followed. Errors reading a
+/// directory are yielded and the walk carries on.
+///
+/// ```no_run
+/// for path in satch::glob("src/**/*.rs") {
+///     println!("{}", path?.display());
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn glob(pattern: &str) -> Walk {
+    WalkBuilder::new("")
+        .pattern(Pattern::new(pattern))
+        .hidden(false)
+        .ignore_files(false)
+        .build()
+}
+
+/// Configures a directory walk, in the spirit of the `ignore` crate.
+///
+/// By default the walk skips hidden entries, honors `.gitignore` and
+/// `.ignore` files, does not follow symbolic links and has no depth limit.
+/// Yielded paths start with the root; patterns are matched against the
+/// path relative to it. The walk only enters the directories named by the
+/// pattern's [literal prefix](Pattern::literal_prefix), so `src/**/*.rs`
+/// never reads anything outside `src`, and skips directories that
+/// [cannot contain a match](Pattern::could_match_dir). Patterns ending in
+/// `/` match only directories, as in [`Pattern::is_match_entry`].
+///
+/// ```no_run
+/// use satch::{Pattern, WalkBuilder};
+///
+/// let walk = WalkBuilder::new("src")
+///     .pattern(Pattern::new("**/*.rs"))
+///     .max_depth(Some(2))
+///     .build();
+/// for path in walk {
+///     println!("{}", path?.display());
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WalkBuilder {
+    root: PathBuf,
+    pattern: Option<Pattern>,
+    max_depth: Option<usize>,
+    min_depth: Option<usize>,
+    follow_links: bool,
+    hidden: bool,
+    ignore_files: bool,
+}
+
+impl WalkBuilder {
+    /// Starts a walk of the directory `root`.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            pattern: None,
+            max_depth: None,
+            min_depth: None,
+            follow_links: false,
+            hidden: true,
+            ignore_files: true,
+        }
+    }
+
+    /// Yields only the paths matching `pattern`. Without a pattern every
+    /// entry is yielded.
+    pub fn pattern(&mut self, pattern: Pattern) -> &mut Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Limits how deep the walk descends. The entries of the root are at
+    /// depth 1, so `Some(1)` lists the root without recursing.
+    pub fn max_depth(&mut self, depth: Option<usize>) -> &mut Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Skips entries shallower than `depth`. They are still descended into,
+    /// so `Some(2)` yields everything except the entries of the root.
+    pub fn min_depth(&mut self, depth: Option<usize>) -> &mut Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Descends into symbolic links to directories. Disabled by default.
+    ///
+    /// A link back to one of its own ancestors is not descended into;
+    /// the walk yields an error for it and carries on.
+    pub fn follow_links(&mut self, yes: bool) -> &mut Self {
+        self.follow_links = yes;
+        self
+    }
+
+    /// Skips entries whose name starts with `.`. Enabled by default.
+    pub fn hidden(&mut self, yes: bool) -> &mut Self {
+        self.hidden = yes;
+        self
+    }
+
+    /// Skips entries excluded by `.gitignore` and `.ignore` files found
+    /// along the way. A file's rules apply to its directory and everything
+    /// below it, and rules from deeper files take precedence. Enabled by
+    /// default.
+    pub fn ignore_files(&mut self, yes: bool) -> &mut Self {
+        self.ignore_files = yes;
+        self
+    }
+
+    /// Starts the walk.
+    pub fn build(&self) -> Walk {
+        let mut walk = Walk {
+            config: self.clone(),
+            prefix: self.pattern.as_ref().map_or_else(PathBuf::new, walk_prefix),
+            stack: Vec::new(),
+        };
+        // ルートの識別子が取れない場合は read_dir がエラーを報告する
+        let ancestors = if self.follow_links {
+            file_id(&walk.dir_path(Path::new(""))).ok().map(|id| Arc::new(Ancestor { id, parent: None }))
+        } else {
+            None
+        };
+        walk.push_children(Path::new(""), 1, None, ancestors);
+        walk
+    }
+}
+
+/// An iterator over the paths found by [`glob`] or [`WalkBuilder::build`].
+#[derive(Debug)]
+pub struct Walk {
+    config: WalkBuilder,
+    /// パターンのリテラル前置部分。この中だけを走査する
+    prefix: PathBuf,
+    /// 未処理のエントリ。末尾から取り出す
+    stack: Vec<io::Result<Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// root からの相対パス
+    relative: PathBuf,
+    is_dir: bool,
+    depth: usize,
+    /// このエントリの子に適用される ignore ルール
+    scope: Option<Arc<IgnoreScope>>,
+    /// このエントリを含むディレクトリの連鎖。リンクを辿る場合のみ記録する
+    ancestors: Option<Arc<Ancestor>>,
+}
+
+/// Identifies a directory independently of the path used to reach it.
+#[cfg(unix)]
+type FileId = (u64, u64);
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+/// Returns the device and inode of the directory at `path`, following links.
+#[cfg(unix)]
+fn file_id(path: &Path) -> io::Result<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+/// Returns the canonical form of `path`, which identifies it on platforms
+/// without inode numbers.
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> io::Result<FileId> {
+    fs::canonicalize(path)
+}
+
+/// A directory on the path from the root to the current entry.
+#[derive(Debug)]
+struct Ancestor {
+    id: FileId,
+    parent: Option<Arc<Ancestor>>,
+}
+
+impl Ancestor {
+    fn contains(ancestor: &Option<Arc<Ancestor>>, id: &FileId) -> bool {
+        let mut current = ancestor.as_deref();
+        while let Some(ancestor) = current {
+            if ancestor.id == *id {
+                return true;
+            }
+            current = ancestor.parent.as_deref();
+        }
+        false
+    }
+}
+
+/// The ignore rules of one directory, chained to those of its ancestors.
+#[derive(Debug)]
+struct IgnoreScope {
+    dir: PathBuf,
+    matcher: GitignoreMatcher,
+    parent: Option<Arc<IgnoreScope>>,
+}
+
+impl IgnoreScope {
+    /// Returns `true` if the innermost scope with a matching rule ignores `relative`.
+    fn ignores(scope: &Option<Arc<IgnoreScope>>, relative: &Path, is_dir: bool) -> bool {
+        let mut current = scope.as_deref();
+        while let Some(scope) = current {
+            let path = relative.strip_prefix(&scope.dir).unwrap_or(relative);
+            if let Some(ignored) = scope.matcher.matched(path, is_dir) {
+                return ignored;
+            }
+            current = scope.parent.as_deref();
+        }
+        false
+    }
+}
+
+impl Walk {
+    /// Returns the path to read for the directory at `relative`.
+    fn dir_path(&self, relative: &Path) -> PathBuf {
+        let dir = self.config.root.join(relative);
+        // 空のパスはカレントディレクトリを指す
+        if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        }
+    }
+
+    /// While `relative` is a proper ancestor of the walk's prefix, returns
+    /// the name of the next directory in the prefix.
+    fn prefix_component(&self, relative: &Path) -> Option<OsString> {
+        let rest = self.prefix.strip_prefix(relative).ok()?;
+        rest.components().next().map(|component| component.as_os_str().to_os_string())
+    }
+
+    /// Queues the entries of the directory at `relative`, sorted by name.
+    fn push_children(
+        &mut self,
+        relative: &Path,
+        depth: usize,
+        scope: Option<Arc<IgnoreScope>>,
+        ancestors: Option<Arc<Ancestor>>,
+    ) {
+        let dir = self.dir_path(relative);
+        let entries: Vec<io::Result<(OsString, fs::FileType)>> = match self.prefix_component(relative) {
+            // 前置部分の途中ではディレクトリ全体を読まずに次の要素だけを調べる
+            Some(name) => match fs::symlink_metadata(dir.join(&name)) {
+                Ok(metadata) => vec![Ok((name, metadata.file_type()))],
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(error) => vec![Err(error)],
+            },
+            None => match fs::read_dir(&dir) {
+                Ok(entries) => entries
+                    .map(|entry| entry.and_then(|entry| Ok((entry.file_name(), entry.file_type()?))))
+                    .collect(),
+                Err(error) => {
+                    self.stack.push(Err(error));
+                    return;
+                }
+            },
+        };
+
+        let scope = if self.config.ignore_files {
+            load_ignore_scope(&dir, relative, scope)
+        } else {
+            scope
+        };
+
+        let mut children = Vec::new();
+        for entry in entries {
+            let child = entry.map(|(name, file_type)| {
+                if self.config.hidden && name.to_string_lossy().starts_with('.') {
+                    return None;
+                }
+                let is_dir = if file_type.is_symlink() && self.config.follow_links {
+                    // リンク先が読めない場合はファイルとして扱う
+                    fs::metadata(dir.join(&name)).is_ok_and(|metadata| metadata.is_dir())
+                } else {
+                    file_type.is_dir()
+                };
+                let relative = relative.join(name);
+                if IgnoreScope::ignores(&scope, &relative, is_dir) {
+                    return None;
+                }
+                Some(Entry {
+                    relative,
+                    is_dir,
+                    depth,
+                    scope: scope.clone(),
+                    ancestors: ancestors.clone(),
+                })
+            });
+            match child {
+                Ok(Some(entry)) => children.push(Ok(entry)),
+                Ok(None) => {}
+                Err(error) => children.push(Err(error)),
+            }
+        }
+
+        children.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.relative.cmp(&b.relative),
+            // エラーは先に報告する
+            (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+            (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+        });
+        self.stack.extend(children.into_iter().rev());
+    }
+
+    /// Returns `false` when no path below the directory `entry` can match.
+    fn could_contain_match(&self, entry: &Entry) -> bool {
+        match &self.config.pattern {
+            Some(pattern) => pattern.could_match_dir(&normalize_path(&entry.relative)),
+            None => true,
+        }
+    }
+
+    /// Queues the children of the directory `entry`, unless following links
+    /// would enter one of its ancestors again.
+    fn descend(&mut self, entry: &Entry) {
+        if !self.config.follow_links {
+            self.push_children(&entry.relative, entry.depth + 1, entry.scope.clone(), None);
+            return;
+        }
+        let path = self.config.root.join(&entry.relative);
+        let id = match file_id(&path) {
+            Ok(id) => id,
+            Err(error) => {
+                self.stack.push(Err(error));
+                return;
+            }
+        };
+        if Ancestor::contains(&entry.ancestors, &id) {
+            // 祖先へのリンクを辿ると無限に再帰する
+            let message = format!("filesystem loop detected at {}", path.display());
+            self.stack.push(Err(io::Error::other(message)));
+            return;
+        }
+        let ancestors = Some(Arc::new(Ancestor {
+            id,
+            parent: entry.ancestors.clone(),
+        }));
+        self.push_children(&entry.relative, entry.depth + 1, entry.scope.clone(), ancestors);
+    }
+}
+
+/// Returns the directories every match of `pattern` lies under, or an empty
+/// path when the literal prefix is not a plain relative path.
+fn walk_prefix(pattern: &Pattern) -> PathBuf {
+    let mut prefix = PathBuf::from(pattern.literal_prefix());
+    // build/ のようなパターンは前置部分のディレクトリそのものにマッチする
+    if pattern.literal().is_some_and(|literal| literal.ends_with('/')) {
+        prefix.pop();
+    }
+    if prefix.components().all(|component| matches!(component, Component::Normal(_))) {
+        prefix
+    } else {
+        PathBuf::new()
+    }
+}
+
+/// Reads the ignore files in `dir`, returning the scope for its entries.
+fn load_ignore_scope(dir: &Path, relative: &Path, parent: Option<Arc<IgnoreScope>>) -> Option<Arc<IgnoreScope>> {
+    let mut matcher = GitignoreMatcher::default();
+    for name in IGNORE_FILES {
+        if let Ok(contents) = fs::read_to_string(dir.join(name)) {
+            matcher.add_lines(&contents);
+        }
+    }
+    if matcher.is_empty() {
+        return parent;
+    }
+    Some(Arc::new(IgnoreScope {
+        dir: relative.to_path_buf(),
+        matcher,
+        parent,
+    }))
+}
+
+impl Iterator for Walk {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.stack.pop() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => return Some(Err(error)),
+            };
+            let within_depth = self.config.max_depth.is_none_or(|max| entry.depth < max);
+            if entry.is_dir && within_depth && self.could_contain_match(&entry) {
+                self.descend(&entry);
+            }
+            if self.config.min_depth.is_some_and(|min| entry.depth < min) {
+                continue;
+            }
+            let matched = match &self.config.pattern {
+                Some(pattern) => pattern.is_match_candidate(&Candidate::from_path(&entry.relative).dir(entry.is_dir)),
+                None => true,
+            };
+            if matched {
+                return Some(Ok(self.config.root.join(entry.relative)));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a fresh directory tree under the system temp directory.
+    fn make_tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("satch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (file, contents) in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn relative_paths(root: &Path, walk: &mut WalkBuilder) -> Vec<String> {
+        walk.build()
+            .map(|path| path.unwrap().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_matches_files_and_directories() {
+        let root = make_tree(
+            "walk",
+            &[("src/main.rs", ""), ("src/lib/mod.rs", ""), ("src/lib/util.js", ""), ("README.md", "")],
+        );
+        let matches = |pattern| relative_paths(&root, WalkBuilder::new(&root).pattern(Pattern::new(pattern)));
+        assert_eq!(matches("**/*.rs"), ["src/lib/mod.rs", "src/main.rs"]);
+        assert_eq!(matches("src/*"), ["src/lib", "src/main.rs"]);
+        assert_eq!(matches("*.md"), ["README.md"]);
+        assert!(matches("*.toml").is_empty());
+        assert_eq!(matches("*/"), ["src"]);
+        assert_eq!(matches("**/lib/"), ["src/lib"]);
+        assert_eq!(matches("src/lib/"), ["src/lib"]);
+        assert!(matches("src/main.rs/").is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_reports_missing_root() {
+        let root = std::env::temp_dir().join(format!("satch-missing-{}", std::process::id()));
+        let mut walk = WalkBuilder::new(root).build();
+        assert!(walk.next().unwrap().is_err());
+        assert!(walk.next().is_none());
+    }
+
+    #[test]
+    fn test_walk_hidden_and_depth() {
+        let root = make_tree("walk-hidden", &[(".env", ""), ("a/b/c.txt", ""), ("a/.cache/d.txt", "")]);
+        let mut walk = WalkBuilder::new(&root);
+        assert_eq!(relative_paths(&root, &mut walk), ["a", "a/b", "a/b/c.txt"]);
+        walk.hidden(false);
+        assert_eq!(relative_paths(&root, &mut walk), [".env", "a", "a/.cache", "a/.cache/d.txt", "a/b", "a/b/c.txt"]);
+        walk.hidden(true).max_depth(Some(2));
+        assert_eq!(relative_paths(&root, &mut walk), ["a", "a/b"]);
+        walk.min_depth(Some(2));
+        assert_eq!(relative_paths(&root, &mut walk), ["a/b"]);
+        walk.max_depth(None).min_depth(Some(3));
+        assert_eq!(relative_paths(&root, &mut walk), ["a/b/c.txt"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_honors_ignore_files() {
+        let root = make_tree(
+            "walk-ignore",
+            &[
+                (".gitignore", "target/\n*.log\n"),
+                ("target/out.txt", ""),
+                ("a.log", ""),
+                ("src/.ignore", "!keep.log\n"),
+                ("src/keep.log", ""),
+                ("src/drop.log", ""),
+                ("src/main.rs", ""),
+            ],
+        );
+        let mut walk = WalkBuilder::new(&root);
+        assert_eq!(relative_paths(&root, &mut walk), ["src", "src/keep.log", "src/main.rs"]);
+        walk.ignore_files(false);
+        assert_eq!(
+            relative_paths(&root, &mut walk),
+            ["a.log", "src", "src/drop.log", "src/keep.log", "src/main.rs", "target", "target/out.txt"]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_starts_at_literal_prefix() {
+        let root = make_tree(
+            "walk-prefix",
+            &[(".gitignore", "*.gen.rs\n"), ("src/lib/a.rs", ""), ("src/lib/b.gen.rs", ""), ("src/main.rs", ""), ("lib/c.rs", "")],
+        );
+        let matches = |pattern| relative_paths(&root, WalkBuilder::new(&root).pattern(Pattern::new(pattern)));
+        // 前置部分より上のignoreファイルも適用される
+        assert_eq!(matches("src/lib/*.rs"), ["src/lib/a.rs"]);
+        assert_eq!(matches("src/main.rs"), ["src/main.rs"]);
+        assert!(matches("missing/**/*.rs").is_empty());
+        assert!(matches("src/missing/*.rs").is_empty());
+
+        // {name} のプレースホルダはディレクトリ名ではない
+        let pattern = Pattern::with_options("{dir}/lib/*.rs", &crate::MatchOptions::new().named_captures(true));
+        assert_eq!(relative_paths(&root, WalkBuilder::new(&root).pattern(pattern)), ["src/lib/a.rs"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_prunes_unmatchable_directories() {
+        let root = make_tree("walk-prune", &[("a/x/test/t.rs", ""), ("a/y/src/s.rs", ""), ("b/x/test/u.rs", "")]);
+        let mut walk = WalkBuilder::new(&root);
+        walk.pattern(Pattern::new("*/*/test/*.rs"));
+        assert_eq!(relative_paths(&root, &mut walk), ["a/x/test/t.rs", "b/x/test/u.rs"]);
+
+        // 剪定されたディレクトリは読まれないので、その中のループも報告されない
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("..", root.join("a/y/up")).unwrap();
+            walk.pattern(Pattern::new("*/x/**/*.rs")).follow_links(true);
+            assert_eq!(relative_paths(&root, &mut walk), ["a/x/test/t.rs", "b/x/test/u.rs"]);
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_follow_links_detects_loops() {
+        let root = make_tree("walk-links", &[("a/b.txt", ""), ("target/c.txt", "")]);
+        std::os::unix::fs::symlink("..", root.join("a/up")).unwrap();
+        std::os::unix::fs::symlink("../target", root.join("a/link")).unwrap();
+
+        let mut walk = WalkBuilder::new(&root);
+        assert_eq!(
+            relative_paths(&root, &mut walk),
+            ["a", "a/b.txt", "a/link", "a/up", "target", "target/c.txt"]
+        );
+
+        walk.follow_links(true);
+        let results: Vec<_> = walk.build().collect();
+        let errors: Vec<_> = results.iter().filter_map(|result| result.as_ref().err()).collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("filesystem loop"));
+        let paths: Vec<_> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|path| path.strip_prefix(&root).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(paths, ["a", "a/b.txt", "a/link", "a/link/c.txt", "a/up", "target", "target/c.txt"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
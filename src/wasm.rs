@@ -0,0 +1,207 @@
+//! JavaScript bindings, built with the `wasm` feature.
+//!
+//! Build the `satch-wasm` workspace crate, which links these bindings into
+//! a `cdylib`, with `wasm-pack build satch-wasm`. Options are read from a
+//! plain object with picomatch's camelCase names.
+
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{cache, is_valid_separator, MatchOptions, Pattern};
+
+/// Returns `true` if `input` matches the glob `pattern`, like picomatch's
+/// `isMatch`. `options` may set `dot`, `nocase`, `windows`, `posixSlashes`,
+/// `matchBase` (or `basename`), `strictSlashes`, `strictGlobstar`,
+/// `noglobstar`, `nobrace`, `noext`, `nonegate`, `extendedClasses`,
+/// `graphemes`, `normalizeUnicode`, `namedCaptures`, `classBang`,
+/// `starMatchesEmpty`, `stripDotSlash`, `separator`, `maxLength`,
+/// `maxSegments` and `maxSteps`; other keys, and values of the wrong type,
+/// are ignored. The compiled pattern is cached as in [`crate::is_match`].
+#[wasm_bindgen(js_name = isMatch)]
+pub fn is_match(input: &str, pattern: &str, options: Option<Object>) -> bool {
+    let options = options.as_ref().map_or_else(MatchOptions::default, match_options);
+    cache::with_pattern(pattern, &options, |pattern| pattern.is_match(input))
+}
+
+/// A pattern compiled once for matching many inputs.
+#[wasm_bindgen(js_name = Pattern)]
+pub struct WasmPattern(Pattern);
+
+#[wasm_bindgen(js_class = Pattern)]
+impl WasmPattern {
+    /// Compiles `pattern` with the same options as [`is_match`].
+    #[wasm_bindgen(constructor)]
+    pub fn new(pattern: &str, options: Option<Object>) -> Self {
+        let options = options.as_ref().map_or_else(MatchOptions::default, match_options);
+        Self(Pattern::with_options(pattern, &options))
+    }
+
+    /// Returns `true` if `input` matches the pattern.
+    #[wasm_bindgen(js_name = isMatch)]
+    pub fn is_match(&self, input: &str) -> bool {
+        self.0.is_match(input)
+    }
+}
+
+/// An option value read from the JavaScript options object.
+#[derive(Debug, Clone, PartialEq)]
+enum OptionValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+fn match_options(object: &Object) -> MatchOptions {
+    read_options(|key| {
+        let value = Reflect::get(object, &JsValue::from_str(key)).ok()?;
+        if let Some(yes) = value.as_bool() {
+            Some(OptionValue::Bool(yes))
+        } else if let Some(n) = value.as_f64() {
+            Some(OptionValue::Number(n))
+        } else {
+            value.as_string().map(OptionValue::String)
+        }
+    })
+}
+
+/// Builds the options from `get`, which looks up one key of the options
+/// object.
+fn read_options(get: impl Fn(&str) -> Option<OptionValue>) -> MatchOptions {
+    let flag = |key: &str| match get(key) {
+        Some(OptionValue::Bool(yes)) => Some(yes),
+        _ => None,
+    };
+    // JavaScript の数値は f64 なので、負の値や小数は無視する
+    let limit = |key: &str| match get(key) {
+        Some(OptionValue::Number(n)) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+        _ => None,
+    };
+    // 区切り文字は1文字の文字列で、MatchOptions::separator が受け付けるものだけ使う
+    let separator = || match get("separator") {
+        Some(OptionValue::String(text)) => {
+            let mut chars = text.chars();
+            chars.next().filter(|&c| chars.next().is_none() && is_valid_separator(c))
+        }
+        _ => None,
+    };
+
+    let mut options = MatchOptions::new();
+    if let Some(yes) = flag("dot") {
+        options = options.dot(yes);
+    }
+    if let Some(yes) = flag("nocase") {
+        options = options.nocase(yes);
+    }
+    if let Some(yes) = flag("windows") {
+        options = options.windows(yes);
+    }
+    if let Some(yes) = flag("posixSlashes") {
+        options = options.posix_slashes(yes);
+    }
+    if let Some(yes) = flag("matchBase").or_else(|| flag("basename")) {
+        options = options.match_base(yes);
+    }
+    if let Some(yes) = flag("strictSlashes") {
+        options = options.strict_slashes(yes);
+    }
+    if let Some(yes) = flag("strictGlobstar") {
+        options = options.strict_globstar(yes);
+    }
+    if let Some(yes) = flag("noglobstar") {
+        options = options.noglobstar(yes);
+    }
+    if let Some(yes) = flag("nobrace") {
+        options = options.nobrace(yes);
+    }
+    if let Some(yes) = flag("noext") {
+        options = options.noext(yes);
+    }
+    if let Some(yes) = flag("nonegate") {
+        options = options.nonegate(yes);
+    }
+    if let Some(yes) = flag("extendedClasses") {
+        options = options.extended_classes(yes);
+    }
+    if let Some(yes) = flag("graphemes") {
+        options = options.graphemes(yes);
+    }
+    if let Some(yes) = flag("normalizeUnicode") {
+        options = options.normalize_unicode(yes);
+    }
+    if let Some(yes) = flag("namedCaptures") {
+        options = options.named_captures(yes);
+    }
+    if let Some(yes) = flag("classBang") {
+        options = options.class_bang(yes);
+    }
+    if let Some(yes) = flag("starMatchesEmpty") {
+        options = options.star_matches_empty(yes);
+    }
+    if let Some(yes) = flag("stripDotSlash") {
+        options = options.strip_dot_slash(yes);
+    }
+    if let Some(separator) = separator() {
+        options = options.separator(separator);
+    }
+    if let Some(bytes) = limit("maxLength") {
+        options = options.max_pattern_len(bytes);
+    }
+    if let Some(segments) = limit("maxSegments") {
+        options = options.max_segments(segments);
+    }
+    if let Some(steps) = limit("maxSteps") {
+        options = options.max_steps(steps);
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(entries: &[(&str, OptionValue)]) -> MatchOptions {
+        read_options(|key| entries.iter().find(|(name, _)| *name == key).map(|(_, value)| value.clone()))
+    }
+
+    #[test]
+    fn test_read_options() {
+        assert_eq!(options(&[]), MatchOptions::default());
+        assert_eq!(
+            options(&[("dot", OptionValue::Bool(true)), ("basename", OptionValue::Bool(true))]),
+            MatchOptions::new().dot(true).match_base(true)
+        );
+        assert_eq!(
+            options(&[
+                ("starMatchesEmpty", OptionValue::Bool(false)),
+                ("stripDotSlash", OptionValue::Bool(false)),
+                ("separator", OptionValue::String(":".into())),
+            ]),
+            MatchOptions::new().star_matches_empty(false).strip_dot_slash(false).separator(':')
+        );
+        assert_eq!(
+            options(&[("maxSteps", OptionValue::Number(100.0)), ("maxLength", OptionValue::Number(1.5))]),
+            MatchOptions::new().max_steps(100)
+        );
+    }
+
+    #[test]
+    fn test_read_options_ignores_invalid_values() {
+        for (key, value) in [
+            ("dot", OptionValue::String("true".into())),
+            ("maxSegments", OptionValue::Number(-1.0)),
+            ("maxSteps", OptionValue::Bool(true)),
+            ("separator", OptionValue::String("::".into())),
+            ("separator", OptionValue::String("*".into())),
+            ("separator", OptionValue::String(String::new())),
+            ("separator", OptionValue::Number(58.0)),
+        ] {
+            assert_eq!(options(&[(key, value.clone())]), MatchOptions::default(), "{key}: {value:?}");
+        }
+    }
+
+    #[test]
+    fn test_is_match_without_options() {
+        assert!(is_match("src/main.rs", "src/*.rs", None));
+        assert!(!is_match("src/lib/mod.rs", "src/*.rs", None));
+    }
+}